@@ -1,4 +1,8 @@
 use std::fmt;
+use std::sync::OnceLock;
+
+use chromiumoxide_pdl::pdl::parser::parse_pdl;
+pub use chromiumoxide_pdl::pdl::Protocol;
 
 use crate::cdp::browser_protocol::fetch;
 use crate::cdp::browser_protocol::network::{self, CookieParam, DeleteCookiesParams};
@@ -16,6 +20,21 @@ pub mod revision;
 /// Currently built CDP revision
 pub const CURRENT_REVISION: Revision = Revision(1045489);
 
+/// The js and browser protocol definitions the types were generated from,
+/// parsed on first use
+pub fn protocols() -> &'static [Protocol<'static>] {
+    static PROTOCOLS: OnceLock<Vec<Protocol<'static>>> = OnceLock::new();
+    PROTOCOLS.get_or_init(|| {
+        [
+            include_str!("../js_protocol.pdl"),
+            include_str!("../browser_protocol.pdl"),
+        ]
+        .into_iter()
+        .map(|pdl| parse_pdl(pdl).expect("the types were generated from valid pdl"))
+        .collect()
+    })
+}
+
 /// convenience fixups
 impl Default for CreateTargetParams {
     fn default() -> Self {
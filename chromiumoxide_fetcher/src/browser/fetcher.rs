@@ -1,24 +1,46 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use super::{BrowserFetcherOptions, BrowserFetcherRevisionInfo, BrowserFetcherRuntime};
+use super::options::DEFAULT_HOST;
+use super::progress::ProgressFn;
+use super::{
+    sibling_path, BrowserFetcherOptions, BrowserFetcherRevisionInfo, BrowserFetcherRuntime,
+    FetchLock, RetryPolicy,
+};
+use crate::chrome_for_testing;
 use crate::error::{FetcherError, Result};
-use crate::{Platform, Revision};
+use crate::{Artifact, BrowserVersion, Platform, Revision};
+
+/// The file an installed Chrome for Testing build keeps its revision in
+const REVISION_FILE: &str = "REVISION";
 
 /// A [`BrowserFetcher`] used to download and install a version of chromium.
 pub struct BrowserFetcher {
-    revision: Revision,
+    version: BrowserVersion,
+    artifact: Artifact,
     host: String,
+    json_host: String,
     path: PathBuf,
     platform: Platform,
+    checksum: Option<String>,
+    progress: Option<ProgressFn>,
+    retry: RetryPolicy,
 }
 
 impl BrowserFetcher {
     pub fn new(options: BrowserFetcherOptions) -> Self {
         Self {
-            revision: options.revision,
+            version: options.version,
+            artifact: options.artifact,
             host: options.host,
+            json_host: options.json_host,
             path: options.path,
             platform: options.platform,
+            checksum: options.checksum,
+            progress: options.progress,
+            retry: options.retry,
         }
     }
 
@@ -26,54 +48,636 @@ impl BrowserFetcher {
     /// installed or remotely. If fetching remotely, the method can take a long
     /// time to resolve.
     ///
-    /// This fails if the download or installation fails. The fetcher doesn't
-    /// retry on network errors during download. If the installation fails,
-    /// it might leave the cache in a bad state and it is advised to wipe it.
+    /// Chrome for Testing channels and milestones are resolved to a version
+    /// remotely on every call, exact versions only if they are not installed.
+    ///
+    /// This fails if the download or installation fails. Network errors are
+    /// retried according to the [`RetryPolicy`] of the options, downloads
+    /// resume where the failed attempt stopped. Builds are extracted next to the cache folder and
+    /// only moved into place once complete, concurrent fetches of the same
+    /// build wait for each other.
     ///
     /// If providing a custom host, make sure files are in the same places as
     /// the official builds otherwise the installation will succeed but the runtime
-    /// will fail.
+    /// will fail. [`Platform::LinuxArm64`] builds are only available from
+    /// custom hosts.
     pub async fn fetch(&self) -> Result<BrowserFetcherRevisionInfo> {
-        if !self.local().await {
-            self.download().await?;
+        match self.version {
+            BrowserVersion::Revision(ref revision) => self.fetch_snapshot(revision).await,
+            _ => self.fetch_chrome_for_testing().await,
+        }
+    }
+
+    async fn fetch_snapshot(&self, revision: &Revision) -> Result<BrowserFetcherRevisionInfo> {
+        // there are no official linux arm64 snapshots
+        let no_snapshots = self.platform == Platform::LinuxArm64 && self.host == DEFAULT_HOST;
+        if self.artifact != Artifact::Chrome || no_snapshots {
+            return Err(FetcherError::NoDownload {
+                artifact: self.artifact,
+                version: self.version.to_string(),
+                platform: self.platform,
+            });
+        }
+
+        let folder_path = self.folder_path(self.platform.folder_name(revision));
+        let executable_path = self.platform.executable(&folder_path, revision);
+        if !BrowserFetcherRuntime::is_executable(&executable_path).await {
+            let _lock = self.lock(&folder_path).await?;
+            // another process may have installed it while we waited
+            if !BrowserFetcherRuntime::is_executable(&executable_path).await {
+                let url = self.platform.download_url(&self.host, revision);
+                self.install(&url, &folder_path, None).await?;
+            }
+        }
+
+        Ok(snapshot_info(folder_path, self.platform, revision.clone()))
+    }
+
+    async fn fetch_chrome_for_testing(&self) -> Result<BrowserFetcherRevisionInfo> {
+        // installed exact versions need no resolution
+        if let BrowserVersion::Version(ref version) = self.version {
+            let folder_path = self.chrome_for_testing_folder(version);
+            if let Some(revision) =
+                installed_revision(&folder_path, self.platform, self.artifact).await
+            {
+                return Ok(self.chrome_for_testing_info(folder_path, revision, version));
+            }
+        }
+
+        let endpoint = chrome_for_testing::endpoint(&self.json_host, &self.version);
+        let json = self
+            .retry(|| BrowserFetcherRuntime::get(&endpoint))
+            .await
+            .map_err(FetcherError::ResolveFailed)?;
+        let build =
+            chrome_for_testing::resolve(&json, &self.version, self.platform, self.artifact)?;
+
+        let folder_path = self.chrome_for_testing_folder(&build.version);
+        if !self.is_installed(&folder_path).await {
+            let _lock = self.lock(&folder_path).await?;
+            if !self.is_installed(&folder_path).await {
+                let marker = (REVISION_FILE, build.revision.to_string());
+                self.install(&build.url, &folder_path, Some(marker)).await?;
+            }
+        }
+
+        Ok(self.chrome_for_testing_info(folder_path, build.revision, &build.version))
+    }
+
+    /// Lists the builds installed in the cache for every platform, the latest
+    /// revisions first.
+    ///
+    /// Folders without a runnable executable, e.g. of builds that were only
+    /// partially removed, are skipped.
+    pub async fn installed(&self) -> Result<Vec<BrowserFetcherRevisionInfo>> {
+        let entries = match std::fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(FetcherError::Cache(err)),
+        };
+
+        let mut installed = Vec::new();
+        for entry in entries {
+            let folder_path = entry.map_err(FetcherError::Cache)?.path();
+            if let Some(info) = installed_info(folder_path).await {
+                installed.push(info);
+            }
         }
+        installed.sort_by(|a, b| b.revision.cmp(&a.revision));
+        Ok(installed)
+    }
 
-        Ok(self.revision_info())
+    /// Removes the installed builds of `revision` for every platform and
+    /// returns them
+    pub async fn remove<T: Into<Revision>>(
+        &self,
+        revision: T,
+    ) -> Result<Vec<BrowserFetcherRevisionInfo>> {
+        let revision = revision.into();
+        let removed: Vec<_> = self
+            .installed()
+            .await?
+            .into_iter()
+            .filter(|info| info.revision == revision)
+            .collect();
+        self.uninstall(&removed).await?;
+        Ok(removed)
     }
 
-    async fn local(&self) -> bool {
-        let folder_path = self.folder_path();
-        BrowserFetcherRuntime::exists(&folder_path).await
+    /// Removes all but the `keep_latest` latest installed builds of every
+    /// platform and artifact and returns the removed builds
+    pub async fn prune(&self, keep_latest: usize) -> Result<Vec<BrowserFetcherRevisionInfo>> {
+        let mut kept = HashMap::<_, usize>::new();
+        let removed: Vec<_> = self
+            .installed()
+            .await?
+            .into_iter()
+            .filter(|info| {
+                let count = kept.entry((info.platform, info.artifact)).or_default();
+                *count += 1;
+                *count > keep_latest
+            })
+            .collect();
+        self.uninstall(&removed).await?;
+        Ok(removed)
     }
 
-    async fn download(&self) -> Result<()> {
-        let url = self.platform.download_url(&self.host, &self.revision);
-        let folder_path = self.folder_path();
-        let archive_path = folder_path.with_extension("zip");
+    async fn uninstall(&self, builds: &[BrowserFetcherRevisionInfo]) -> Result<()> {
+        for info in builds {
+            // don't remove a build another process is installing
+            let _lock = self.lock(&info.folder_path).await?;
+            match std::fs::remove_dir_all(&info.folder_path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(FetcherError::Cache(err))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
 
-        BrowserFetcherRuntime::download(&url, &archive_path)
+    async fn is_installed(&self, folder_path: &Path) -> bool {
+        installed_revision(folder_path, self.platform, self.artifact)
             .await
-            .map_err(FetcherError::DownloadFailed)?;
-        BrowserFetcherRuntime::unzip(archive_path, folder_path)
+            .is_some()
+    }
+
+    async fn lock(&self, folder_path: &Path) -> Result<FetchLock> {
+        FetchLock::acquire(sibling_path(folder_path, "lock"))
             .await
-            .map_err(FetcherError::InstallFailed)?;
+            .map_err(|err| FetcherError::InstallFailed(err.into()))
+    }
+
+    /// Downloads the archive at `url`, verifies it and installs it to
+    /// `folder_path` together with the `marker` file
+    async fn install(
+        &self,
+        url: &str,
+        folder_path: &Path,
+        marker: Option<(&'static str, String)>,
+    ) -> Result<()> {
+        let archive_path = sibling_path(folder_path, "zip.part");
+
+        self.retry(|| BrowserFetcherRuntime::download(url, &archive_path, self.progress.as_ref()))
+            .await
+            .map_err(FetcherError::DownloadFailed)?;
+
+        if let Some(ref expected) = self.checksum {
+            let actual = BrowserFetcherRuntime::sha256(archive_path.clone())
+                .await
+                .map_err(FetcherError::DownloadFailed)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                // don't resume a corrupt download
+                let _ = std::fs::remove_file(&archive_path);
+                return Err(FetcherError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        BrowserFetcherRuntime::install(
+            archive_path,
+            folder_path.to_path_buf(),
+            marker,
+            self.progress.clone(),
+        )
+        .await
+        .map_err(FetcherError::InstallFailed)?;
 
         Ok(())
     }
 
-    fn folder_path(&self) -> PathBuf {
+    /// Calls `f` until it succeeds or the retry policy gives up
+    async fn retry<T, F, Fut>(&self, mut f: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match f().await {
+                Err(_) if retry < self.retry.max_retries => {
+                    BrowserFetcherRuntime::sleep(self.retry.backoff(retry)).await;
+                    retry += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn folder_path(&self, name: String) -> PathBuf {
         let mut folder_path = self.path.clone();
-        folder_path.push(self.platform.folder_name(&self.revision));
+        folder_path.push(name);
         folder_path
     }
 
-    fn revision_info(&self) -> BrowserFetcherRevisionInfo {
-        let folder_path = self.folder_path();
-        let executable_path = self.platform.executable(&folder_path, &self.revision);
-        BrowserFetcherRevisionInfo {
-            folder_path,
-            executable_path,
-            revision: self.revision.clone(),
+    fn chrome_for_testing_folder(&self, version: &str) -> PathBuf {
+        self.folder_path(chrome_for_testing::folder_name(
+            self.platform,
+            self.artifact,
+            version,
+        ))
+    }
+
+    fn chrome_for_testing_info(
+        &self,
+        folder_path: PathBuf,
+        revision: Revision,
+        version: &str,
+    ) -> BrowserFetcherRevisionInfo {
+        chrome_for_testing_info(folder_path, self.platform, self.artifact, revision, version)
+    }
+}
+
+fn snapshot_info(
+    folder_path: PathBuf,
+    platform: Platform,
+    revision: Revision,
+) -> BrowserFetcherRevisionInfo {
+    BrowserFetcherRevisionInfo {
+        executable_path: platform.executable(&folder_path, &revision),
+        folder_path,
+        revision,
+        version: None,
+        platform,
+        artifact: Artifact::Chrome,
+    }
+}
+
+fn chrome_for_testing_info(
+    folder_path: PathBuf,
+    platform: Platform,
+    artifact: Artifact,
+    revision: Revision,
+    version: &str,
+) -> BrowserFetcherRevisionInfo {
+    BrowserFetcherRevisionInfo {
+        executable_path: chrome_for_testing::executable(&folder_path, platform, artifact),
+        folder_path,
+        revision,
+        version: Some(version.to_string()),
+        platform,
+        artifact,
+    }
+}
+
+/// The build installed in the cache folder `folder_path`, if it is complete
+async fn installed_info(folder_path: PathBuf) -> Option<BrowserFetcherRevisionInfo> {
+    let name = folder_path.file_name()?.to_str()?;
+    // leftovers of an interrupted installation
+    if name.ends_with(".staging") || !folder_path.is_dir() {
+        return None;
+    }
+
+    if let Some((platform, revision)) = Platform::parse_folder_name(name) {
+        let info = snapshot_info(folder_path, platform, revision);
+        return BrowserFetcherRuntime::is_executable(&info.executable_path)
+            .await
+            .then_some(info);
+    }
+
+    let (platform, artifact, version) = chrome_for_testing::parse_folder_name(name)?;
+    let revision = installed_revision(&folder_path, platform, artifact).await?;
+    Some(chrome_for_testing_info(
+        folder_path,
+        platform,
+        artifact,
+        revision,
+        &version,
+    ))
+}
+
+/// The revision of the Chrome for Testing build installed in `folder_path`,
+/// builds without the revision file or a runnable executable are incomplete.
+async fn installed_revision(
+    folder_path: &Path,
+    platform: Platform,
+    artifact: Artifact,
+) -> Option<Revision> {
+    let executable_path = chrome_for_testing::executable(folder_path, platform, artifact);
+    if !BrowserFetcherRuntime::is_executable(&executable_path).await {
+        return None;
+    }
+    let revision = BrowserFetcherRuntime::read(&folder_path.join(REVISION_FILE))
+        .await
+        .ok()?;
+    Revision::try_from(revision.trim().to_string()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Cursor, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::ZipArchive;
+    use super::*;
+
+    /// Serves `archive` over http, like the snapshot host, and records the
+    /// offset each request resumes from. The first response is cut after
+    /// `cut` bytes, like a dropped connection.
+    fn serve(archive: Vec<u8>, cut: Option<usize>) -> (String, Arc<Mutex<Vec<u64>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let offsets = Arc::new(Mutex::new(Vec::new()));
+        let requests = Arc::clone(&offsets);
+        thread::spawn(move || {
+            let mut cut = cut;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut offset = 0;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.strip_prefix("range: bytes=") {
+                        offset = range.trim_end_matches('-').parse().unwrap();
+                    }
+                }
+                requests.lock().unwrap().push(offset as u64);
+
+                let status = if offset >= archive.len() {
+                    "416 Range Not Satisfiable"
+                } else if offset > 0 {
+                    "206 Partial Content"
+                } else {
+                    "200 OK"
+                };
+                let body = &archive[offset.min(archive.len())..];
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(&body[..cut.take().unwrap_or(body.len())]);
+            }
+        });
+        (host, offsets)
+    }
+
+    /// A snapshot archive of [`Platform::Linux`] with a runnable executable
+    fn archive() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .unix_permissions(0o755);
+        zip.start_file("chrome-linux/chrome", options).unwrap();
+        zip.write_all(&[b'#'; 64 * 1024]).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// An empty cache folder for the test `name`
+    fn cache(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "chromiumoxide-fetcher-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn fetcher(path: &Path, host: &str, checksum: Option<&str>) -> BrowserFetcher {
+        let mut options = BrowserFetcherOptions::builder()
+            .with_revision(1)
+            .with_host(host)
+            .with_path(path)
+            .with_platform(Platform::Linux)
+            .with_retry(RetryPolicy::new(1).with_initial_backoff(Duration::ZERO));
+        if let Some(checksum) = checksum {
+            options = options.with_checksum(checksum);
+        }
+        BrowserFetcher::new(options.build().unwrap())
+    }
+
+    /// Creates a runnable file at `executable_path`
+    fn executable(executable_path: &Path) {
+        fs::create_dir_all(executable_path.parent().unwrap()).unwrap();
+        fs::write(executable_path, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(executable_path, fs::Permissions::from_mode(0o755)).unwrap();
         }
     }
+
+    /// Installs a snapshot build to the cache at `path`, with a folder named
+    /// `name` if it's not the one of the build
+    fn install_snapshot(path: &Path, platform: Platform, revision: u32, name: Option<&str>) {
+        let revision = Revision(revision);
+        let folder_path = path.join(
+            name.map(str::to_string)
+                .unwrap_or_else(|| platform.folder_name(&revision)),
+        );
+        executable(&platform.executable(&folder_path, &revision));
+    }
+
+    /// Installs a Chrome for Testing build of [`Platform::Linux`] to the cache
+    /// at `path`
+    fn install_chrome_for_testing(path: &Path, artifact: Artifact, version: &str, revision: u32) {
+        let folder_path = path.join(chrome_for_testing::folder_name(
+            Platform::Linux,
+            artifact,
+            version,
+        ));
+        executable(&chrome_for_testing::executable(
+            &folder_path,
+            Platform::Linux,
+            artifact,
+        ));
+        fs::write(folder_path.join(REVISION_FILE), revision.to_string()).unwrap();
+    }
+
+    #[cfg(feature = "async-std-runtime")]
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        async_std::task::block_on(fut)
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(fut)
+    }
+
+    #[test]
+    fn sibling_paths_keep_versions_apart() {
+        let path = Path::new("cache").join("chrome-linux64-120.0.6099.71");
+        assert_eq!(
+            sibling_path(&path, "zip.part"),
+            Path::new("cache").join("chrome-linux64-120.0.6099.71.zip.part")
+        );
+        assert_ne!(
+            sibling_path(&path, "lock"),
+            sibling_path(
+                &path.with_file_name("chrome-linux64-120.0.6099.109"),
+                "lock"
+            )
+        );
+    }
+
+    #[test]
+    fn resumes_interrupted_downloads() {
+        let archive = archive();
+        let (host, offsets) = serve(archive.clone(), Some(archive.len() / 2));
+        let path = cache("resume");
+
+        let info = block_on(fetcher(&path, &host, None).fetch()).unwrap();
+        assert!(info.executable_path.is_file());
+        assert_eq!(*offsets.lock().unwrap(), [0, archive.len() as u64 / 2]);
+        assert!(!path.join("linux-1.zip.part").exists());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn removes_archives_that_fail_verification() {
+        let (host, _) = serve(archive(), None);
+        let path = cache("checksum");
+
+        let err = block_on(fetcher(&path, &host, Some("00")).fetch()).unwrap_err();
+        assert!(matches!(err, FetcherError::ChecksumMismatch { .. }));
+        assert!(!path.join("linux-1.zip.part").exists());
+        assert!(!path.join("linux-1").exists());
+
+        let (host, offsets) = serve(b"not a zip".to_vec(), None);
+        let err = block_on(fetcher(&path, &host, None).fetch()).unwrap_err();
+        assert!(matches!(err, FetcherError::InstallFailed(_)));
+        assert!(!path.join("linux-1.zip.part").exists());
+        assert!(!path.join("linux-1.staging").exists());
+        // downloaded from the start again instead of resuming the archive
+        block_on(fetcher(&path, &host, None).fetch()).unwrap_err();
+        assert_eq!(*offsets.lock().unwrap(), [0, 0]);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn replaces_incomplete_installations() {
+        let (host, _) = serve(archive(), None);
+        let path = cache("staging");
+        fs::create_dir_all(path.join("linux-1.staging")).unwrap();
+        fs::write(path.join("linux-1.staging").join("leftover"), "").unwrap();
+        fs::create_dir_all(path.join("linux-1")).unwrap();
+        fs::write(path.join("linux-1").join("leftover"), "").unwrap();
+
+        let info = block_on(fetcher(&path, &host, None).fetch()).unwrap();
+        assert!(info.executable_path.is_file());
+        assert!(!path.join("linux-1").join("leftover").exists());
+        assert!(!path.join("linux-1.staging").exists());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn waits_for_concurrent_fetches() {
+        let (host, offsets) = serve(archive(), None);
+        let path = cache("lock");
+        let lock = block_on(FetchLock::acquire(path.join("linux-1.lock"))).unwrap();
+
+        let fetch = {
+            let (path, host) = (path.clone(), host.clone());
+            thread::spawn(move || block_on(fetcher(&path, &host, None).fetch()))
+        };
+        // install the build like another process holding the lock
+        thread::sleep(Duration::from_millis(500));
+        let executable = Platform::Linux.executable(&path.join("linux-1"), &Revision(1));
+        let archive = Cursor::new(archive());
+        ZipArchive::new(archive)
+            .unwrap()
+            .extract(path.join("linux-1"), |_, _| {})
+            .unwrap();
+        assert!(executable.is_file());
+        drop(lock);
+
+        let info = fetch.join().unwrap().unwrap();
+        assert_eq!(info.executable_path, executable);
+        assert!(offsets.lock().unwrap().is_empty());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn ignores_lock_files_left_behind() {
+        let (host, _) = serve(archive(), None);
+        let path = cache("stale-lock");
+        // like after a fetch that was killed
+        fs::write(path.join("linux-1.lock"), "4194304").unwrap();
+
+        let info = block_on(fetcher(&path, &host, None).fetch()).unwrap();
+        assert!(info.executable_path.is_file());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn lists_and_prunes_installed_builds() {
+        use Artifact::{Chrome, ChromeHeadlessShell};
+        use Platform::{Linux, Win64};
+
+        let path = cache("installed");
+        install_snapshot(&path, Linux, 1, None);
+        install_snapshot(&path, Linux, 2, None);
+        install_snapshot(&path, Win64, 2, None);
+        install_snapshot(&path, Win64, 3, None);
+        install_chrome_for_testing(&path, Chrome, "118.0.5993.70", 1192594);
+        install_chrome_for_testing(&path, ChromeHeadlessShell, "118.0.5993.88", 1192600);
+        install_chrome_for_testing(&path, ChromeHeadlessShell, "117.0.5938.149", 1181205);
+        // leftovers of interrupted installations and removals
+        install_snapshot(&path, Linux, 4, Some("linux-4.staging"));
+        fs::create_dir_all(path.join("linux-5").join("chrome-linux")).unwrap();
+        let incomplete = chrome_for_testing::folder_name(Linux, Chrome, "119.0.6045.105");
+        fs::create_dir_all(path.join(&incomplete)).unwrap();
+        fs::write(path.join("linux-6.zip.part"), "").unwrap();
+
+        let fetcher = fetcher(&path, "http://127.0.0.1:0", None);
+        let installed = || {
+            let installed = block_on(fetcher.installed()).unwrap();
+            assert!(installed
+                .windows(2)
+                .all(|pair| pair[0].revision >= pair[1].revision));
+            installed
+                .into_iter()
+                .map(|info| (info.platform, info.artifact, info.revision.0))
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(
+            installed(),
+            HashSet::from([
+                (Linux, ChromeHeadlessShell, 1192600),
+                (Linux, Chrome, 1192594),
+                (Linux, ChromeHeadlessShell, 1181205),
+                (Win64, Chrome, 3),
+                (Win64, Chrome, 2),
+                (Linux, Chrome, 2),
+                (Linux, Chrome, 1),
+            ])
+        );
+
+        // removes the revision of every platform
+        let removed = block_on(fetcher.remove(2)).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(removed.iter().all(|info| !info.folder_path.exists()));
+
+        // keeps the latest build of every platform and artifact
+        let pruned = block_on(fetcher.prune(1)).unwrap();
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(
+            installed(),
+            HashSet::from([
+                (Linux, ChromeHeadlessShell, 1192600),
+                (Linux, Chrome, 1192594),
+                (Win64, Chrome, 3),
+            ])
+        );
+
+        // only complete builds are touched
+        assert!(path.join("linux-4.staging").exists());
+        assert!(path.join("linux-5").exists());
+        assert!(path.join(incomplete).exists());
+        fs::remove_dir_all(path).unwrap();
+    }
 }
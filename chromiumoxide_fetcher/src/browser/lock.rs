@@ -0,0 +1,125 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::BrowserFetcherRuntime;
+
+/// How often to check whether the lock was released
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Guards the installation of a build against other processes fetching the
+/// same build into the same cache.
+///
+/// The lock is an advisory lock of the OS on a file next to the installation,
+/// it is released once dropped or when the process holding it dies. The file
+/// itself is kept, removing it would race with processes waiting for it.
+#[derive(Debug)]
+pub struct FetchLock {
+    file: fs::File,
+}
+
+impl FetchLock {
+    /// Waits until no other process holds the lock at `path`
+    pub async fn acquire(path: PathBuf) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        while !try_lock(&file)? {
+            BrowserFetcherRuntime::sleep(POLL_INTERVAL).await;
+        }
+        Ok(Self { file })
+    }
+}
+
+impl Drop for FetchLock {
+    fn drop(&mut self) {
+        // closing the file releases the lock as well
+        let _ = unlock(&self.file);
+    }
+}
+
+/// Takes the exclusive lock on `file`, returns `false` if another process
+/// holds it
+#[cfg(unix)]
+fn try_lock(file: &fs::File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    match err.kind() {
+        io::ErrorKind::WouldBlock => Ok(false),
+        _ => Err(err),
+    }
+}
+
+#[cfg(unix)]
+fn unlock(file: &fs::File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn try_lock(file: &fs::File) -> io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::ERROR_LOCK_VIOLATION;
+    use windows_sys::Win32::Storage::FileSystem::{
+        LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY,
+    };
+
+    let mut overlapped = unsafe { std::mem::zeroed() };
+    let locked = unsafe {
+        LockFileEx(
+            file.as_raw_handle() as _,
+            LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if locked != 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(windows)]
+fn unlock(file: &fs::File) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::UnlockFileEx;
+
+    let mut overlapped = unsafe { std::mem::zeroed() };
+    let unlocked = unsafe {
+        UnlockFileEx(
+            file.as_raw_handle() as _,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if unlocked != 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
@@ -1,11 +1,17 @@
 pub use self::fetcher::BrowserFetcher;
+use self::lock::FetchLock;
 pub use self::options::BrowserFetcherOptions;
+pub use self::progress::{Phase, Progress};
+pub use self::retry::RetryPolicy;
 pub use self::revision_info::BrowserFetcherRevisionInfo;
-use self::runtime::BrowserFetcherRuntime;
+use self::runtime::{sibling_path, BrowserFetcherRuntime};
 use self::zip::ZipArchive;
 
 mod fetcher;
+mod lock;
 mod options;
+mod progress;
+mod retry;
 mod revision_info;
 mod runtime;
 mod zip;
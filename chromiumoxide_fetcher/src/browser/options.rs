@@ -1,25 +1,43 @@
+use std::fmt;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use directories::BaseDirs;
 
+use super::progress::ProgressFn;
+use super::{Progress, RetryPolicy};
+use crate::chrome_for_testing::DEFAULT_JSON_HOST;
 use crate::error::{FetcherError, Result};
-use crate::{Platform, Revision, CURRENT_REVISION};
+use crate::{Artifact, BrowserVersion, Channel, Platform, Revision, CURRENT_REVISION};
 
 const CACHE_NAME: &str = "chromiumoxide";
-const DEFAULT_HOST: &str = "https://storage.googleapis.com";
+pub(crate) const DEFAULT_HOST: &str = "https://storage.googleapis.com";
 
 /// Options for the fetcher
+#[derive(Clone)]
 pub struct BrowserFetcherOptions {
-    /// The desired browser revision.
+    /// The desired browser version.
     ///
-    /// defaults to CURRENT_REVISION
-    pub(crate) revision: Revision,
+    /// defaults to the chromium snapshot CURRENT_REVISION
+    pub(crate) version: BrowserVersion,
+
+    /// The binary to download, only Chrome for Testing provides others than
+    /// the browser.
+    ///
+    /// defaults to Artifact::Chrome
+    pub(crate) artifact: Artifact,
 
     /// The host that will be used for downloading.
     ///
     /// defaults to https://storage.googleapis.com
     pub(crate) host: String,
 
+    /// The host of the Chrome for Testing json endpoints, the builds are
+    /// downloaded from the urls they list.
+    ///
+    /// defaults to https://googlechromelabs.github.io/chrome-for-testing
+    pub(crate) json_host: String,
+
     /// The path to download browsers to.
     ///
     /// defaults to $HOME/.cache/chromiumoxide
@@ -29,6 +47,36 @@ pub struct BrowserFetcherOptions {
     ///
     /// defaults to the currently used platform
     pub(crate) platform: Platform,
+
+    /// The expected SHA-256 of the downloaded archive, as hex.
+    ///
+    /// defaults to None, which only verifies the size of the download
+    pub(crate) checksum: Option<String>,
+
+    /// Called with the progress of downloads and installations.
+    ///
+    /// defaults to None
+    pub(crate) progress: Option<ProgressFn>,
+
+    /// How to retry failed requests.
+    ///
+    /// defaults to 3 retries with exponential backoff
+    pub(crate) retry: RetryPolicy,
+}
+
+impl fmt::Debug for BrowserFetcherOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrowserFetcherOptions")
+            .field("version", &self.version)
+            .field("artifact", &self.artifact)
+            .field("host", &self.host)
+            .field("json_host", &self.json_host)
+            .field("path", &self.path)
+            .field("platform", &self.platform)
+            .field("checksum", &self.checksum)
+            .field("retry", &self.retry)
+            .finish_non_exhaustive()
+    }
 }
 
 impl BrowserFetcherOptions {
@@ -43,15 +91,41 @@ impl BrowserFetcherOptions {
 
 #[derive(Default)]
 pub struct BrowserFetcherOptionsBuilder {
-    revision: Option<Revision>,
+    version: Option<BrowserVersion>,
+    artifact: Artifact,
     host: Option<String>,
+    json_host: Option<String>,
     path: Option<PathBuf>,
     platform: Option<Platform>,
+    checksum: Option<String>,
+    progress: Option<ProgressFn>,
+    retry: RetryPolicy,
 }
 
 impl BrowserFetcherOptionsBuilder {
     pub fn with_revision<T: Into<Revision>>(mut self, revision: T) -> Self {
-        self.revision = Some(revision.into());
+        self.version = Some(BrowserVersion::Revision(revision.into()));
+        self
+    }
+
+    /// Fetches a chromium snapshot revision or a Chrome for Testing build
+    pub fn with_version<T: Into<BrowserVersion>>(mut self, version: T) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Fetches the latest Chrome for Testing build of the channel
+    pub fn with_channel(self, channel: Channel) -> Self {
+        self.with_version(BrowserVersion::Channel(channel))
+    }
+
+    /// Fetches the latest Chrome for Testing build of the milestone
+    pub fn with_milestone(self, milestone: u32) -> Self {
+        self.with_version(BrowserVersion::Milestone(milestone))
+    }
+
+    pub fn with_artifact(mut self, artifact: Artifact) -> Self {
+        self.artifact = artifact;
         self
     }
 
@@ -60,6 +134,13 @@ impl BrowserFetcherOptionsBuilder {
         self
     }
 
+    /// Resolves Chrome for Testing builds with the json endpoints of `host`,
+    /// e.g. a mirror that provides builds for [`Platform::LinuxArm64`]
+    pub fn with_chrome_for_testing_host<T: Into<String>>(mut self, host: T) -> Self {
+        self.json_host = Some(host.into());
+        self
+    }
+
     pub fn with_path<T: Into<PathBuf>>(mut self, path: T) -> Self {
         self.path = Some(path.into());
         self
@@ -70,6 +151,26 @@ impl BrowserFetcherOptionsBuilder {
         self
     }
 
+    /// Verifies the downloaded archive against the hex encoded SHA-256
+    pub fn with_checksum<T: Into<String>>(mut self, checksum: T) -> Self {
+        self.checksum = Some(checksum.into());
+        self
+    }
+
+    /// Reports the progress of downloads and installations to `progress`
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     pub fn build(self) -> Result<BrowserFetcherOptions> {
         let path = self
             .path
@@ -91,10 +192,19 @@ impl BrowserFetcherOptionsBuilder {
                 ))?;
 
         Ok(BrowserFetcherOptions {
-            revision: self.revision.unwrap_or(CURRENT_REVISION),
+            version: self
+                .version
+                .unwrap_or(BrowserVersion::Revision(CURRENT_REVISION)),
+            artifact: self.artifact,
             host: self.host.unwrap_or_else(|| DEFAULT_HOST.to_string()),
+            json_host: self
+                .json_host
+                .unwrap_or_else(|| DEFAULT_JSON_HOST.to_string()),
             path,
             platform,
+            checksum: self.checksum,
+            progress: self.progress,
+            retry: self.retry,
         })
     }
 }
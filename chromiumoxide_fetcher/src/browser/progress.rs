@@ -0,0 +1,51 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// What the fetcher is currently doing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Downloading the archive, progress is counted in bytes
+    Download,
+    /// Extracting the archive, progress is counted in archive entries
+    Extract,
+}
+
+/// Progress of a fetch, reported to the callback of the options
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub phase: Phase,
+    /// How much of the phase is done, including a resumed partial download
+    pub current: u64,
+    /// The size of the phase, if known
+    pub total: Option<u64>,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self.phase {
+            Phase::Download => "Downloading",
+            Phase::Extract => "Extracting",
+        };
+        match self.total {
+            Some(total) => write!(f, "{phase}: {}/{total}", self.current),
+            None => write!(f, "{phase}: {}", self.current),
+        }
+    }
+}
+
+pub(crate) type ProgressFn = Arc<dyn Fn(Progress) + Send + Sync>;
+
+pub(crate) fn report(
+    progress: Option<&ProgressFn>,
+    phase: Phase,
+    current: u64,
+    total: Option<u64>,
+) {
+    if let Some(progress) = progress {
+        progress(Progress {
+            phase,
+            current,
+            total,
+        })
+    }
+}
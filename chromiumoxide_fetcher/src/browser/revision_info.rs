@@ -1,7 +1,7 @@
 use std::fmt;
 use std::path::PathBuf;
 
-use crate::Revision;
+use crate::{Artifact, Platform, Revision};
 
 /// Details of an installed version of chromium
 #[derive(Clone, Debug)]
@@ -9,10 +9,18 @@ pub struct BrowserFetcherRevisionInfo {
     pub folder_path: PathBuf,
     pub executable_path: PathBuf,
     pub revision: Revision,
+    /// The Chrome for Testing version, `None` for chromium snapshots
+    pub version: Option<String>,
+    pub platform: Platform,
+    /// The installed binary, always [`Artifact::Chrome`] for chromium snapshots
+    pub artifact: Artifact,
 }
 
 impl fmt::Display for BrowserFetcherRevisionInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref version) = self.version {
+            write!(f, "Version: {version}, ")?;
+        }
         write!(
             f,
             "Revision: {}, Path: {}",
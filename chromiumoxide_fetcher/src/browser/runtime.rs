@@ -1,101 +1,333 @@
 use anyhow::Context;
+use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use super::ZipArchive;
+use sha2::{Digest, Sha256};
+
+use super::progress::{report, ProgressFn};
+use super::{Phase, ZipArchive};
 
 #[derive(Debug, Default)]
 pub struct BrowserFetcherRuntime;
 
 #[cfg(feature = "async-std-runtime")]
 impl BrowserFetcherRuntime {
-    pub async fn exists(folder_path: &Path) -> bool {
-        async_std::fs::metadata(folder_path).await.is_ok()
+    pub async fn is_executable(path: &Path) -> bool {
+        async_std::fs::metadata(path)
+            .await
+            .map(|metadata| is_executable(&metadata))
+            .unwrap_or(false)
     }
 
-    pub async fn download(url: &str, archive_path: &Path) -> anyhow::Result<()> {
-        use async_std::io::WriteExt;
-        use surf::http;
+    pub async fn read(path: &Path) -> anyhow::Result<String> {
+        Ok(async_std::fs::read_to_string(path).await?)
+    }
 
-        // Open file
-        let file = async_std::fs::File::create(&archive_path)
+    pub async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+
+    pub async fn get(url: &str) -> anyhow::Result<Vec<u8>> {
+        let url = url.parse::<surf::Url>().context("Invalid url")?;
+        let mut res = surf::get(url)
             .await
-            .context("Failed to create archive file")?;
-        let mut file = async_std::io::BufWriter::new(file);
+            .map_err(|e| e.into_inner())
+            .context("Failed to send request to host")?;
+        if res.status() != surf::StatusCode::Ok {
+            anyhow::bail!("Request failed with status {}", res.status());
+        }
+        res.body_bytes()
+            .await
+            .map_err(|e| e.into_inner())
+            .context("Failed to read response")
+    }
+
+    /// Downloads `url` to `archive_path`, resuming a previous partial download
+    pub async fn download(
+        url: &str,
+        archive_path: &Path,
+        progress: Option<&ProgressFn>,
+    ) -> anyhow::Result<()> {
+        use async_std::io::{ReadExt, WriteExt};
+
+        // Resume
+        let offset = async_std::fs::metadata(archive_path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or_default();
 
         // Download
         let url = url.parse::<surf::Url>().context("Invalid archive url")?;
-        let res = surf::RequestBuilder::new(http::Method::Get, url)
+        let mut req = surf::RequestBuilder::new(surf::http::Method::Get, url);
+        if offset > 0 {
+            req = req.header("Range", format!("bytes={offset}-"));
+        }
+        let mut res = req
             .await
             .map_err(|e| e.into_inner())
             .context("Failed to send request to host")?;
-        if res.status() != surf::StatusCode::Ok {
-            anyhow::bail!("Invalid archive url");
-        }
-        async_std::io::copy(res, &mut file)
+        let append = match res.status() {
+            surf::StatusCode::PartialContent => true,
+            surf::StatusCode::Ok => false,
+            // the previous download was complete
+            surf::StatusCode::RequestedRangeNotSatisfiable if offset > 0 => return Ok(()),
+            _ => anyhow::bail!("Invalid archive url"),
+        };
+        let expected = res.len().map(|len| len as u64);
+        let offset = if append { offset } else { 0 };
+        let total = expected.map(|len| offset + len);
+
+        // Open file
+        let file = async_std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&archive_path)
             .await
-            .context("Failed to write to archive file")?;
+            .context("Failed to create archive file")?;
+        let mut file = async_std::io::BufWriter::new(file);
+        let mut buf = vec![0; 64 * 1024];
+        let mut written = 0;
+        report(progress, Phase::Download, offset, total);
+        loop {
+            let read = res
+                .read(&mut buf)
+                .await
+                .context("Failed to read response")?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buf[..read])
+                .await
+                .context("Failed to write to archive file")?;
+            written += read as u64;
+            report(progress, Phase::Download, offset + written, total);
+        }
 
         // Flush to disk
         file.flush().await.context("Failed to flush to disk")?;
-        Ok(())
+        verify_size(written, expected)
+    }
+
+    pub async fn sha256(archive_path: PathBuf) -> anyhow::Result<String> {
+        async_std::task::spawn_blocking(move || do_sha256(&archive_path)).await
     }
 
-    pub async fn unzip(archive_path: PathBuf, folder_path: PathBuf) -> anyhow::Result<()> {
-        async_std::task::spawn_blocking(move || do_unzip(&archive_path, &folder_path)).await?;
-        Ok(())
+    pub async fn install(
+        archive_path: PathBuf,
+        folder_path: PathBuf,
+        marker: Option<(&'static str, String)>,
+        progress: Option<ProgressFn>,
+    ) -> anyhow::Result<()> {
+        async_std::task::spawn_blocking(move || {
+            do_install(&archive_path, &folder_path, marker, progress.as_ref())
+        })
+        .await
     }
 }
 
 #[cfg(feature = "tokio-runtime")]
 impl BrowserFetcherRuntime {
-    pub async fn exists(folder_path: &Path) -> bool {
-        tokio::fs::metadata(folder_path).await.is_ok()
+    pub async fn is_executable(path: &Path) -> bool {
+        tokio::fs::metadata(path)
+            .await
+            .map(|metadata| is_executable(&metadata))
+            .unwrap_or(false)
+    }
+
+    pub async fn read(path: &Path) -> anyhow::Result<String> {
+        Ok(tokio::fs::read_to_string(path).await?)
+    }
+
+    pub async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub async fn get(url: &str) -> anyhow::Result<Vec<u8>> {
+        let url = url.parse::<reqwest::Url>().context("Invalid url")?;
+        let res = reqwest::get(url)
+            .await
+            .context("Failed to send request to host")?;
+        if res.status() != reqwest::StatusCode::OK {
+            anyhow::bail!("Request failed with status {}", res.status());
+        }
+        Ok(res
+            .bytes()
+            .await
+            .context("Failed to read response")?
+            .to_vec())
     }
 
-    pub async fn download(url: &str, archive_path: &Path) -> anyhow::Result<()> {
+    /// Downloads `url` to `archive_path`, resuming a previous partial download
+    pub async fn download(
+        url: &str,
+        archive_path: &Path,
+        progress: Option<&ProgressFn>,
+    ) -> anyhow::Result<()> {
         use tokio::io::AsyncWriteExt;
 
-        // Open file
-        let file = tokio::fs::File::create(&archive_path)
+        // Resume
+        let offset = tokio::fs::metadata(archive_path)
             .await
-            .context("Failed to create archive file")?;
-        let mut file = tokio::io::BufWriter::new(file);
+            .map(|m| m.len())
+            .unwrap_or_default();
 
         // Download
         let url = url.parse::<reqwest::Url>().context("Invalid archive url")?;
-        let mut res = reqwest::get(url)
-            .await
-            .context("Failed to send request to host")?;
-        if res.status() != reqwest::StatusCode::OK {
-            anyhow::bail!("Invalid archive url");
+        let mut req = reqwest::Client::new().get(url);
+        if offset > 0 {
+            req = req.header(reqwest::header::RANGE, format!("bytes={offset}-"));
         }
+        let mut res = req.send().await.context("Failed to send request to host")?;
+        let append = match res.status() {
+            reqwest::StatusCode::PARTIAL_CONTENT => true,
+            reqwest::StatusCode::OK => false,
+            // the previous download was complete
+            reqwest::StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => return Ok(()),
+            _ => anyhow::bail!("Invalid archive url"),
+        };
+        let expected = res.content_length();
+        let offset = if append { offset } else { 0 };
+        let total = expected.map(|len| offset + len);
+
+        // Open file
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&archive_path)
+            .await
+            .context("Failed to create archive file")?;
+        let mut file = tokio::io::BufWriter::new(file);
+        let mut written = 0;
+        report(progress, Phase::Download, offset, total);
         while let Some(chunk) = res.chunk().await.context("Failed to read response chunk")? {
-            file.write(&chunk)
+            file.write_all(&chunk)
                 .await
                 .context("Failed to write to archive file")?;
+            written += chunk.len() as u64;
+            report(progress, Phase::Download, offset + written, total);
         }
 
         // Flush to disk
         file.flush().await.context("Failed to flush to disk")?;
+        verify_size(written, expected)
+    }
 
-        Ok(())
+    pub async fn sha256(archive_path: PathBuf) -> anyhow::Result<String> {
+        tokio::task::spawn_blocking(move || do_sha256(&archive_path)).await?
     }
 
-    pub async fn unzip(archive_path: PathBuf, folder_path: PathBuf) -> anyhow::Result<()> {
-        tokio::task::spawn_blocking(move || do_unzip(&archive_path, &folder_path)).await?
+    pub async fn install(
+        archive_path: PathBuf,
+        folder_path: PathBuf,
+        marker: Option<(&'static str, String)>,
+        progress: Option<ProgressFn>,
+    ) -> anyhow::Result<()> {
+        tokio::task::spawn_blocking(move || {
+            do_install(&archive_path, &folder_path, marker, progress.as_ref())
+        })
+        .await?
     }
 }
 
-fn do_unzip(archive_path: &Path, folder_path: &Path) -> anyhow::Result<()> {
-    use std::fs;
+/// The path next to `folder_path` with `suffix` appended to its whole name.
+///
+/// Unlike `Path::with_extension` this keeps the last part of dotted versions,
+/// so `120.0.6099.71` and `120.0.6099.109` don't share their files.
+pub fn sibling_path(folder_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = folder_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    folder_path.with_file_name(name)
+}
+
+/// Whether the file of `metadata` can be run by the current user
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Fails if the connection dropped before the whole body was received, the
+/// part that was written is resumed by the next download
+fn verify_size(written: u64, expected: Option<u64>) -> anyhow::Result<()> {
+    match expected {
+        Some(expected) if written != expected => {
+            anyhow::bail!("Download incomplete, received {written} of {expected} bytes")
+        }
+        _ => Ok(()),
+    }
+}
+
+fn do_sha256(archive_path: &Path) -> anyhow::Result<String> {
+    let mut file = fs::File::open(archive_path).context("Failed to open archive")?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).context("Failed to read archive")?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Extracts the archive next to `folder_path` and moves it into place once it
+/// is complete, so `folder_path` either doesn't exist or is fully installed.
+fn do_install(
+    archive_path: &Path,
+    folder_path: &Path,
+    marker: Option<(&'static str, String)>,
+    progress: Option<&ProgressFn>,
+) -> anyhow::Result<()> {
+    let staging_path = sibling_path(folder_path, "staging");
 
     // Prepare
-    fs::create_dir_all(folder_path).context("Failed to create folder")?;
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path).context("Failed to clean staging folder")?;
+    }
+    fs::create_dir_all(&staging_path).context("Failed to create folder")?;
     let file = fs::File::open(archive_path).context("Failed to open archive")?;
 
     // Unzip
-    let mut archive = ZipArchive::new(file).context("Failed to unzip archive")?;
-    archive.extract(folder_path)?;
+    let extracted = ZipArchive::new(file)
+        .and_then(|mut archive| {
+            archive.extract(&staging_path, |current, total| {
+                report(progress, Phase::Extract, current as u64, Some(total as u64))
+            })
+        })
+        .context("Failed to unzip archive");
+    if let Err(err) = extracted {
+        // a corrupt archive can't be resumed, download it again next time
+        let _ = fs::remove_file(archive_path);
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(err);
+    }
+    if let Some((name, contents)) = marker {
+        fs::write(staging_path.join(name), contents).context("Failed to write marker")?;
+    }
+
+    // Move into place, replacing an incomplete installation
+    if folder_path.exists() {
+        fs::remove_dir_all(folder_path).context("Failed to remove previous installation")?;
+    }
+    fs::rename(&staging_path, folder_path).context("Failed to move installation into place")?;
 
     // Clean (if possible)
     let _ = fs::remove_file(archive_path);
@@ -35,9 +35,17 @@ impl<R: Read + Seek> ZipArchive<R> {
     /// We must be careful with this implementation since it is not
     /// protected against malicious symlinks, but we trust the binaries
     /// provided by chromium.
-    pub fn extract<P: AsRef<Path>>(&mut self, directory: P) -> ZipResult<()> {
+    ///
+    /// `progress` is called with the number of extracted entries and the total.
+    pub fn extract<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        mut progress: impl FnMut(usize, usize),
+    ) -> ZipResult<()> {
         use std::fs;
-        for i in 0..self.len() {
+        let len = self.len();
+        for i in 0..len {
+            progress(i, len);
             let mut file = self.by_index(i)?;
             let filepath = file
                 .enclosed_name()
@@ -72,6 +80,7 @@ impl<R: Read + Seek> ZipArchive<R> {
                 }
             }
         }
+        progress(len, len);
         Ok(())
     }
 }
@@ -0,0 +1,226 @@
+//! Resolution of Chrome for Testing builds, see
+//! <https://github.com/GoogleChromeLabs/chrome-for-testing#json-api-endpoints>
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{FetcherError, Result};
+use crate::{Artifact, BrowserVersion, Platform, Revision};
+
+pub(crate) const DEFAULT_JSON_HOST: &str = "https://googlechromelabs.github.io/chrome-for-testing";
+
+/// A resolved Chrome for Testing build
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Build {
+    pub version: String,
+    pub revision: Revision,
+    /// Where to download the archive of the requested artifact
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    version: String,
+    revision: String,
+    #[serde(default)]
+    downloads: HashMap<String, Vec<Download>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Download {
+    platform: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KnownGoodVersions {
+    versions: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastKnownGoodVersions {
+    channels: HashMap<String, Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestVersionsPerMilestone {
+    milestones: HashMap<String, Release>,
+}
+
+/// The json endpoint that resolves `version`
+pub(crate) fn endpoint(host: &str, version: &BrowserVersion) -> String {
+    let name = match version {
+        BrowserVersion::Channel(_) => "last-known-good-versions-with-downloads.json",
+        BrowserVersion::Milestone(_) => "latest-versions-per-milestone-with-downloads.json",
+        _ => "known-good-versions-with-downloads.json",
+    };
+    format!("{}/{}", host.trim_end_matches('/'), name)
+}
+
+/// Finds the build of `version` in the response of its [`endpoint`]
+pub(crate) fn resolve(
+    json: &[u8],
+    version: &BrowserVersion,
+    platform: Platform,
+    artifact: Artifact,
+) -> Result<Build> {
+    let parse_err = |err: serde_json::Error| FetcherError::ResolveFailed(err.into());
+    let release = match version {
+        BrowserVersion::Channel(channel) => serde_json::from_slice::<LastKnownGoodVersions>(json)
+            .map_err(parse_err)?
+            .channels
+            .remove(channel.name()),
+        BrowserVersion::Milestone(milestone) => {
+            serde_json::from_slice::<LatestVersionsPerMilestone>(json)
+                .map_err(parse_err)?
+                .milestones
+                .remove(&milestone.to_string())
+        }
+        BrowserVersion::Version(version) => serde_json::from_slice::<KnownGoodVersions>(json)
+            .map_err(parse_err)?
+            .versions
+            .into_iter()
+            .find(|release| release.version == *version),
+        BrowserVersion::Revision(_) => None,
+    }
+    .ok_or_else(|| FetcherError::VersionNotFound(version.to_string()))?;
+
+    let platform_name = platform.cft_name();
+    let url = release
+        .downloads
+        .get(artifact.name())
+        .and_then(|downloads| downloads.iter().find(|d| d.platform == platform_name))
+        .map(|download| download.url.clone())
+        .ok_or_else(|| FetcherError::NoDownload {
+            artifact,
+            version: release.version.clone(),
+            platform,
+        })?;
+
+    Ok(Build {
+        revision: Revision::try_from(release.revision)?,
+        version: release.version,
+        url,
+    })
+}
+
+/// The name of the folder a build is installed to
+pub(crate) fn folder_name(platform: Platform, artifact: Artifact, version: &str) -> String {
+    format!("{}-{}-{}", artifact, platform.cft_name(), version)
+}
+
+/// The platform, artifact and version of a build installed to the folder `name`
+pub(crate) fn parse_folder_name(name: &str) -> Option<(Platform, Artifact, String)> {
+    Artifact::ALL
+        .into_iter()
+        .flat_map(|artifact| Platform::ALL.map(|platform| (platform, artifact)))
+        .find_map(|(platform, artifact)| {
+            let version = name.strip_prefix(&folder_name(platform, artifact, ""))?;
+            (!version.is_empty()).then(|| (platform, artifact, version.to_string()))
+        })
+}
+
+/// The path of the binary of an installed build
+pub(crate) fn executable(folder_path: &Path, platform: Platform, artifact: Artifact) -> PathBuf {
+    let platform_name = platform.cft_name();
+    let mut path = folder_path.to_path_buf();
+    // the archives contain a single folder named after the artifact
+    path.push(format!("{artifact}-{platform_name}"));
+    match (artifact, platform) {
+        (Artifact::Chrome, Platform::Mac | Platform::MacArm) => {
+            path.push("Google Chrome for Testing.app");
+            path.push("Contents");
+            path.push("MacOS");
+            path.push("Google Chrome for Testing");
+        }
+        (Artifact::Chrome, Platform::Win32 | Platform::Win64) => path.push("chrome.exe"),
+        (Artifact::Chrome, _) => path.push("chrome"),
+        (_, Platform::Win32 | Platform::Win64) => path.push(format!("{artifact}.exe")),
+        (_, _) => path.push(artifact.name()),
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channel;
+
+    const LAST_KNOWN_GOOD: &str = r#"{
+        "timestamp": "2023-10-18T08:09:10.123Z",
+        "channels": {
+            "Stable": {
+                "channel": "Stable",
+                "version": "118.0.5993.70",
+                "revision": "1192594",
+                "downloads": {
+                    "chrome": [
+                        { "platform": "linux64", "url": "https://example.com/118.0.5993.70/linux64/chrome-linux64.zip" },
+                        { "platform": "win64", "url": "https://example.com/118.0.5993.70/win64/chrome-win64.zip" }
+                    ],
+                    "chromedriver": [
+                        { "platform": "linux64", "url": "https://example.com/118.0.5993.70/linux64/chromedriver-linux64.zip" }
+                    ]
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn resolves_channel() {
+        let version = BrowserVersion::Channel(Channel::Stable);
+        let build = resolve(
+            LAST_KNOWN_GOOD.as_bytes(),
+            &version,
+            Platform::Linux,
+            Artifact::ChromeDriver,
+        )
+        .unwrap();
+        assert_eq!(build.version, "118.0.5993.70");
+        assert_eq!(build.revision, Revision(1192594));
+        assert!(build.url.ends_with("chromedriver-linux64.zip"));
+
+        let err = resolve(
+            LAST_KNOWN_GOOD.as_bytes(),
+            &version,
+            Platform::Win64,
+            Artifact::ChromeHeadlessShell,
+        )
+        .unwrap_err();
+        assert!(matches!(err, FetcherError::NoDownload { .. }));
+        assert!(matches!(
+            resolve(
+                LAST_KNOWN_GOOD.as_bytes(),
+                &BrowserVersion::Channel(Channel::Beta),
+                Platform::Linux,
+                Artifact::Chrome
+            ),
+            Err(FetcherError::VersionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn parses_folder_name() {
+        let name = folder_name(
+            Platform::MacArm,
+            Artifact::ChromeHeadlessShell,
+            "118.0.5993.70",
+        );
+        assert_eq!(
+            parse_folder_name(&name),
+            Some((
+                Platform::MacArm,
+                Artifact::ChromeHeadlessShell,
+                "118.0.5993.70".to_string()
+            ))
+        );
+        assert_eq!(parse_folder_name("chrome-linux64-"), None);
+        assert_eq!(
+            Platform::parse_folder_name("mac_arm-1045629"),
+            Some((Platform::MacArm, Revision(1045629)))
+        );
+        assert_eq!(Platform::parse_folder_name("linux-1045629.lock"), None);
+    }
+}
@@ -2,6 +2,8 @@ use std::num::ParseIntError;
 
 use thiserror::Error;
 
+use crate::{Artifact, Platform};
+
 pub type Result<T, E = FetcherError> = std::result::Result<T, E>;
 
 #[derive(Debug, Error)]
@@ -18,6 +20,25 @@ pub enum FetcherError {
     #[error("Installation of browser failed")]
     InstallFailed(#[source] anyhow::Error),
 
+    #[error("Failed to access the browser cache")]
+    Cache(#[source] std::io::Error),
+
     #[error("OS {0} {1} is not supported")]
     UnsupportedOs(&'static str, &'static str),
+
+    #[error("Resolving the browser version failed")]
+    ResolveFailed(#[source] anyhow::Error),
+
+    #[error("Checksum of the download is {actual}, expected {expected}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("Browser version {0} not found")]
+    VersionNotFound(String),
+
+    #[error("No {artifact} download of version {version} for {platform:?}")]
+    NoDownload {
+        artifact: Artifact,
+        version: String,
+        platform: Platform,
+    },
 }
@@ -1,12 +1,17 @@
-pub use self::browser::{BrowserFetcher, BrowserFetcherOptions, BrowserFetcherRevisionInfo};
+pub use self::browser::{
+    BrowserFetcher, BrowserFetcherOptions, BrowserFetcherRevisionInfo, Phase, Progress, RetryPolicy,
+};
 pub use self::error::FetcherError;
 pub use self::platform::Platform;
 pub use self::revision::Revision;
+pub use self::version::{Artifact, BrowserVersion, Channel};
 
 /// Currently downloaded chromium revision
 pub const CURRENT_REVISION: Revision = Revision(1045629);
 
 mod browser;
+mod chrome_for_testing;
 mod error;
 mod platform;
 mod revision;
+mod version;
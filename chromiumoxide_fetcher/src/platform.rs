@@ -3,9 +3,12 @@ use std::path::{Path, PathBuf};
 use crate::Revision;
 
 /// List of platforms with pre-built chromium binaries
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Platform {
     Linux,
+    /// Linux on aarch64, neither chromium snapshots nor Chrome for Testing
+    /// provide builds for it so they must be downloaded from a custom host.
+    LinuxArm64,
     Mac,
     MacArm,
     Win32,
@@ -13,10 +16,20 @@ pub enum Platform {
 }
 
 impl Platform {
+    pub(crate) const ALL: [Platform; 6] = [
+        Self::Linux,
+        Self::LinuxArm64,
+        Self::Mac,
+        Self::MacArm,
+        Self::Win32,
+        Self::Win64,
+    ];
+
     pub(crate) fn download_url(&self, host: &str, revision: &Revision) -> String {
         let archive = self.archive_name(revision);
         let name = match self {
             Self::Linux => "Linux_x64",
+            Self::LinuxArm64 => "Linux_Arm64",
             Self::Mac => "Mac",
             Self::MacArm => "Mac_Arm",
             Self::Win32 => "Win",
@@ -28,9 +41,21 @@ impl Platform {
         )
     }
 
+    /// The name of the platform in Chrome for Testing downloads
+    pub(crate) fn cft_name(&self) -> &'static str {
+        match self {
+            Self::Linux => "linux64",
+            Self::LinuxArm64 => "linux-arm64",
+            Self::Mac => "mac-x64",
+            Self::MacArm => "mac-arm64",
+            Self::Win32 => "win32",
+            Self::Win64 => "win64",
+        }
+    }
+
     pub(crate) fn archive_name(&self, revision: &Revision) -> String {
         match self {
-            Self::Linux => "chrome-linux".to_string(),
+            Self::Linux | Self::LinuxArm64 => "chrome-linux".to_string(),
             Self::Mac | Self::MacArm => "chrome-mac".to_string(),
             Self::Win32 | Self::Win64 => {
                 if revision.0 > 591_479 {
@@ -43,21 +68,37 @@ impl Platform {
     }
 
     pub(crate) fn folder_name(&self, revision: &Revision) -> String {
-        let platform = match self {
+        format!("{}-{revision}", self.folder_prefix())
+    }
+
+    /// The platform and revision of a snapshot installed to the folder `name`
+    pub(crate) fn parse_folder_name(name: &str) -> Option<(Platform, Revision)> {
+        Self::ALL.into_iter().find_map(|platform| {
+            let revision = name
+                .strip_prefix(platform.folder_prefix())?
+                .strip_prefix('-')?
+                .parse()
+                .ok()?;
+            Some((platform, Revision(revision)))
+        })
+    }
+
+    fn folder_prefix(&self) -> &'static str {
+        match self {
             Self::Linux => "linux",
+            Self::LinuxArm64 => "linux_arm64",
             Self::Mac => "mac",
             Self::MacArm => "mac_arm",
             Self::Win32 => "win32",
             Self::Win64 => "win64",
-        };
-        format!("{platform}-{revision}")
+        }
     }
 
     pub(crate) fn executable(&self, folder_path: &Path, revision: &Revision) -> PathBuf {
         let mut path = folder_path.to_path_buf();
         path.push(self.archive_name(revision));
         match self {
-            Self::Linux => path.push("chrome"),
+            Self::Linux | Self::LinuxArm64 => path.push("chrome"),
             Self::Mac | Self::MacArm => {
                 path.push("Chromium.app");
                 path.push("Contents");
@@ -70,9 +111,10 @@ impl Platform {
     }
 
     pub(crate) fn current() -> Option<Platform> {
-        // Currently there are no builds for Linux arm
         if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
             Some(Self::Linux)
+        } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+            Some(Self::LinuxArm64)
         } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
             Some(Self::Mac)
         } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::Revision;
+
+/// A release channel of Chrome for Testing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Stable,
+    Beta,
+    Dev,
+    Canary,
+}
+
+impl Channel {
+    /// The name of the channel in the Chrome for Testing json endpoints
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Stable => "Stable",
+            Self::Beta => "Beta",
+            Self::Dev => "Dev",
+            Self::Canary => "Canary",
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The binary to download from Chrome for Testing
+///
+/// Chromium snapshots only provide the [`Artifact::Chrome`] browser.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Artifact {
+    /// The full browser
+    #[default]
+    Chrome,
+    /// The lightweight headless-only browser
+    ChromeHeadlessShell,
+    /// The webdriver for the browser of the same version
+    ChromeDriver,
+}
+
+impl Artifact {
+    pub(crate) const ALL: [Artifact; 3] =
+        [Self::Chrome, Self::ChromeHeadlessShell, Self::ChromeDriver];
+
+    /// The name of the artifact in the Chrome for Testing json endpoints and
+    /// archives
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Chrome => "chrome",
+            Self::ChromeHeadlessShell => "chrome-headless-shell",
+            Self::ChromeDriver => "chromedriver",
+        }
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Which build of the browser to fetch
+///
+/// Except for [`BrowserVersion::Revision`], which is a chromium snapshot,
+/// the versions are Chrome for Testing builds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BrowserVersion {
+    /// A chromium snapshot
+    Revision(Revision),
+    /// The latest version of a release channel
+    Channel(Channel),
+    /// The latest version of a milestone, e.g. `118`
+    Milestone(u32),
+    /// An exact version, e.g. `118.0.5993.70`
+    Version(String),
+}
+
+impl BrowserVersion {
+    /// Whether this is a Chrome for Testing build
+    pub fn is_chrome_for_testing(&self) -> bool {
+        !matches!(self, Self::Revision(_))
+    }
+}
+
+impl From<Revision> for BrowserVersion {
+    fn from(revision: Revision) -> Self {
+        Self::Revision(revision)
+    }
+}
+
+impl From<Channel> for BrowserVersion {
+    fn from(channel: Channel) -> Self {
+        Self::Channel(channel)
+    }
+}
+
+impl fmt::Display for BrowserVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Revision(revision) => write!(f, "r{revision}"),
+            Self::Channel(channel) => write!(f, "{channel}"),
+            Self::Milestone(milestone) => write!(f, "M{milestone}"),
+            Self::Version(version) => f.write_str(version),
+        }
+    }
+}
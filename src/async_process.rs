@@ -60,11 +60,22 @@ impl Command {
     }
 
     pub fn spawn(&mut self) -> std::io::Result<Child> {
+        // don't inherit pipes that are created at the same time
+        #[cfg(unix)]
+        let _spawning = crate::transport::spawn_lock();
         let inner = self.inner.spawn()?;
         Ok(Child::new(inner))
     }
 }
 
+impl From<std::process::Command> for Command {
+    fn from(cmd: std::process::Command) -> Self {
+        let mut inner = process::Command::from(cmd);
+        inner.kill_on_drop(true);
+        Self { inner }
+    }
+}
+
 #[derive(Debug)]
 pub struct Child {
     pub stderr: Option<ChildStderr>,
@@ -99,6 +110,17 @@ impl Child {
         }
     }
 
+    /// Kill the child process without waiting for it to exit
+    pub fn start_kill(&mut self) -> std::io::Result<()> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "async-std-runtime")] {
+                self.inner.kill()
+            } else if #[cfg(feature = "tokio-runtime")] {
+                self.inner.start_kill()
+            }
+        }
+    }
+
     /// Asynchronously wait for the child process to exit
     pub async fn wait(&mut self) -> std::io::Result<ExitStatus> {
         cfg_if::cfg_if! {
@@ -1,12 +1,14 @@
 use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
     io,
     path::{Path, PathBuf},
 };
 
-use futures::channel::mpsc::{channel, unbounded, Sender};
+use futures::channel::mpsc::{channel, unbounded, Sender, UnboundedReceiver};
 use futures::channel::oneshot::channel as oneshot_channel;
 use futures::select;
 use futures::SinkExt;
@@ -19,15 +21,29 @@ use chromiumoxide_cdp::cdp::{CdpEventMessage, IntoEventKind};
 use chromiumoxide_types::*;
 
 use crate::async_process::{self, Child, ExitStatus, Stdio};
+use crate::auth::Credentials;
 use crate::cmd::{to_command_response, CommandMessage};
+use crate::compat::{ProtocolCheck, ProtocolCompat};
 use crate::conn::Connection;
+use crate::context::BrowserContextOptions;
 use crate::detection::{self, DetectionOptions};
-use crate::error::{BrowserStderr, CdpError, Result};
+use crate::devtools::DevToolsHttpClient;
+use crate::error::{BrowserStderr, CdpError, DevToolsError, Result};
+#[cfg(feature = "fetcher")]
+use crate::fetcher::{BrowserFetcher, BrowserFetcherOptions};
 use crate::handler::browser::BrowserContext;
+use crate::handler::metrics::Metrics;
+use crate::handler::supervision::{
+    LifecycleEvent, ReconnectPolicy, ReconnectSource, RelaunchedProcess,
+};
 use crate::handler::viewport::Viewport;
 use crate::handler::{Handler, HandlerConfig, HandlerMessage, REQUEST_TIMEOUT};
-use crate::listeners::{EventListenerRequest, EventStream};
+use crate::listeners::{EventListenerRequest, EventStream, ListenerOptions};
 use crate::page::Page;
+use crate::profile::TempUserDataDir;
+#[cfg(unix)]
+use crate::transport::{BrowserPipes, PipeTransport};
+use crate::transport::{Recorder, Transport};
 use crate::utils;
 use chromiumoxide_cdp::cdp::browser_protocol::browser::{
     BrowserContextId, CloseReturns, GetVersionParams, GetVersionReturns,
@@ -46,8 +62,16 @@ pub struct Browser {
     config: Option<BrowserConfig>,
     /// The spawned chromium instance
     child: Option<Child>,
+    /// The temporary user data dir of the spawned chromium instance, removed
+    /// once it exited
+    temp_user_data_dir: Option<TempUserDataDir>,
+    /// The process the `Handler` launched after the connection was lost
+    relaunched: RelaunchedProcess,
     /// The debug web socket url of the chromium instance
     debug_ws_url: String,
+    /// The client the browser was connected with, for its DevTools http
+    /// endpoints
+    devtools: Option<DevToolsHttpClient>,
     /// The context of the browser
     browser_context: BrowserContext,
 }
@@ -90,56 +114,125 @@ impl Browser {
         url: impl Into<String>,
         config: HandlerConfig,
     ) -> Result<(Self, Handler)> {
-        let mut debug_ws_url = url.into();
-
-        if debug_ws_url.starts_with("http") {
-            match reqwest::Client::new()
-                .get(
-                    if debug_ws_url.ends_with("/json/version")
-                        || debug_ws_url.ends_with("/json/version/")
-                    {
-                        debug_ws_url.clone()
-                    } else {
-                        format!(
-                            "{}{}json/version",
-                            &debug_ws_url,
-                            if debug_ws_url.ends_with('/') { "" } else { "/" }
-                        )
-                    },
-                )
-                .header("content-type", "application/json")
-                .send()
-                .await
-            {
-                Ok(req) => {
-                    let socketaddr = req.remote_addr().unwrap();
-                    let connection: BrowserConnection =
-                        serde_json::from_slice(&req.bytes().await.unwrap_or_default())
-                            .unwrap_or_default();
-
-                    if !connection.web_socket_debugger_url.is_empty() {
-                        // prevent proxy interfaces from returning local ips to connect to the exact machine
-                        debug_ws_url = connection
-                            .web_socket_debugger_url
-                            .replace("127.0.0.1", &socketaddr.ip().to_string());
-                    }
-                }
-                Err(_) => return Err(CdpError::NoResponse),
-            }
+        let url = url.into();
+        if url.starts_with("http") {
+            Self::connect_with_client(DevToolsHttpClient::new(&url)?, config).await
+        } else {
+            Self::connect_to_websocket(url, None, config).await
+        }
+    }
+
+    /// Connect to the browser whose websocket is read from the `json/version`
+    /// endpoint with `client`, e.g. a remote browser that requires
+    /// authentication.
+    ///
+    /// The headers of the client are also sent with the websocket handshake.
+    pub async fn connect_with_client(
+        client: DevToolsHttpClient,
+        config: HandlerConfig,
+    ) -> Result<(Self, Handler)> {
+        let connection = client.version().await?;
+        if connection.web_socket_debugger_url.is_empty() {
+            return Err(DevToolsError::NoWebSocketUrl(client.base_url().to_string()).into());
         }
+        Self::connect_to_websocket(connection.web_socket_debugger_url, Some(client), config).await
+    }
+
+    /// Connect over the page-level websocket of a target, like the
+    /// `webSocketDebuggerUrl` of an entry of `/json/list`
+    /// (`ws://<host>/devtools/page/<target id>`), for endpoints that don't
+    /// expose the websocket of the browser.
+    ///
+    /// The page of the returned target is available via
+    /// [`Browser::get_page`] once the `Handler` is polled. Commands that
+    /// require the browser websocket, like creating browser contexts, may be
+    /// rejected by the browser.
+    pub async fn connect_to_target(
+        ws_url: impl AsRef<str>,
+        config: HandlerConfig,
+    ) -> Result<(Self, Handler, TargetId)> {
+        let ws_url = ws_url.as_ref();
+        let target_id = page_target_id(ws_url)?;
+        let (browser, handler) =
+            Self::connect_to_websocket(ws_url.to_string(), None, config).await?;
+        Ok((browser, handler, target_id))
+    }
+
+    /// Like [`Browser::connect_to_target`], but the protocol check uses
+    /// `client` and its headers are sent with the websocket handshake
+    pub async fn connect_to_target_with_client(
+        ws_url: impl AsRef<str>,
+        client: DevToolsHttpClient,
+        config: HandlerConfig,
+    ) -> Result<(Self, Handler, TargetId)> {
+        let ws_url = ws_url.as_ref();
+        let target_id = page_target_id(ws_url)?;
+        let (browser, handler) =
+            Self::connect_to_websocket(ws_url.to_string(), Some(client), config).await?;
+        Ok((browser, handler, target_id))
+    }
+
+    async fn connect_to_websocket(
+        debug_ws_url: String,
+        client: Option<DevToolsHttpClient>,
+        config: HandlerConfig,
+    ) -> Result<(Self, Handler)> {
+        config
+            .protocol_check
+            .verify(&debug_ws_url, client.as_ref())
+            .await?;
 
-        let conn = Connection::<CdpEventMessage>::connect(&debug_ws_url).await?;
+        let headers = client
+            .as_ref()
+            .map(|client| client.headers().clone())
+            .unwrap_or_default();
+        let conn =
+            Connection::<CdpEventMessage>::connect_with_headers(&debug_ws_url, headers.clone())
+                .await?;
 
         let (tx, rx) = channel(1);
 
-        let fut = Handler::new(conn, rx, config);
+        let mut fut = Handler::new(conn, rx, config);
+        fut.set_reconnect_source(ReconnectSource::WebSocket(debug_ws_url.clone(), headers));
         let browser_context = fut.default_browser_context().clone();
 
         let browser = Self {
             sender: tx,
             config: None,
             child: None,
+            temp_user_data_dir: None,
+            relaunched: fut.relaunched_process(),
             debug_ws_url,
+            devtools: client,
+            browser_context,
+        };
+        Ok((browser, fut))
+    }
+
+    /// Connect to a chromium instance over a custom [`Transport`], for
+    /// example a tunnel through another service or an in-memory pair created
+    /// with [`memory_pair`](crate::transport::memory_pair).
+    ///
+    /// The returned browser has no websocket address.
+    pub async fn connect_with_transport(
+        transport: impl Transport<CdpEventMessage> + 'static,
+        config: HandlerConfig,
+    ) -> Result<(Self, Handler)> {
+        let conn = Connection::new(transport);
+
+        let (tx, rx) = channel(1);
+
+        let fut = Handler::new(conn, rx, config);
+        let browser_context = fut.default_browser_context().clone();
+
+        let browser = Self {
+            sender: tx,
+            config: None,
+            child: None,
+            temp_user_data_dir: None,
+            relaunched: fut.relaunched_process(),
+            debug_ws_url: String::new(),
+            devtools: None,
             browser_context,
         };
         Ok((browser, fut))
@@ -148,60 +241,41 @@ impl Browser {
     /// Launches a new instance of `chromium` in the background and attaches to
     /// its debug web socket.
     ///
-    /// This fails when no chromium executable could be detected.
+    /// If no chromium executable was detected, it is fetched with the
+    /// options of [`BrowserConfigBuilder::fetch_if_missing`].
     ///
     /// This fails if no web socket url could be detected from the child
     /// processes stderr for more than the configured `launch_timeout`
     /// (20 seconds by default).
     pub async fn launch(mut config: BrowserConfig) -> Result<(Self, Handler)> {
+        #[cfg(feature = "fetcher")]
+        config.fetch_if_missing().await?;
+
         // Canonalize paths to reduce issues with sandboxing
         config.executable = utils::canonicalize(&config.executable).await?;
+        tracing::debug!(
+            "Launching {} ({:?})",
+            config.executable.display(),
+            config.executable_source
+        );
 
-        // Launch a new chromium instance
-        let mut child = config.launch()?;
-
-        /// Faillible initialization to run once the child process is created.
-        ///
-        /// All faillible calls must be executed inside this function. This ensures that all
-        /// errors are caught and that the child process is properly cleaned-up.
-        async fn with_child(
-            config: &BrowserConfig,
-            child: &mut Child,
-        ) -> Result<(String, Connection<CdpEventMessage>)> {
-            let dur = config.launch_timeout;
-            cfg_if::cfg_if! {
-                if #[cfg(feature = "async-std-runtime")] {
-                    let timeout_fut = Box::pin(async_std::task::sleep(dur));
-                } else if #[cfg(feature = "tokio-runtime")] {
-                    let timeout_fut = Box::pin(tokio::time::sleep(dur));
-                } else {
-                    panic!("missing chromiumoxide runtime: enable `async-std-runtime` or `tokio-runtime`")
-                }
-            };
-            // extract the ws:
-            let debug_ws_url = ws_url_from_output(child, timeout_fut).await?;
-            let conn = Connection::<CdpEventMessage>::connect(&debug_ws_url).await?;
-            Ok((debug_ws_url, conn))
+        let (mut child, debug_ws_url, conn, temp_dir) = config.spawn().await?;
+        if let Err(err) = config.protocol_check.verify(&debug_ws_url, None).await {
+            let _ = child.kill().await;
+            return Err(err);
         }
+        Ok(Self::launched(config, child, debug_ws_url, conn, temp_dir))
+    }
 
-        let (debug_ws_url, conn) = match with_child(&config, &mut child).await {
-            Ok(conn) => conn,
-            Err(e) => {
-                // An initialization error occurred, clean up the process
-                if let Ok(Some(_)) = child.try_wait() {
-                    // already exited, do nothing, may happen if the browser crashed
-                } else {
-                    // the process is still alive, kill it and wait for exit (avoid zombie processes)
-                    child.kill().await.expect("`Browser::launch` failed but could not clean-up the child process (`kill`)");
-                    child.wait().await.expect("`Browser::launch` failed but could not clean-up the child process (`wait`)");
-                }
-                return Err(e);
-            }
-        };
-
-        // Only infaillible calls are allowed after this point to avoid clean-up issues with the
-        // child process.
-
+    /// Creates the `Browser` and its `Handler` for a launched chromium
+    /// instance
+    fn launched(
+        config: BrowserConfig,
+        child: Child,
+        debug_ws_url: String,
+        conn: Connection<CdpEventMessage>,
+        temp_user_data_dir: Option<TempUserDataDir>,
+    ) -> (Self, Handler) {
         let (tx, rx) = channel(1);
 
         let handler_config = HandlerConfig {
@@ -211,20 +285,41 @@ impl Browser {
             request_timeout: config.request_timeout,
             request_intercept: config.request_intercept,
             cache_enabled: config.cache_enabled,
+            extra_headers: config.extra_headers.clone(),
+            credentials: config.credentials.clone(),
+            reconnect: config.reconnect.clone(),
+            metrics: config.metrics.clone(),
+            recorder: config.recorder.clone(),
+            protocol_check: config.protocol_check,
         };
 
-        let fut = Handler::new(conn, rx, handler_config);
+        let mut fut = Handler::new(conn, rx, handler_config);
+        match config.reconnect {
+            Some(ref policy) if policy.relaunch => {
+                fut.set_reconnect_source(ReconnectSource::Launch(Box::new(config.clone())));
+            }
+            _ if !debug_ws_url.is_empty() => {
+                fut.set_reconnect_source(ReconnectSource::WebSocket(
+                    debug_ws_url.clone(),
+                    Default::default(),
+                ));
+            }
+            _ => {}
+        }
         let browser_context = fut.default_browser_context().clone();
 
         let browser = Self {
             sender: tx,
             config: Some(config),
             child: Some(child),
+            temp_user_data_dir,
+            relaunched: fut.relaunched_process(),
             debug_ws_url,
+            devtools: None,
             browser_context,
         };
 
-        Ok((browser, fut))
+        (browser, fut)
     }
 
     /// Request to fetch all existing browser targets.
@@ -253,6 +348,9 @@ impl Browser {
     /// spawned instance exit, to avoid "zombie" processes ([`Browser::wait`],
     /// [`Browser::wait_sync`], [`Browser::try_wait`]).
     /// [`Browser::drop`] waits automatically if needed.
+    ///
+    /// If the browser was launched with a temporary user data dir, this waits
+    /// for the exit and removes the directory.
     pub async fn close(&mut self) -> Result<CloseReturns> {
         let (tx, rx) = oneshot_channel();
 
@@ -261,7 +359,11 @@ impl Browser {
             .send(HandlerMessage::CloseBrowser(tx))
             .await?;
 
-        rx.await?
+        let closed = rx.await??;
+        if self.temp_user_data_dir.is_some() {
+            self.wait().await?;
+        }
+        Ok(closed)
     }
 
     /// Asynchronously wait for the spawned chromium instance to exit completely.
@@ -273,8 +375,11 @@ impl Browser {
     /// This call has no effect if this [`Browser`] did not spawn any chromium instance (e.g.
     /// connected to an existing browser through [`Browser::connect`])
     pub async fn wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.take_relaunched();
         if let Some(child) = self.child.as_mut() {
-            Ok(Some(child.wait().await?))
+            let status = child.wait().await?;
+            self.temp_user_data_dir = None;
+            Ok(Some(status))
         } else {
             Ok(None)
         }
@@ -289,8 +394,13 @@ impl Browser {
     /// This call has no effect if this [`Browser`] did not spawn any chromium instance (e.g.
     /// connected to an existing browser through [`Browser::connect`])
     pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.take_relaunched();
         if let Some(child) = self.child.as_mut() {
-            child.try_wait()
+            let status = child.try_wait()?;
+            if status.is_some() {
+                self.temp_user_data_dir = None;
+            }
+            Ok(status)
         } else {
             Ok(None)
         }
@@ -307,6 +417,7 @@ impl Browser {
     /// This call has no effect if this [`Browser`] did not spawn any chromium instance (e.g.
     /// connected to an existing browser through [`Browser::connect`])
     pub fn get_mut_child(&mut self) -> Option<&mut Child> {
+        self.take_relaunched();
         self.child.as_mut()
     }
 
@@ -321,10 +432,38 @@ impl Browser {
     /// This call has no effect if this [`Browser`] did not spawn any chromium instance (e.g.
     /// connected to an existing browser through [`Browser::connect`])
     pub async fn kill(&mut self) -> Option<io::Result<()>> {
-        match self.child.as_mut() {
-            Some(child) => Some(child.kill().await),
-            None => None,
+        self.take_relaunched();
+        let child = self.child.as_mut()?;
+        let res = child.kill().await;
+        if res.is_ok() {
+            self.temp_user_data_dir = None;
+        }
+        Some(res)
+    }
+
+    /// Takes over the process the `Handler` launched after the connection was
+    /// lost, see [`ReconnectPolicy::relaunch`]
+    fn take_relaunched(&mut self) {
+        let Some(relaunched) = self.relaunched.lock().unwrap().take() else {
+            return;
+        };
+        let lost = self.child.replace(relaunched.child);
+        let lost_dir =
+            std::mem::replace(&mut self.temp_user_data_dir, relaunched.temp_user_data_dir);
+        if let (Some(lost), Some(lost_dir)) = (lost, lost_dir) {
+            // it may still be writing to its dir
+            kill_and_remove(lost, lost_dir);
         }
+        self.debug_ws_url = relaunched.debug_ws_url;
+    }
+
+    /// The temporary user data dir the browser was launched with, if no
+    /// `user_data_dir` was configured.
+    ///
+    /// It is removed once the browser exited, unless
+    /// [`BrowserConfigBuilder::keep_user_data_dir`] is set.
+    pub fn temp_user_data_dir(&self) -> Option<&Path> {
+        self.temp_user_data_dir.as_ref().map(|dir| dir.path())
     }
 
     /// If not launched as incognito this creates a new incognito browser
@@ -372,10 +511,24 @@ impl Browser {
     }
 
     /// Returns the address of the websocket this browser is attached to
+    ///
+    /// This is empty if the browser was launched with
+    /// [`LaunchTransport::Pipe`] or connected with a custom transport. After a
+    /// relaunch see [`Browser::current_websocket_address`].
     pub fn websocket_address(&self) -> &String {
         &self.debug_ws_url
     }
 
+    /// Returns the address of the websocket of the current browser process,
+    /// which differs from [`Browser::websocket_address`] once the process was
+    /// relaunched, see [`ReconnectPolicy::relaunch`]
+    pub fn current_websocket_address(&self) -> String {
+        match *self.relaunched.lock().unwrap() {
+            Some(ref relaunched) => relaunched.debug_ws_url.clone(),
+            None => self.debug_ws_url.clone(),
+        }
+    }
+
     /// Whether the BrowserContext is incognito.
     pub fn is_incognito(&self) -> bool {
         self.is_incognito_configured() || self.browser_context.is_incognito()
@@ -409,6 +562,29 @@ impl Browser {
         Ok(self.execute(GetVersionParams::default()).await?.result)
     }
 
+    /// Compares the protocol of the browser, from `Browser.getVersion` and
+    /// `/json/protocol`, with the protocol the types were generated from
+    ///
+    /// Browsers launched with [`LaunchTransport::Pipe`] have no http endpoints
+    /// to read the protocol from.
+    pub async fn protocol_compat(&self) -> Result<ProtocolCompat> {
+        let debug_ws_url = self.current_websocket_address();
+        if debug_ws_url.is_empty() {
+            return Err(DevToolsError::Unavailable.into());
+        }
+        let client = match self.devtools.clone() {
+            Some(client) => client,
+            None => DevToolsHttpClient::new(&debug_ws_url)?,
+        };
+        let version = self.version().await?;
+        let protocol = client.protocol_definition().await?;
+        Ok(ProtocolCompat::compare(
+            version.product,
+            version.protocol_version,
+            &protocol,
+        ))
+    }
+
     /// Returns the user agent of the browser
     pub async fn user_agent(&self) -> Result<String> {
         Ok(self.version().await?.user_agent)
@@ -450,15 +626,36 @@ impl Browser {
 
     /// Set listener for browser event
     pub async fn event_listener<T: IntoEventKind>(&self) -> Result<EventStream<T>> {
-        let (tx, rx) = unbounded();
+        self.event_listener_with(ListenerOptions::unbounded()).await
+    }
+
+    /// Set listener for browser event that buffers the events according to
+    /// `options`, see [`Page::event_listener_with`]
+    pub async fn event_listener_with<T: IntoEventKind>(
+        &self,
+        options: ListenerOptions,
+    ) -> Result<EventStream<T>> {
+        let (req, stream) = EventListenerRequest::with_options::<T>(options);
         self.sender
             .clone()
-            .send(HandlerMessage::AddEventListener(
-                EventListenerRequest::new::<T>(tx),
-            ))
+            .send(HandlerMessage::AddEventListener(req))
             .await?;
 
-        Ok(EventStream::new(rx))
+        Ok(stream)
+    }
+
+    /// Returns a stream of changes of the browser connection, like
+    /// disconnects, reconnects and crashed targets.
+    ///
+    /// After a [`LifecycleEvent::Reconnected`] all previous pages are gone and
+    /// need to be recreated.
+    pub async fn lifecycle_events(&self) -> Result<UnboundedReceiver<LifecycleEvent>> {
+        let (tx, rx) = unbounded();
+        self.sender
+            .clone()
+            .send(HandlerMessage::AddLifecycleListener(tx))
+            .await?;
+        Ok(rx)
     }
 
     /// Creates a new empty browser context.
@@ -470,6 +667,40 @@ impl Browser {
         Ok(response.result.browser_context_id)
     }
 
+    /// Creates a new isolated browser context and returns a handle to it.
+    ///
+    /// Pages opened with [`crate::BrowserContext::new_page`] don't share
+    /// cookies, cache or permissions with pages of other contexts.
+    ///
+    /// ```no_run
+    /// # use chromiumoxide::browser::Browser;
+    /// # use chromiumoxide::auth::Credentials;
+    /// # use chromiumoxide::context::BrowserContextOptions;
+    /// # use chromiumoxide::error::Result;
+    /// # async fn demo(browser: Browser) -> Result<()> {
+    ///     let ctx = browser
+    ///         .new_browser_context(
+    ///             BrowserContextOptions::builder()
+    ///                 .proxy_server("http://proxy.example.com:8080")
+    ///                 .proxy_credentials(Credentials {
+    ///                     username: "user".to_string(),
+    ///                     password: "secret".to_string(),
+    ///                 })
+    ///                 .build(),
+    ///         )
+    ///         .await?;
+    ///     let page = ctx.new_page("https://example.com").await?;
+    ///     ctx.close().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn new_browser_context(
+        &self,
+        options: impl Into<BrowserContextOptions>,
+    ) -> Result<crate::context::BrowserContext> {
+        crate::context::BrowserContext::create(options.into(), self.sender.clone()).await
+    }
+
     /// Deletes a browser context.
     pub async fn dispose_browser_context(
         &self,
@@ -484,6 +715,7 @@ impl Browser {
 
 impl Drop for Browser {
     fn drop(&mut self) {
+        self.take_relaunched();
         if let Some(child) = self.child.as_mut() {
             if let Ok(Some(_)) = child.try_wait() {
                 // Already exited, do nothing. Usually occurs after using the method close or kill.
@@ -496,11 +728,163 @@ impl Drop for Browser {
                 // do it himself since the runtime doesn't provide garantees as to when the reap occurs, so we
                 // warn him here.
                 tracing::warn!("Browser was not closed manually, it will be killed automatically in the background");
+                // the temporary user data dir can only be removed once the
+                // browser stopped writing to it
+                if let (Some(child), Some(dir)) =
+                    (self.child.take(), self.temp_user_data_dir.take())
+                {
+                    kill_and_remove(child, dir);
+                }
             }
         }
     }
 }
 
+/// How long to wait for a killed browser to exit before its temporary user
+/// data dir is removed anyway
+const REAP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Kills the browser and removes its temporary user data dir once it exited.
+///
+/// Waiting for the exit blocks, so it happens on a separate thread instead of
+/// the executor thread that dropped the `Browser`.
+fn kill_and_remove(mut child: Child, temp_user_data_dir: TempUserDataDir) {
+    // fails if the process already exited
+    let _ = child.start_kill();
+    std::thread::spawn(move || {
+        let deadline = std::time::Instant::now() + REAP_TIMEOUT;
+        while let Ok(None) = child.try_wait() {
+            if std::time::Instant::now() > deadline {
+                tracing::warn!("Browser did not exit after it was killed");
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        drop(temp_user_data_dir);
+    });
+}
+
+/// Completes after the `launch_timeout` of the config
+fn launch_timeout(dur: Duration) -> futures::future::BoxFuture<'static, ()> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "async-std-runtime")] {
+            Box::pin(async_std::task::sleep(dur))
+        } else if #[cfg(feature = "tokio-runtime")] {
+            Box::pin(tokio::time::sleep(dur))
+        } else {
+            panic!("missing chromiumoxide runtime: enable `async-std-runtime` or `tokio-runtime`")
+        }
+    }
+}
+
+/// Faillible initialization of a browser launched with a debugging port.
+///
+/// All faillible calls must be executed inside this function. This ensures that all
+/// errors are caught and that the child process is properly cleaned-up.
+async fn ws_connection(
+    child: &mut Child,
+    timeout_fut: impl Future<Output = ()> + Unpin,
+) -> Result<(String, Connection<CdpEventMessage>)> {
+    // extract the ws:
+    let debug_ws_url = ws_url_from_output(child, timeout_fut).await?;
+    let conn = Connection::<CdpEventMessage>::connect(&debug_ws_url).await?;
+    Ok((debug_ws_url, conn))
+}
+
+/// Waits until the browser launched with `--remote-debugging-pipe` answers a
+/// first command, the pipe counterpart of [`ws_url_from_output`].
+///
+/// The launch fails like [`ws_url_from_output`], with the browser's stderr
+/// output.
+#[cfg(unix)]
+async fn pipe_connection(
+    child_process: &mut Child,
+    mut transport: PipeTransport<CdpEventMessage>,
+    timeout_fut: impl Future<Output = ()> + Unpin,
+) -> Result<Connection<CdpEventMessage>> {
+    use futures::{AsyncBufReadExt, AsyncReadExt, FutureExt, StreamExt};
+
+    // the `Connection` counts its ids up from 0
+    let probe_id = CallId::new(usize::MAX);
+    transport
+        .send(MethodCall {
+            id: probe_id,
+            method: GetVersionParams::default().identifier(),
+            session_id: None,
+            params: serde_json::json!({}),
+        })
+        .await?;
+
+    let mut timeout_fut = timeout_fut.fuse();
+    let stderr = child_process.stderr.take().expect("no stderror");
+    let mut stderr_bytes = Vec::<u8>::new();
+    let mut stderr_open = true;
+    let mut pipe_open = true;
+    let mut exit_status_fut = Box::pin(child_process.wait()).fuse();
+    let mut buf = futures::io::BufReader::new(stderr);
+    loop {
+        select! {
+            _ = timeout_fut => return Err(CdpError::LaunchTimeout(BrowserStderr::new(stderr_bytes))),
+            exit_status = exit_status_fut => {
+                // the rest of the output of the exited browser
+                if stderr_open {
+                    select! {
+                        _ = buf.read_to_end(&mut stderr_bytes).fuse() => {},
+                        _ = timeout_fut => {},
+                    }
+                }
+                return Err(match exit_status {
+                    Err(e) => CdpError::LaunchIo(e, BrowserStderr::new(stderr_bytes)),
+                    Ok(exit_status) => CdpError::LaunchExit(exit_status, BrowserStderr::new(stderr_bytes)),
+                })
+            },
+            read_res = async {
+                if stderr_open {
+                    buf.read_until(b'\n', &mut stderr_bytes).await
+                } else {
+                    futures::future::pending().await
+                }
+            }.fuse() => {
+                match read_res {
+                    Err(e) => return Err(CdpError::LaunchIo(e, BrowserStderr::new(stderr_bytes))),
+                    Ok(byte_count) => stderr_open = byte_count > 0,
+                }
+            },
+            msg = async {
+                if pipe_open {
+                    transport.next().await
+                } else {
+                    futures::future::pending().await
+                }
+            }.fuse() => {
+                match msg {
+                    Some(Ok(Message::Response(resp))) if resp.id == probe_id => break,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                    // the browser is exiting, or hangs until the timeout
+                    None => pipe_open = false,
+                }
+            }
+        }
+    }
+
+    let traffic = transport.traffic();
+    Ok(Connection::new(transport).with_traffic(traffic))
+}
+
+/// The id of the target of a page-level websocket url
+fn page_target_id(ws_url: &str) -> Result<TargetId, DevToolsError> {
+    let invalid = || DevToolsError::InvalidUrl(ws_url.to_string());
+    let target_id = url::Url::parse(ws_url)
+        .map_err(|_| invalid())?
+        .path()
+        .split_once("/devtools/page/")
+        .map(|(_, id)| id.trim_end_matches('/').to_string())
+        .filter(|id| !id.is_empty() && !id.contains('/'))
+        .ok_or_else(invalid)?;
+    Ok(TargetId::new(target_id))
+}
+
 /// Resolve devtools WebSocket URL from the provided browser process
 ///
 /// If an error occurs, it returns the browser's stderr output.
@@ -562,20 +946,31 @@ async fn ws_url_from_output(
 #[derive(Debug, Clone)]
 pub struct BrowserConfig {
     /// Determines whether to run headless version of the browser. Defaults to
-    /// true.
-    headless: bool,
+    /// [`HeadlessMode::True`].
+    headless: HeadlessMode,
     /// Determines whether to run the browser with a sandbox.
     sandbox: bool,
     /// Launch the browser with a specific window width and height.
     window_size: Option<(u32, u32)>,
+    /// Launch the browser with its window at a specific position.
+    window_position: Option<(i32, i32)>,
     /// Launch the browser with a specific debugging port.
     port: u16,
+    /// How to talk to the launched browser
+    transport: LaunchTransport,
     /// Path for Chrome or Chromium.
     ///
     /// If unspecified, the create will try to automatically detect a suitable
     /// binary.
     executable: std::path::PathBuf,
 
+    /// How the executable was found
+    executable_source: ExecutableSource,
+
+    /// Downloads the browser on launch if no executable was detected
+    #[cfg(feature = "fetcher")]
+    fetch_if_missing: Option<BrowserFetcherOptions>,
+
     /// A list of Chrome extensions to load.
     ///
     /// An extension should be a path to a folder containing the extension code.
@@ -592,6 +987,13 @@ pub struct BrowserConfig {
     /// Data dir for user data
     pub user_data_dir: Option<PathBuf>,
 
+    /// Directory whose contents are copied into the temporary user data dir
+    /// of each launch, if no `user_data_dir` is set
+    user_data_template: Option<PathBuf>,
+
+    /// Whether to keep the temporary user data dir after the browser exited
+    keep_user_data_dir: bool,
+
     /// Whether to launch the `Browser` in incognito mode
     incognito: bool,
 
@@ -610,24 +1012,76 @@ pub struct BrowserConfig {
     /// Whether to disable DEFAULT_ARGS or not, default is false
     disable_default_args: bool,
 
+    /// Switches of DEFAULT_ARGS that are not passed to the browser
+    removed_default_args: Vec<String>,
+
+    /// The proxy server the browser sends its requests to
+    proxy_server: Option<String>,
+
+    /// Hosts the browser connects to without the proxy server
+    proxy_bypass_list: Vec<String>,
+
+    /// The language of the browser, replaces the default `en_US`
+    lang: Option<String>,
+
+    /// The user agent the browser sends
+    user_agent: Option<String>,
+
+    /// Whether to disable the GPU hardware acceleration
+    disable_gpu: bool,
+
+    /// Origins that are allowed to connect to the debugging websocket
+    remote_allow_origins: Vec<String>,
+
+    /// Features to enable, merged with the `--enable-features` of the args
+    enable_features: Vec<String>,
+
+    /// Features to disable, merged with the `--disable-features` of the args
+    disable_features: Vec<String>,
+
     /// Whether to enable request interception
     pub request_intercept: bool,
 
     /// Whether to enable cache
     pub cache_enabled: bool,
+
+    /// Extra http headers every page sends with its requests
+    pub extra_headers: HashMap<String, String>,
+
+    /// Credentials every page answers http authentication challenges with
+    pub credentials: Option<Credentials>,
+
+    /// How to recover from a lost connection to the browser
+    pub reconnect: Option<ReconnectPolicy>,
+
+    /// Receives measurements of the `Handler`
+    pub metrics: Option<Arc<dyn Metrics>>,
+
+    /// Records the protocol traffic with the browser
+    pub recorder: Option<Recorder>,
+
+    /// Whether to compare the protocol of the browser with the generated
+    /// types on launch
+    pub protocol_check: ProtocolCheck,
 }
 
 #[derive(Debug, Clone)]
 pub struct BrowserConfigBuilder {
-    headless: bool,
+    headless: HeadlessMode,
     sandbox: bool,
     window_size: Option<(u32, u32)>,
+    window_position: Option<(i32, i32)>,
     port: u16,
+    transport: LaunchTransport,
     executable: Option<PathBuf>,
     executation_detection: DetectionOptions,
+    #[cfg(feature = "fetcher")]
+    fetch_if_missing: Option<BrowserFetcherOptions>,
     extensions: Vec<String>,
     process_envs: Option<HashMap<String, String>>,
     user_data_dir: Option<PathBuf>,
+    user_data_template: Option<PathBuf>,
+    keep_user_data_dir: bool,
     incognito: bool,
     launch_timeout: Duration,
     ignore_https_errors: bool,
@@ -635,8 +1089,94 @@ pub struct BrowserConfigBuilder {
     request_timeout: Duration,
     args: Vec<String>,
     disable_default_args: bool,
+    removed_default_args: Vec<String>,
+    proxy_server: Option<String>,
+    proxy_bypass_list: Vec<String>,
+    lang: Option<String>,
+    user_agent: Option<String>,
+    disable_gpu: bool,
+    remote_allow_origins: Vec<String>,
+    enable_features: Vec<String>,
+    disable_features: Vec<String>,
     request_intercept: bool,
     cache_enabled: bool,
+    extra_headers: HashMap<String, String>,
+    credentials: Option<Credentials>,
+    reconnect: Option<ReconnectPolicy>,
+    metrics: Option<Arc<dyn Metrics>>,
+    recorder: Option<Recorder>,
+    protocol_check: ProtocolCheck,
+}
+
+/// How the executable of a [`BrowserConfig`] was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutableSource {
+    /// Set with [`BrowserConfigBuilder::chrome_executable`]
+    Configured,
+    /// Found by [`detection::default_executable`]
+    Detected,
+    /// Downloaded, or found in the cache, by the fetcher
+    Fetched,
+}
+
+/// Whether and how the browser runs headless
+///
+/// See <https://developer.chrome.com/docs/chromium/headless> for the
+/// differences between the modes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeadlessMode {
+    /// Run the browser with its UI
+    False,
+    /// Pass `--headless`, which runs the default headless mode of the
+    /// browser, the old mode before Chrome 112 and the new mode since
+    #[default]
+    True,
+    /// Pass `--headless=new`, which runs the regular browser without its UI
+    New,
+    /// Pass `--headless=old`, the separate old headless implementation of
+    /// browsers before Chrome 132, which removed it
+    Old,
+    /// The old headless implementation as separate binary, for Chrome 132
+    /// and later.
+    ///
+    /// This requires the `chrome-headless-shell` binary as executable, e.g.
+    /// fetched as `Artifact::ChromeHeadlessShell`, which always runs headless
+    /// and only gets `--headless`. Building the config fails if the
+    /// configured executable is another browser, a fetched one only logs a
+    /// warning.
+    Shell,
+}
+
+impl HeadlessMode {
+    /// The `--headless` switch of the mode
+    fn arg(&self) -> Option<&'static str> {
+        match self {
+            HeadlessMode::False => None,
+            HeadlessMode::True | HeadlessMode::Shell => Some("--headless"),
+            HeadlessMode::New => Some("--headless=new"),
+            HeadlessMode::Old => Some("--headless=old"),
+        }
+    }
+}
+
+/// Whether the executable is a `chrome-headless-shell` binary
+fn is_headless_shell(executable: &Path) -> bool {
+    executable.file_stem() == Some(OsStr::new("chrome-headless-shell"))
+}
+
+/// The channel chromiumoxide uses to talk to a launched browser
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LaunchTransport {
+    /// Connect to the debug websocket, whose url is read from the browser's
+    /// stderr
+    #[default]
+    WebSocket,
+    /// Launch the browser with `--remote-debugging-pipe` and exchange the
+    /// messages over the file descriptors 3 and 4 of the browser process.
+    ///
+    /// No debugging port is opened and no output of the browser needs to be
+    /// parsed. Only supported on unix.
+    Pipe,
 }
 
 impl BrowserConfig {
@@ -652,15 +1192,21 @@ impl BrowserConfig {
 impl Default for BrowserConfigBuilder {
     fn default() -> Self {
         Self {
-            headless: true,
+            headless: HeadlessMode::default(),
             sandbox: true,
             window_size: None,
+            window_position: None,
             port: 0,
+            transport: LaunchTransport::default(),
             executable: None,
             executation_detection: DetectionOptions::default(),
+            #[cfg(feature = "fetcher")]
+            fetch_if_missing: None,
             extensions: Vec::new(),
             process_envs: None,
             user_data_dir: None,
+            user_data_template: None,
+            keep_user_data_dir: false,
             incognito: false,
             launch_timeout: Duration::from_millis(LAUNCH_TIMEOUT),
             ignore_https_errors: true,
@@ -668,8 +1214,23 @@ impl Default for BrowserConfigBuilder {
             request_timeout: Duration::from_millis(REQUEST_TIMEOUT),
             args: Vec::new(),
             disable_default_args: false,
+            removed_default_args: Vec::new(),
+            proxy_server: None,
+            proxy_bypass_list: Vec::new(),
+            lang: None,
+            user_agent: None,
+            disable_gpu: false,
+            remote_allow_origins: Vec::new(),
+            enable_features: Vec::new(),
+            disable_features: Vec::new(),
             request_intercept: false,
             cache_enabled: true,
+            extra_headers: HashMap::new(),
+            credentials: None,
+            reconnect: None,
+            metrics: None,
+            recorder: None,
+            protocol_check: ProtocolCheck::Ignore,
         }
     }
 }
@@ -680,13 +1241,25 @@ impl BrowserConfigBuilder {
         self
     }
 
+    /// Launches the browser window at `x`, `y` of the screen
+    pub fn window_position(mut self, x: i32, y: i32) -> Self {
+        self.window_position = Some((x, y));
+        self
+    }
+
     pub fn no_sandbox(mut self) -> Self {
         self.sandbox = false;
         self
     }
 
     pub fn with_head(mut self) -> Self {
-        self.headless = false;
+        self.headless = HeadlessMode::False;
+        self
+    }
+
+    /// Whether and how the browser runs headless, see [`HeadlessMode`]
+    pub fn headless_mode(mut self, mode: HeadlessMode) -> Self {
+        self.headless = mode;
         self
     }
 
@@ -705,6 +1278,12 @@ impl BrowserConfigBuilder {
         self
     }
 
+    /// How to talk to the launched browser, see [`LaunchTransport`]
+    pub fn transport(mut self, transport: LaunchTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
     pub fn launch_timeout(mut self, timeout: Duration) -> Self {
         self.launch_timeout = timeout;
         self
@@ -725,11 +1304,35 @@ impl BrowserConfigBuilder {
         self
     }
 
+    /// Launches the browser with the user data dir `data_dir`, which is left
+    /// as it is.
+    ///
+    /// By default each launch gets a new temporary user data dir, which is
+    /// removed once the browser exited.
     pub fn user_data_dir(mut self, data_dir: impl AsRef<Path>) -> Self {
         self.user_data_dir = Some(data_dir.as_ref().to_path_buf());
         self
     }
 
+    /// Copies the contents of `template`, like the `Default/Preferences` or
+    /// extensions of a profile, into the temporary user data dir of each
+    /// launch. Symlinks are not copied.
+    ///
+    /// Has no effect if a [`BrowserConfigBuilder::user_data_dir`] is set, or
+    /// on the shared dir of [`BrowserConfig::launch`].
+    pub fn user_data_template(mut self, template: impl AsRef<Path>) -> Self {
+        self.user_data_template = Some(template.as_ref().to_path_buf());
+        self
+    }
+
+    /// Keeps the temporary user data dir after the browser exited, e.g. to
+    /// inspect it for debugging. Its path is logged when the browser is
+    /// closed.
+    pub fn keep_user_data_dir(mut self) -> Self {
+        self.keep_user_data_dir = true;
+        self
+    }
+
     pub fn chrome_executable(mut self, path: impl AsRef<Path>) -> Self {
         self.executable = Some(path.as_ref().to_path_buf());
         self
@@ -740,6 +1343,17 @@ impl BrowserConfigBuilder {
         self
     }
 
+    /// Downloads the browser with the `options` when [`Browser::launch`] is
+    /// called, if no executable was configured or detected.
+    ///
+    /// The fetcher defaults to the chromium revision whose protocol
+    /// chromiumoxide was generated from.
+    #[cfg(feature = "fetcher")]
+    pub fn fetch_if_missing(mut self, options: BrowserFetcherOptions) -> Self {
+        self.fetch_if_missing = Some(options);
+        self
+    }
+
     pub fn extension(mut self, extension: impl Into<String>) -> Self {
         self.extensions.push(extension.into());
         self
@@ -796,6 +1410,101 @@ impl BrowserConfigBuilder {
         self
     }
 
+    /// Doesn't pass the default arg with the switch of `arg` to the browser,
+    /// e.g. `--disable-extensions`. A value of `arg` is ignored.
+    pub fn remove_default_arg(mut self, arg: impl Into<String>) -> Self {
+        self.removed_default_args.push(arg.into());
+        self
+    }
+
+    /// Doesn't pass the default args with the switches of `args` to the
+    /// browser, see [`BrowserConfigBuilder::remove_default_arg`]
+    pub fn remove_default_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.removed_default_args
+            .extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sends all requests through the proxy `server`, e.g.
+    /// `http://localhost:8080` or `socks5://localhost:1080`
+    pub fn proxy_server(mut self, server: impl Into<String>) -> Self {
+        self.proxy_server = Some(server.into());
+        self
+    }
+
+    /// Connects to `hosts` without the proxy server, e.g. `*.example.com` or
+    /// `<-loopback>`
+    pub fn proxy_bypass_list<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.proxy_bypass_list
+            .extend(hosts.into_iter().map(Into::into));
+        self
+    }
+
+    /// The language of the browser, e.g. `de-DE`, which defaults to `en_US`
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// The user agent the browser sends instead of its own
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn disable_gpu(mut self) -> Self {
+        self.disable_gpu = true;
+        self
+    }
+
+    /// Origins that are allowed to connect to the debugging websocket, e.g.
+    /// `http://localhost:3000` or `*`
+    pub fn remote_allow_origins<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.remote_allow_origins
+            .extend(origins.into_iter().map(Into::into));
+        self
+    }
+
+    /// Enables the browser `features`.
+    ///
+    /// All `--enable-features` and `--disable-features` of the default args,
+    /// these features and the args are merged into one switch each. A feature
+    /// that is enabled and disabled keeps the state it was given last, in that
+    /// order.
+    pub fn enable_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.enable_features
+            .extend(features.into_iter().map(Into::into));
+        self
+    }
+
+    /// Disables the browser `features`, see
+    /// [`BrowserConfigBuilder::enable_features`]
+    pub fn disable_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.disable_features
+            .extend(features.into_iter().map(Into::into));
+        self
+    }
+
     pub fn enable_request_intercept(mut self) -> Self {
         self.request_intercept = true;
         self
@@ -816,22 +1525,96 @@ impl BrowserConfigBuilder {
         self
     }
 
+    /// Adds an extra http header that every page sends with its requests.
+    pub fn extra_http_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds extra http headers that every page sends with its requests.
+    pub fn extra_http_headers<I, K, V>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.extra_headers
+            .extend(headers.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Credentials every page answers http authentication challenges with.
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Reconnects to the browser, or relaunches it, when the connection is
+    /// lost instead of stopping the `Handler`.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Reports the measurements of the `Handler` to `metrics`
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Records all messages exchanged with the browser, they can be played
+    /// back with a [`ReplayTransport`](crate::transport::ReplayTransport)
+    pub fn record(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Compares the protocol of the launched browser with the generated
+    /// types, see [`ProtocolCheck`]
+    pub fn protocol_check(mut self, check: ProtocolCheck) -> Self {
+        self.protocol_check = check;
+        self
+    }
+
     pub fn build(self) -> std::result::Result<BrowserConfig, String> {
-        let executable = if let Some(e) = self.executable {
-            e
-        } else {
-            detection::default_executable(self.executation_detection)?
+        let (executable, executable_source) = match self.executable {
+            Some(e) => (e, ExecutableSource::Configured),
+            None => match detection::default_executable(self.executation_detection) {
+                Ok(e) => (e, ExecutableSource::Detected),
+                // fetched on launch
+                #[cfg(feature = "fetcher")]
+                Err(_) if self.fetch_if_missing.is_some() => {
+                    (PathBuf::new(), ExecutableSource::Fetched)
+                }
+                Err(err) => return Err(err),
+            },
         };
+        if self.headless == HeadlessMode::Shell
+            && executable_source != ExecutableSource::Fetched
+            && !is_headless_shell(&executable)
+        {
+            return Err(format!(
+                "HeadlessMode::Shell requires chrome-headless-shell, not {}",
+                executable.display()
+            ));
+        }
 
         Ok(BrowserConfig {
             headless: self.headless,
             sandbox: self.sandbox,
             window_size: self.window_size,
+            window_position: self.window_position,
             port: self.port,
+            transport: self.transport,
             executable,
+            executable_source,
+            #[cfg(feature = "fetcher")]
+            fetch_if_missing: self.fetch_if_missing,
             extensions: self.extensions,
             process_envs: self.process_envs,
             user_data_dir: self.user_data_dir,
+            user_data_template: self.user_data_template,
+            keep_user_data_dir: self.keep_user_data_dir,
             incognito: self.incognito,
             launch_timeout: self.launch_timeout,
             ignore_https_errors: self.ignore_https_errors,
@@ -839,21 +1622,160 @@ impl BrowserConfigBuilder {
             request_timeout: self.request_timeout,
             args: self.args,
             disable_default_args: self.disable_default_args,
+            removed_default_args: self.removed_default_args,
+            proxy_server: self.proxy_server,
+            proxy_bypass_list: self.proxy_bypass_list,
+            lang: self.lang,
+            user_agent: self.user_agent,
+            disable_gpu: self.disable_gpu,
+            remote_allow_origins: self.remote_allow_origins,
+            enable_features: self.enable_features,
+            disable_features: self.disable_features,
             request_intercept: self.request_intercept,
             cache_enabled: self.cache_enabled,
+            extra_headers: self.extra_headers,
+            credentials: self.credentials,
+            reconnect: self.reconnect,
+            metrics: self.metrics,
+            recorder: self.recorder,
+            protocol_check: self.protocol_check,
         })
     }
 }
 
 impl BrowserConfig {
-    pub fn launch(&self) -> io::Result<Child> {
-        let mut cmd = async_process::Command::new(&self.executable);
+    /// The path of the browser executable, empty until it is fetched on
+    /// launch
+    pub fn executable(&self) -> &Path {
+        &self.executable
+    }
+
+    /// How the browser executable was found
+    pub fn executable_source(&self) -> ExecutableSource {
+        self.executable_source
+    }
 
-        if self.disable_default_args {
-            cmd.args(&self.args);
+    /// Fetches the browser if no executable was found when building the
+    /// config
+    #[cfg(feature = "fetcher")]
+    async fn fetch_if_missing(&mut self) -> Result<()> {
+        if !self.executable.as_os_str().is_empty() {
+            return Ok(());
+        }
+        if let Some(options) = self.fetch_if_missing.take() {
+            let info = BrowserFetcher::new(options).fetch().await?;
+            tracing::info!("Fetched browser {info}");
+            if self.headless == HeadlessMode::Shell && !is_headless_shell(&info.executable_path) {
+                tracing::warn!(
+                    "HeadlessMode::Shell requires chrome-headless-shell, running {} with --headless",
+                    info.executable_path.display()
+                );
+            }
+            self.executable = info.executable_path;
+        }
+        Ok(())
+    }
+
+    /// Spawns the browser with the configured transport and connects to it.
+    ///
+    /// The process is killed again if connecting fails.
+    ///
+    /// Without a configured `user_data_dir` the browser gets a new temporary
+    /// one, which is returned to be removed once the process exited.
+    pub(crate) async fn spawn(
+        &self,
+    ) -> Result<(
+        Child,
+        String,
+        Connection<CdpEventMessage>,
+        Option<TempUserDataDir>,
+    )> {
+        let (user_data_dir, temp_dir) = self.launch_user_data_dir()?;
+        let timeout_fut = launch_timeout(self.launch_timeout);
+        let (mut child, res) = if self.transport == LaunchTransport::Pipe {
+            cfg_if::cfg_if! {
+                if #[cfg(unix)] {
+                    let (mut child, transport) = self.spawn_with_pipe(&user_data_dir)?;
+                    let res = pipe_connection(&mut child, transport, timeout_fut)
+                        .await
+                        .map(|conn| (String::new(), conn));
+                    (child, res)
+                } else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "`--remote-debugging-pipe` is only supported on unix",
+                    )
+                    .into());
+                }
+            }
         } else {
-            cmd.args(DEFAULT_ARGS).args(&self.args);
+            // Launch a new chromium instance
+            let mut child = self.spawn_with_port(&user_data_dir)?;
+            let res = ws_connection(&mut child, timeout_fut).await;
+            (child, res)
+        };
+
+        let (debug_ws_url, conn) = match res {
+            Ok(conn) => conn,
+            Err(e) => {
+                // An initialization error occurred, clean up the process
+                if let Ok(Some(_)) = child.try_wait() {
+                    // already exited, do nothing, may happen if the browser crashed
+                } else {
+                    // the process is still alive, kill it and wait for exit (avoid zombie processes)
+                    child.kill().await.expect("`Browser::launch` failed but could not clean-up the child process (`kill`)");
+                    child.wait().await.expect("`Browser::launch` failed but could not clean-up the child process (`wait`)");
+                }
+                return Err(e);
+            }
+        };
+
+        Ok((child, debug_ws_url, conn, temp_dir))
+    }
+
+    /// Spawns the browser with a debugging port, whose websocket url it
+    /// prints to stderr.
+    ///
+    /// Without a configured `user_data_dir` the browser uses the shared
+    /// `chromiumoxide-runner` dir in the temp dir of the system, only
+    /// [`Browser::launch`] creates a temporary one per launch.
+    pub fn launch(&self) -> io::Result<Child> {
+        self.spawn_with_port(&self.shared_user_data_dir())
+    }
+
+    /// Spawns the browser with `--remote-debugging-pipe` and returns the
+    /// transport to talk to it.
+    ///
+    /// The user data dir is chosen like for [`BrowserConfig::launch`].
+    #[cfg(unix)]
+    pub fn launch_with_pipe<T>(&self) -> io::Result<(Child, PipeTransport<T>)> {
+        self.spawn_with_pipe(&self.shared_user_data_dir())
+    }
+
+    /// The configured user data dir, or the one shared by all processes
+    /// spawned without a `Browser` that could remove a temporary one
+    fn shared_user_data_dir(&self) -> PathBuf {
+        self.user_data_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("chromiumoxide-runner"))
+    }
+
+    /// The configured user data dir, or a new temporary one seeded with the
+    /// template
+    fn launch_user_data_dir(&self) -> io::Result<(PathBuf, Option<TempUserDataDir>)> {
+        if let Some(ref user_data_dir) = self.user_data_dir {
+            return Ok((user_data_dir.clone(), None));
         }
+        // If the user did not specify a data directory, this would default to the systems default
+        // data directory. In most cases, we would rather have a fresh instance of Chromium. Create
+        // a temp dir just for this launch instead.
+        let temp_dir =
+            TempUserDataDir::create(self.user_data_template.as_deref(), self.keep_user_data_dir)?;
+        Ok((temp_dir.path().to_path_buf(), Some(temp_dir)))
+    }
+
+    fn spawn_with_port(&self, user_data_dir: &Path) -> io::Result<Child> {
+        let mut cmd = self.command(user_data_dir);
 
         if !self
             .args
@@ -862,45 +1784,139 @@ impl BrowserConfig {
         {
             cmd.arg(format!("--remote-debugging-port={}", self.port));
         }
+        async_process::Command::from(cmd)
+            .stderr(Stdio::piped())
+            .spawn()
+    }
 
-        cmd.args(
-            self.extensions
-                .iter()
-                .map(|e| format!("--load-extension={e}")),
-        );
+    #[cfg(unix)]
+    fn spawn_with_pipe<T>(&self, user_data_dir: &Path) -> io::Result<(Child, PipeTransport<T>)> {
+        let pipes = BrowserPipes::new()?;
+        let mut cmd = self.command(user_data_dir);
+        cmd.arg("--remote-debugging-pipe");
+        pipes.attach(&mut cmd);
+
+        let child = async_process::Command::from(cmd)
+            .stderr(Stdio::piped())
+            .spawn()?;
+        Ok((child, pipes.into_transport()))
+    }
 
-        if let Some(ref user_data) = self.user_data_dir {
-            cmd.arg(format!("--user-data-dir={}", user_data.display()));
-        } else {
-            // If the user did not specify a data directory, this would default to the systems default
-            // data directory. In most cases, we would rather have a fresh instance of Chromium. Specify
-            // a temp dir just for chromiumoxide instead.
-            cmd.arg(format!(
-                "--user-data-dir={}",
-                std::env::temp_dir().join("chromiumoxide-runner").display()
-            ));
+    /// The command to spawn the browser with, without any debugging
+    /// transport
+    fn command(&self, user_data_dir: &Path) -> std::process::Command {
+        let mut cmd = std::process::Command::new(&self.executable);
+        cmd.args(self.browser_args(user_data_dir));
+
+        if let Some(ref envs) = self.process_envs {
+            cmd.envs(envs);
         }
+        cmd
+    }
+
+    /// The args the browser is launched with.
+    ///
+    /// The default args come first, followed by the configured options and
+    /// the additional args. If a switch is passed more than once, the last
+    /// one wins, except for the feature lists which are merged.
+    fn browser_args(&self, user_data_dir: &Path) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        if !self.disable_default_args {
+            args.extend(
+                DEFAULT_ARGS
+                    .iter()
+                    .filter(|arg| {
+                        !self
+                            .removed_default_args
+                            .iter()
+                            .any(|removed| switch_name(removed) == switch_name(arg))
+                    })
+                    .map(|arg| arg.to_string()),
+            );
+        }
+
+        args.push(format!("--user-data-dir={}", user_data_dir.display()));
 
         if let Some((width, height)) = self.window_size {
-            cmd.arg(format!("--window-size={width},{height}"));
+            args.push(format!("--window-size={width},{height}"));
+        }
+
+        if let Some((x, y)) = self.window_position {
+            args.push(format!("--window-position={x},{y}"));
         }
 
         if !self.sandbox {
-            cmd.args(["--no-sandbox", "--disable-setuid-sandbox"]);
+            args.extend([
+                "--no-sandbox".to_string(),
+                "--disable-setuid-sandbox".to_string(),
+            ]);
         }
 
-        if self.headless {
-            cmd.args(["--headless", "--hide-scrollbars", "--mute-audio"]);
+        if let Some(headless) = self.headless.arg() {
+            args.extend([
+                headless.to_string(),
+                "--hide-scrollbars".to_string(),
+                "--mute-audio".to_string(),
+            ]);
         }
 
         if self.incognito {
-            cmd.arg("--incognito");
+            args.push("--incognito".to_string());
         }
 
-        if let Some(ref envs) = self.process_envs {
-            cmd.envs(envs);
+        if let Some(ref server) = self.proxy_server {
+            args.push(format!("--proxy-server={server}"));
+        }
+
+        if !self.proxy_bypass_list.is_empty() {
+            args.push(format!(
+                "--proxy-bypass-list={}",
+                self.proxy_bypass_list.join(";")
+            ));
         }
-        cmd.stderr(Stdio::piped()).spawn()
+
+        if let Some(ref lang) = self.lang {
+            args.push(format!("--lang={lang}"));
+        }
+
+        if let Some(ref user_agent) = self.user_agent {
+            args.push(format!("--user-agent={user_agent}"));
+        }
+
+        if self.disable_gpu {
+            args.push("--disable-gpu".to_string());
+        }
+
+        if !self.remote_allow_origins.is_empty() {
+            args.push(format!(
+                "--remote-allow-origins={}",
+                self.remote_allow_origins.join(",")
+            ));
+        }
+
+        if !self.enable_features.is_empty() {
+            args.push(format!(
+                "--enable-features={}",
+                self.enable_features.join(",")
+            ));
+        }
+
+        if !self.disable_features.is_empty() {
+            args.push(format!(
+                "--disable-features={}",
+                self.disable_features.join(",")
+            ));
+        }
+
+        args.extend(self.args.iter().cloned());
+
+        let mut args = merge_args(args);
+        args.extend(
+            self.extensions
+                .iter()
+                .map(|e| format!("--load-extension={e}")),
+        );
+        args
     }
 }
 
@@ -914,13 +1930,90 @@ impl BrowserConfig {
 /// Windows) is consulted. If all of the above fail, an error is returned.
 #[deprecated(note = "Use detection::default_executable instead")]
 pub fn default_executable() -> Result<std::path::PathBuf, String> {
-    let options = DetectionOptions {
-        msedge: false,
-        unstable: false,
-    };
+    let options = DetectionOptions::default()
+        .msedge(false)
+        .unstable(false)
+        .distro(false)
+        .brave(false);
     detection::default_executable(options)
 }
 
+/// Switches whose comma separated lists are merged, as enable and disable
+/// pair, instead of the last one winning
+const LIST_SWITCHES: [(&str, &str); 2] = [
+    ("enable-features", "disable-features"),
+    ("enable-blink-features", "disable-blink-features"),
+];
+
+/// The name of the switch of `arg` without dashes and value, e.g. `lang` for
+/// `--lang=en_US`
+fn switch_name(arg: &str) -> &str {
+    let arg = arg.trim_start_matches('-');
+    arg.split_once('=').map_or(arg, |(name, _)| name)
+}
+
+/// Keeps the last occurrence of each switch and merges the lists of
+/// [`LIST_SWITCHES`], which are appended after the other args.
+///
+/// Args that are no switches, like a url to open, are kept as they are.
+fn merge_args(args: Vec<String>) -> Vec<String> {
+    // (enabled, disabled) of each pair in `LIST_SWITCHES`
+    let mut lists: Vec<(Vec<String>, Vec<String>)> = vec![Default::default(); LIST_SWITCHES.len()];
+    let mut merged = Vec::with_capacity(args.len());
+    let mut seen = HashSet::new();
+
+    for arg in args.into_iter().rev() {
+        if !arg.starts_with('-') {
+            merged.push(arg);
+            continue;
+        }
+        let name = switch_name(&arg);
+        let list = LIST_SWITCHES
+            .iter()
+            .enumerate()
+            .find_map(|(idx, (enable, disable))| {
+                if name == *enable {
+                    Some((idx, true))
+                } else if name == *disable {
+                    Some((idx, false))
+                } else {
+                    None
+                }
+            });
+        if let Some((idx, enable)) = list {
+            let (enabled, disabled) = &mut lists[idx];
+            let values = arg.split_once('=').map_or("", |(_, values)| values);
+            // walking backwards, the first mention of a feature is the last
+            // one passed
+            for value in values.split(',').rev().filter(|value| !value.is_empty()) {
+                if enabled.iter().chain(disabled.iter()).any(|v| v == value) {
+                    continue;
+                }
+                if enable {
+                    enabled.push(value.to_string());
+                } else {
+                    disabled.push(value.to_string());
+                }
+            }
+            continue;
+        }
+        if seen.insert(name.to_string()) {
+            merged.push(arg);
+        }
+    }
+    merged.reverse();
+
+    for ((enable, disable), (enabled, disabled)) in LIST_SWITCHES.iter().zip(lists) {
+        for (switch, mut values) in [(enable, enabled), (disable, disabled)] {
+            if !values.is_empty() {
+                values.reverse();
+                merged.push(format!("--{switch}={}", values.join(",")));
+            }
+        }
+    }
+    merged
+}
+
 /// These are passed to the Chrome binary by default.
 /// Via https://github.com/puppeteer/puppeteer/blob/4846b8723cf20d3551c0d755df394cc5e0c82a94/src/node/Launcher.ts#L157
 static DEFAULT_ARGS: [&str; 25] = [
@@ -950,3 +2043,170 @@ static DEFAULT_ARGS: [&str; 25] = [
     "--enable-blink-features=IdleDetection",
     "--lang=en_US",
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(builder: BrowserConfigBuilder) -> Vec<String> {
+        builder
+            .chrome_executable("chrome")
+            .build()
+            .unwrap()
+            .browser_args(Path::new("profile"))
+    }
+
+    #[test]
+    fn merges_args() {
+        let args = args(
+            BrowserConfig::builder()
+                .headless_mode(HeadlessMode::New)
+                .lang("de-DE")
+                .remove_default_arg("--disable-extensions")
+                .proxy_bypass_list(["localhost", "*.example.com"])
+                .enable_features(["Foo"])
+                .disable_features(["NetworkService"])
+                .arg("--enable-features=Bar,TranslateUI")
+                .arg("--window-size=1,2")
+                .arg("about:blank"),
+        );
+        let count = |name: &str| args.iter().filter(|a| switch_name(a) == name).count();
+
+        assert!(args.contains(&"--headless=new".to_string()));
+        assert!(args.contains(&"--lang=de-DE".to_string()));
+        assert_eq!(count("lang"), 1);
+        assert_eq!(count("disable-extensions"), 0);
+        assert!(args.contains(&"--disable-sync".to_string()));
+        assert!(args.contains(&"--proxy-bypass-list=localhost;*.example.com".to_string()));
+        assert!(args.contains(&"about:blank".to_string()));
+        assert_eq!(
+            args.iter()
+                .filter(|a| a.starts_with("--window-size"))
+                .collect::<Vec<_>>(),
+            ["--window-size=1,2"]
+        );
+        assert!(args.contains(
+            &"--enable-features=NetworkServiceInProcess,Foo,Bar,TranslateUI".to_string()
+        ));
+        assert!(args.contains(&"--disable-features=NetworkService".to_string()));
+        assert_eq!(count("enable-features"), 1);
+        assert_eq!(count("disable-features"), 1);
+    }
+
+    #[test]
+    fn headless_modes() {
+        let old = args(BrowserConfig::builder().headless_mode(HeadlessMode::Old));
+        assert!(old.contains(&"--headless=old".to_string()));
+
+        let shell = BrowserConfig::builder()
+            .headless_mode(HeadlessMode::Shell)
+            .chrome_executable("/opt/chrome-headless-shell/chrome-headless-shell")
+            .build()
+            .unwrap();
+        assert!(shell
+            .browser_args(Path::new("profile"))
+            .contains(&"--headless".to_string()));
+        assert!(BrowserConfig::builder()
+            .headless_mode(HeadlessMode::Shell)
+            .chrome_executable("/usr/bin/google-chrome")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn disables_default_args() {
+        let args = args(
+            BrowserConfig::builder()
+                .with_head()
+                .disable_default_args()
+                .disable_gpu(),
+        );
+        assert!(!args.iter().any(|a| a.starts_with("--headless")));
+        assert!(!args.contains(&"--disable-sync".to_string()));
+        assert!(args.contains(&"--disable-gpu".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[async_std::test]
+    async fn drop_kills_the_browser_and_removes_its_profile() {
+        let dir = TempUserDataDir::create(None, false).unwrap();
+        let path = dir.path().to_path_buf();
+        // keeps writing to the profile like a running browser, only with
+        // builtins so no other process writes to it once the shell is killed
+        let mut child = async_process::Command::new("sh")
+            .arg("-c")
+            .arg(r#"while :; do : > "$0/Preferences"; done"#)
+            .arg(&path)
+            .spawn()
+            .unwrap();
+        let pid = child.as_mut_inner().id().to_string();
+        let (transport, _peer) = crate::transport::memory_pair::<CdpEventMessage>();
+        let (browser, _handler) = Browser::launched(
+            BrowserConfig::with_executable("chrome"),
+            child,
+            String::new(),
+            Connection::new(transport),
+            Some(dir),
+        );
+        async_std::task::sleep(Duration::from_millis(50)).await;
+
+        drop(browser);
+        for _ in 0..500 {
+            if !path.exists() {
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!path.exists());
+        // the dir is only removed after the process was reaped
+        let status = std::process::Command::new("kill")
+            .args(["-0", &pid])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!status.success());
+    }
+
+    /// Writes an executable shell script to use as browser
+    #[cfg(unix)]
+    fn fake_browser(name: &str, script: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path =
+            std::env::temp_dir().join(format!("chromiumoxide-{name}-{}.sh", std::process::id()));
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[async_std::test]
+    async fn pipe_launch_reports_failures() {
+        let crashing = fake_browser("crashing", "echo 'no display' >&2; exit 3");
+        let config = BrowserConfig::builder()
+            .chrome_executable(&crashing)
+            .transport(LaunchTransport::Pipe)
+            .build()
+            .unwrap();
+        match Browser::launch(config).await {
+            Err(CdpError::LaunchExit(status, stderr)) => {
+                assert_eq!(status.code(), Some(3));
+                assert_eq!(stderr.as_slice(), b"no display\n");
+            }
+            res => panic!("{:?}", res.err()),
+        }
+
+        let hanging = fake_browser("hanging", "exec sleep 30");
+        let config = BrowserConfig::builder()
+            .chrome_executable(&hanging)
+            .transport(LaunchTransport::Pipe)
+            .launch_timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let err = Browser::launch(config).await.err().unwrap();
+        assert!(matches!(err, CdpError::LaunchTimeout(_)), "{err:?}");
+
+        std::fs::remove_file(crashing).unwrap();
+        std::fs::remove_file(hanging).unwrap();
+    }
+}
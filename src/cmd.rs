@@ -32,6 +32,23 @@ pub(crate) fn to_command_response<T: Command>(
     }
 }
 
+/// Overrides for a single command, see [`Page::execute_with`](crate::Page::execute_with)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOptions {
+    /// How long to wait for the response, instead of the
+    /// `HandlerConfig::request_timeout`
+    pub timeout: Option<Duration>,
+}
+
+impl CommandOptions {
+    /// Options that wait `timeout` for the response
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+        }
+    }
+}
+
 /// Messages used internally to communicate with the connection, which is
 /// executed in the the background task.
 #[derive(Debug, Serialize)]
@@ -42,6 +59,10 @@ pub struct CommandMessage<T = Result<Response>> {
     pub params: serde_json::Value,
     #[serde(skip_serializing)]
     pub sender: OneshotSender<T>,
+    /// When the command times out, if it overrides the
+    /// `HandlerConfig::request_timeout`
+    #[serde(skip_serializing)]
+    pub deadline: Option<Instant>,
 }
 
 impl<T> CommandMessage<T> {
@@ -51,9 +72,17 @@ impl<T> CommandMessage<T> {
             session_id: None,
             params: serde_json::to_value(cmd)?,
             sender,
+            deadline: None,
         })
     }
 
+    /// Times the command out after `timeout` instead of the
+    /// `HandlerConfig::request_timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
+        self
+    }
+
     /// Whether this command is a navigation
     pub fn is_navigation(&self) -> bool {
         self.method.as_ref() == NavigateParams::IDENTIFIER
@@ -69,6 +98,7 @@ impl<T> CommandMessage<T> {
             session_id,
             params: serde_json::to_value(cmd)?,
             sender,
+            deadline: None,
         })
     }
 
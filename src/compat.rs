@@ -0,0 +1,531 @@
+//! Compatibility of the protocol a browser speaks with the protocol the
+//! types of [`chromiumoxide_cdp`] were generated from.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Deserialize;
+
+use chromiumoxide_cdp::cdp::browser_protocol::{
+    browser, emulation, fetch, inspector, log, network, page, performance, security, target,
+};
+use chromiumoxide_cdp::cdp::js_protocol::runtime;
+use chromiumoxide_cdp::revision::Revision;
+use chromiumoxide_cdp::CURRENT_REVISION;
+
+use crate::devtools::DevToolsHttpClient;
+use crate::error::{CdpError, Result};
+
+/// What to do when connecting to a browser whose protocol is not compatible
+/// with the generated types, see [`ProtocolCompat::is_compatible`].
+///
+/// The protocol is read from the DevTools http endpoints, browsers launched
+/// with [`LaunchTransport::Pipe`](crate::LaunchTransport::Pipe) are not
+/// checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtocolCheck {
+    /// Don't compare the protocols
+    #[default]
+    Ignore,
+    /// Log the differences as a warning
+    Warn,
+    /// Fail with [`CdpError::IncompatibleProtocol`]
+    Fail,
+}
+
+impl ProtocolCheck {
+    /// Checks the protocol of the browser whose websocket is `debug_ws_url`,
+    /// with `client` if the endpoints need a configured client
+    pub(crate) async fn verify(
+        self,
+        debug_ws_url: &str,
+        client: Option<&DevToolsHttpClient>,
+    ) -> Result<()> {
+        if self == ProtocolCheck::Ignore || debug_ws_url.is_empty() {
+            return Ok(());
+        }
+        let client = match client {
+            Some(client) => client.clone(),
+            None => DevToolsHttpClient::new(debug_ws_url)?,
+        };
+        let compat = match ProtocolCompat::fetch(&client).await {
+            Ok(compat) => compat,
+            Err(err) if self == ProtocolCheck::Warn => {
+                tracing::warn!("Failed to check the protocol of the browser: {err}");
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        if compat.is_compatible() {
+            return Ok(());
+        }
+        match self {
+            ProtocolCheck::Fail => Err(CdpError::IncompatibleProtocol(Box::new(compat))),
+            _ => {
+                tracing::warn!("{compat}");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The commands the `Handler` sends to manage targets, frames and network
+/// requests
+const USED_COMMANDS: &[&str] = &[
+    browser::GetVersionParams::IDENTIFIER,
+    browser::CloseParams::IDENTIFIER,
+    target::SetDiscoverTargetsParams::IDENTIFIER,
+    target::GetTargetsParams::IDENTIFIER,
+    target::CreateTargetParams::IDENTIFIER,
+    target::AttachToTargetParams::IDENTIFIER,
+    target::SetAutoAttachParams::IDENTIFIER,
+    target::ActivateTargetParams::IDENTIFIER,
+    target::DetachFromTargetParams::IDENTIFIER,
+    target::CloseTargetParams::IDENTIFIER,
+    target::CreateBrowserContextParams::IDENTIFIER,
+    target::DisposeBrowserContextParams::IDENTIFIER,
+    page::EnableParams::IDENTIFIER,
+    page::GetFrameTreeParams::IDENTIFIER,
+    page::SetLifecycleEventsEnabledParams::IDENTIFIER,
+    page::CreateIsolatedWorldParams::IDENTIFIER,
+    page::AddScriptToEvaluateOnNewDocumentParams::IDENTIFIER,
+    page::NavigateParams::IDENTIFIER,
+    runtime::EnableParams::IDENTIFIER,
+    runtime::RunIfWaitingForDebuggerParams::IDENTIFIER,
+    runtime::EvaluateParams::IDENTIFIER,
+    runtime::CallFunctionOnParams::IDENTIFIER,
+    network::EnableParams::IDENTIFIER,
+    network::SetCacheDisabledParams::IDENTIFIER,
+    network::SetExtraHttpHeadersParams::IDENTIFIER,
+    network::EmulateNetworkConditionsParams::IDENTIFIER,
+    fetch::EnableParams::IDENTIFIER,
+    fetch::DisableParams::IDENTIFIER,
+    fetch::ContinueRequestParams::IDENTIFIER,
+    fetch::ContinueWithAuthParams::IDENTIFIER,
+    security::SetIgnoreCertificateErrorsParams::IDENTIFIER,
+    performance::EnableParams::IDENTIFIER,
+    log::EnableParams::IDENTIFIER,
+    emulation::SetDeviceMetricsOverrideParams::IDENTIFIER,
+    emulation::SetTouchEmulationEnabledParams::IDENTIFIER,
+];
+
+/// The events the `Handler` tracks targets, frames and network requests with
+const USED_EVENTS: &[&str] = &[
+    target::EventTargetCreated::IDENTIFIER,
+    target::EventTargetDestroyed::IDENTIFIER,
+    target::EventTargetCrashed::IDENTIFIER,
+    target::EventAttachedToTarget::IDENTIFIER,
+    target::EventDetachedFromTarget::IDENTIFIER,
+    inspector::EventTargetCrashed::IDENTIFIER,
+    page::EventFrameAttached::IDENTIFIER,
+    page::EventFrameDetached::IDENTIFIER,
+    page::EventFrameNavigated::IDENTIFIER,
+    page::EventFrameStartedLoading::IDENTIFIER,
+    page::EventFrameStoppedLoading::IDENTIFIER,
+    page::EventLifecycleEvent::IDENTIFIER,
+    page::EventNavigatedWithinDocument::IDENTIFIER,
+    runtime::EventExecutionContextCreated::IDENTIFIER,
+    runtime::EventExecutionContextDestroyed::IDENTIFIER,
+    runtime::EventExecutionContextsCleared::IDENTIFIER,
+    runtime::EventBindingCalled::IDENTIFIER,
+    network::EventRequestWillBeSent::IDENTIFIER,
+    network::EventResponseReceived::IDENTIFIER,
+    network::EventLoadingFinished::IDENTIFIER,
+    network::EventLoadingFailed::IDENTIFIER,
+    network::EventRequestServedFromCache::IDENTIFIER,
+    fetch::EventRequestPaused::IDENTIFIER,
+    fetch::EventAuthRequired::IDENTIFIER,
+];
+
+/// The differences between the protocol of a browser and the protocol of
+/// [`CURRENT_REVISION`].
+///
+/// Only the commands and events chromiumoxide itself relies on are
+/// compared, including experimental ones. Parameters that are optional,
+/// experimental or deprecated in the generated protocol may be missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolCompat {
+    /// The revision the types were generated from
+    pub revision: Revision,
+    /// The product of the browser, e.g. `Chrome/118.0.5993.70`
+    pub product: String,
+    /// The protocol version of the browser, e.g. `1.3`
+    pub protocol_version: String,
+    /// Domains the browser doesn't support
+    pub missing_domains: Vec<String>,
+    /// Commands of supported domains the browser doesn't support, e.g.
+    /// `Page.navigate`
+    pub missing_commands: Vec<String>,
+    /// Events of supported domains the browser doesn't emit
+    pub missing_events: Vec<String>,
+    /// Commands whose parameters or return values differ
+    pub changed_commands: Vec<ProtocolChange>,
+    /// Events whose parameters differ
+    pub changed_events: Vec<ProtocolChange>,
+}
+
+/// A command or event whose definition differs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolChange {
+    /// The name of the command or event, e.g. `Page.navigate`
+    pub name: String,
+    /// Required parameters of the browser the generated types lack
+    pub added: Vec<String>,
+    /// Required parameters or return values of the generated types the
+    /// browser lacks
+    pub removed: Vec<String>,
+}
+
+impl ProtocolCompat {
+    /// Whether all commands and events chromiumoxide relies on are supported
+    /// by the browser
+    pub fn is_compatible(&self) -> bool {
+        self.missing_domains.is_empty()
+            && self.missing_commands.is_empty()
+            && self.missing_events.is_empty()
+            && self.changed_commands.is_empty()
+            && self.changed_events.is_empty()
+    }
+
+    /// Compares the protocol served by the DevTools http endpoints
+    pub(crate) async fn fetch(client: &DevToolsHttpClient) -> Result<Self> {
+        let version = client.version().await?;
+        let protocol = client.protocol_definition().await?;
+        Ok(Self::compare(
+            version.browser,
+            version.protocol_version,
+            &protocol,
+        ))
+    }
+
+    pub(crate) fn compare(
+        product: String,
+        protocol_version: String,
+        remote: &RemoteProtocol,
+    ) -> Self {
+        let mut compat = Self {
+            revision: CURRENT_REVISION,
+            product,
+            protocol_version,
+            missing_domains: Vec::new(),
+            missing_commands: Vec::new(),
+            missing_events: Vec::new(),
+            changed_commands: Vec::new(),
+            changed_events: Vec::new(),
+        };
+        let remote_domains: HashMap<_, _> = remote
+            .domains
+            .iter()
+            .map(|domain| (domain.domain.as_str(), domain))
+            .collect();
+        let domains: HashMap<_, _> = chromiumoxide_cdp::protocols()
+            .iter()
+            .flat_map(|protocol| protocol.domains.iter())
+            .map(|domain| (domain.name.as_ref(), domain))
+            .collect();
+
+        for name in USED_COMMANDS {
+            let (domain_name, command_name) = name.split_once('.').unwrap();
+            let Some(command) = domains.get(domain_name).and_then(|domain| {
+                domain
+                    .commands
+                    .iter()
+                    .find(|command| command.name == command_name)
+            }) else {
+                continue;
+            };
+            let Some(remote_domain) = remote_domains.get(domain_name) else {
+                compat.push_missing_domain(domain_name);
+                continue;
+            };
+            let Some(remote) = find(&remote_domain.commands, command_name) else {
+                compat.missing_commands.push(name.to_string());
+                continue;
+            };
+            let known: HashSet<_> = command
+                .parameters
+                .iter()
+                .map(|param| param.name.as_ref())
+                .collect();
+            let added = remote
+                .parameters
+                .iter()
+                .filter(|param| !param.optional && !known.contains(param.name.as_str()))
+                .map(|param| param.name.clone())
+                .collect();
+            let mut removed = removed(
+                command.parameters.iter().map(|p| {
+                    (
+                        p.name.as_ref(),
+                        p.optional || p.experimental || p.deprecated,
+                    )
+                }),
+                &remote.parameters,
+            );
+            removed.extend(removed_returns(
+                command.returns.iter().map(|p| {
+                    (
+                        p.name.as_ref(),
+                        p.optional || p.experimental || p.deprecated,
+                    )
+                }),
+                &remote.returns,
+            ));
+            compat.push_change(name.to_string(), added, removed, true);
+        }
+
+        for name in USED_EVENTS {
+            let (domain_name, event_name) = name.split_once('.').unwrap();
+            let Some(event) = domains
+                .get(domain_name)
+                .and_then(|domain| domain.events.iter().find(|event| event.name == event_name))
+            else {
+                continue;
+            };
+            let Some(remote_domain) = remote_domains.get(domain_name) else {
+                compat.push_missing_domain(domain_name);
+                continue;
+            };
+            let Some(remote) = find(&remote_domain.events, event_name) else {
+                compat.missing_events.push(name.to_string());
+                continue;
+            };
+            let removed = removed(
+                event.parameters.iter().map(|p| {
+                    (
+                        p.name.as_ref(),
+                        p.optional || p.experimental || p.deprecated,
+                    )
+                }),
+                &remote.parameters,
+            );
+            compat.push_change(name.to_string(), Vec::new(), removed, false);
+        }
+        compat
+    }
+
+    fn push_missing_domain(&mut self, domain: &str) {
+        if !self.missing_domains.iter().any(|missing| missing == domain) {
+            self.missing_domains.push(domain.to_string());
+        }
+    }
+
+    fn push_change(
+        &mut self,
+        name: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+        command: bool,
+    ) {
+        if added.is_empty() && removed.is_empty() {
+            return;
+        }
+        let change = ProtocolChange {
+            name,
+            added,
+            removed,
+        };
+        if command {
+            self.changed_commands.push(change);
+        } else {
+            self.changed_events.push(change);
+        }
+    }
+}
+
+impl fmt::Display for ProtocolCompat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compatible() {
+            return write!(
+                f,
+                "{} (protocol {}) is compatible with revision {}",
+                self.product, self.protocol_version, self.revision
+            );
+        }
+        write!(
+            f,
+            "{} (protocol {}) is not compatible with revision {}",
+            self.product, self.protocol_version, self.revision
+        )?;
+        for (label, names) in [
+            ("missing domains", &self.missing_domains),
+            ("missing commands", &self.missing_commands),
+            ("missing events", &self.missing_events),
+        ] {
+            if !names.is_empty() {
+                write!(f, ", {label}: {}", names.join(", "))?;
+            }
+        }
+        for (label, changes) in [
+            ("changed commands", &self.changed_commands),
+            ("changed events", &self.changed_events),
+        ] {
+            if !changes.is_empty() {
+                let names: Vec<_> = changes.iter().map(|c| c.name.as_str()).collect();
+                write!(f, ", {label}: {}", names.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The protocol definition served at `/json/protocol`
+#[derive(Debug, Deserialize)]
+pub(crate) struct RemoteProtocol {
+    domains: Vec<RemoteDomain>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteDomain {
+    domain: String,
+    #[serde(default)]
+    commands: Vec<RemoteItem>,
+    #[serde(default)]
+    events: Vec<RemoteItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteItem {
+    name: String,
+    #[serde(default)]
+    parameters: Vec<RemoteParam>,
+    #[serde(default)]
+    returns: Vec<RemoteParam>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteParam {
+    name: String,
+    #[serde(default)]
+    optional: bool,
+}
+
+fn find<'a>(items: &'a [RemoteItem], name: &str) -> Option<&'a RemoteItem> {
+    items.iter().find(|item| item.name == name)
+}
+
+/// The required parameters of `ours`, given as name and whether they are
+/// optional, that are not in `theirs`
+fn removed<'a>(ours: impl Iterator<Item = (&'a str, bool)>, theirs: &[RemoteParam]) -> Vec<String> {
+    let theirs: HashSet<_> = theirs.iter().map(|param| param.name.as_str()).collect();
+    ours.filter(|(name, optional)| !optional && !theirs.contains(name))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Like [`removed`], but return values the browser only sends optionally are
+/// missing as well
+fn removed_returns<'a>(
+    ours: impl Iterator<Item = (&'a str, bool)>,
+    theirs: &[RemoteParam],
+) -> Vec<String> {
+    let theirs: HashSet<_> = theirs
+        .iter()
+        .filter(|param| !param.optional)
+        .map(|param| param.name.as_str())
+        .collect();
+    ours.filter(|(name, optional)| !optional && !theirs.contains(name))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(json: serde_json::Value) -> RemoteProtocol {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn compares_protocols() {
+        let compat = ProtocolCompat::compare(
+            "Chrome/1.0".to_string(),
+            "1.3".to_string(),
+            &remote(serde_json::json!({
+                "domains": [{
+                    "domain": "Page",
+                    "commands": [{
+                        "name": "navigate",
+                        "parameters": [
+                            { "name": "url" },
+                            { "name": "mode" }
+                        ],
+                        "returns": [{ "name": "frameId", "optional": true }]
+                    }, {
+                        "name": "setLifecycleEventsEnabled",
+                        "parameters": [{ "name": "enabled" }]
+                    }],
+                    "events": [{ "name": "lifecycleEvent", "parameters": [
+                        { "name": "frameId" },
+                        { "name": "loaderId" },
+                        { "name": "name" }
+                    ] }]
+                }]
+            })),
+        );
+        assert!(!compat.is_compatible());
+        assert!(compat.missing_domains.contains(&"Target".to_string()));
+        assert!(!compat.missing_domains.contains(&"Page".to_string()));
+        assert_eq!(
+            compat
+                .missing_domains
+                .iter()
+                .filter(|domain| *domain == "Target")
+                .count(),
+            1
+        );
+        // experimental, but used
+        assert!(compat
+            .missing_commands
+            .contains(&"Page.createIsolatedWorld".to_string()));
+        // not used
+        assert!(!compat.missing_commands.contains(&"Page.reload".to_string()));
+        assert!(compat
+            .missing_events
+            .contains(&"Page.frameNavigated".to_string()));
+        assert!(!compat
+            .missing_events
+            .contains(&"Page.loadEventFired".to_string()));
+
+        let navigate = compat
+            .changed_commands
+            .iter()
+            .find(|change| change.name == "Page.navigate")
+            .unwrap();
+        assert_eq!(navigate.added, vec!["mode".to_string()]);
+        assert_eq!(navigate.removed, vec!["frameId".to_string()]);
+        assert!(!compat
+            .changed_commands
+            .iter()
+            .any(|change| change.name == "Page.setLifecycleEventsEnabled"));
+        assert_eq!(
+            compat.changed_events,
+            vec![ProtocolChange {
+                name: "Page.lifecycleEvent".to_string(),
+                added: Vec::new(),
+                removed: vec!["timestamp".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn used_methods_are_generated() {
+        // methods that are not generated would be skipped by the comparison
+        let domains: HashSet<_> = USED_COMMANDS
+            .iter()
+            .chain(USED_EVENTS)
+            .map(|name| name.split_once('.').unwrap().0)
+            .collect();
+        let protocol = remote(serde_json::json!({
+            "domains": domains
+                .iter()
+                .map(|domain| serde_json::json!({ "domain": domain }))
+                .collect::<Vec<_>>()
+        }));
+        let compat =
+            ProtocolCompat::compare("Chrome/1.0".to_string(), "1.3".to_string(), &protocol);
+        assert_eq!(compat.missing_commands.len(), USED_COMMANDS.len());
+        assert_eq!(compat.missing_events.len(), USED_EVENTS.len());
+    }
+}
@@ -1,9 +1,11 @@
 use std::collections::VecDeque;
+use std::fmt;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::ready;
 
-use async_tungstenite::{tungstenite::protocol::WebSocketConfig, WebSocketStream};
+use async_tungstenite::tungstenite::http::HeaderMap;
 use futures::stream::Stream;
 use futures::task::{Context, Poll};
 use futures::{SinkExt, StreamExt};
@@ -11,56 +13,107 @@ use futures::{SinkExt, StreamExt};
 use chromiumoxide_cdp::cdp::browser_protocol::target::SessionId;
 use chromiumoxide_types::{CallId, EventMessage, Message, MethodCall, MethodId};
 
-use crate::error::CdpError;
-use crate::error::Result;
+use crate::error::{CdpError, Result};
+use crate::transport::{RecordEvent, Recorder, TrafficCounter, Transport, WsTransport};
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "async-std-runtime")] {
-       use async_tungstenite::async_std::ConnectStream;
-    } else if #[cfg(feature = "tokio-runtime")] {
-        use async_tungstenite::tokio::ConnectStream;
-    }
-}
-/// Exchanges the messages with the websocket
+/// Exchanges the messages with the browser over a [`Transport`]
 #[must_use = "streams do nothing unless polled"]
-#[derive(Debug)]
 pub struct Connection<T: EventMessage> {
     /// Queue of commands to send.
     pending_commands: VecDeque<MethodCall>,
-    /// The websocket of the chromium instance
-    ws: WebSocketStream<ConnectStream>,
+    /// The channel to the chromium instance
+    transport: Box<dyn Transport<T>>,
     /// The identifier for a specific command
     next_id: usize,
     needs_flush: bool,
-    /// The message that is currently being proceessed
-    pending_flush: Option<MethodCall>,
+    /// The id of the message that is currently being proceessed
+    pending_flush: Option<CallId>,
+    /// The bytes exchanged over the transport, if it counts them
+    traffic: Option<Arc<TrafficCounter>>,
+    /// Records all exchanged messages, if set
+    recorder: Option<ConnectionRecorder<T>>,
     _marker: PhantomData<T>,
 }
 
-impl<T: EventMessage + Unpin> Connection<T> {
+impl<T: EventMessage + Unpin + 'static> Connection<T> {
+    /// Connects to the debug websocket of a chromium instance
     pub async fn connect(debug_ws_url: impl AsRef<str>) -> Result<Self> {
-        let config = WebSocketConfig {
-            max_message_size: None,
-            max_frame_size: None,
-            ..Default::default()
-        };
+        Self::connect_with_headers(debug_ws_url, HeaderMap::new()).await
+    }
 
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "async-std-runtime")] {
-               let (ws, _) = async_tungstenite::async_std::connect_async_with_config(debug_ws_url.as_ref(), Some(config)).await?;
-            } else if #[cfg(feature = "tokio-runtime")] {
-                 let (ws, _) = async_tungstenite::tokio::connect_async_with_config(debug_ws_url.as_ref(), Some(config)).await?;
-            }
-        }
+    /// Connects to the debug websocket and sends `headers` with the
+    /// handshake
+    pub async fn connect_with_headers(
+        debug_ws_url: impl AsRef<str>,
+        headers: HeaderMap,
+    ) -> Result<Self> {
+        let ws = WsTransport::connect_with_headers(debug_ws_url, headers).await?;
+        let traffic = ws.traffic();
+        Ok(Self::new(ws).with_traffic(traffic))
+    }
+}
 
-        Ok(Self {
+impl<T: EventMessage + 'static> Connection<T> {
+    /// Creates a new connection that exchanges the messages over `transport`
+    pub fn new(transport: impl Transport<T> + 'static) -> Self {
+        Self {
             pending_commands: Default::default(),
-            ws,
+            transport: Box::new(transport),
             next_id: 0,
             needs_flush: false,
             pending_flush: None,
+            traffic: None,
+            recorder: None,
             _marker: Default::default(),
-        })
+        }
+    }
+}
+
+impl<T: EventMessage> Connection<T> {
+    /// Reports the bytes counted by the transport in the `Handler` metrics
+    pub fn with_traffic(mut self, traffic: Arc<TrafficCounter>) -> Self {
+        self.traffic = Some(traffic);
+        self
+    }
+
+    /// The bytes exchanged over the transport, if it counts them
+    pub fn traffic(&self) -> Option<&TrafficCounter> {
+        self.traffic.as_deref()
+    }
+}
+
+impl<T: EventMessage + RecordEvent> Connection<T> {
+    /// Writes every sent `MethodCall` and received `Message` to `recorder`
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.set_recorder(recorder);
+        self
+    }
+
+    pub(crate) fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(ConnectionRecorder {
+            recorder,
+            record_received: Recorder::record_received::<T>,
+        });
+    }
+}
+
+/// The `Recorder` of a connection together with the way to record its
+/// received messages, so that `Connection` itself needs no `RecordEvent`
+/// bound.
+struct ConnectionRecorder<T> {
+    recorder: Recorder,
+    record_received: fn(&Recorder, &Message<T>),
+}
+
+impl<T: EventMessage> fmt::Debug for Connection<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection")
+            .field("pending_commands", &self.pending_commands)
+            .field("next_id", &self.next_id)
+            .field("needs_flush", &self.needs_flush)
+            .field("pending_flush", &self.pending_flush)
+            .field("recorder", &self.recorder.as_ref().map(|r| &r.recorder))
+            .finish()
     }
 }
 
@@ -94,16 +147,19 @@ impl<T: EventMessage> Connection<T> {
     /// sink
     fn start_send_next(&mut self, cx: &mut Context<'_>) -> Result<()> {
         if self.needs_flush {
-            if let Poll::Ready(Ok(())) = self.ws.poll_flush_unpin(cx) {
+            if let Poll::Ready(Ok(())) = self.transport.poll_flush_unpin(cx) {
                 self.needs_flush = false;
             }
         }
         if self.pending_flush.is_none() && !self.needs_flush {
             if let Some(cmd) = self.pending_commands.pop_front() {
                 tracing::trace!("Sending {:?}", cmd);
-                let msg = serde_json::to_string(&cmd)?;
-                self.ws.start_send_unpin(msg.into())?;
-                self.pending_flush = Some(cmd);
+                let id = cmd.id;
+                if let Some(ref rec) = self.recorder {
+                    rec.recorder.record_sent(&cmd);
+                }
+                self.transport.start_send_unpin(cmd)?;
+                self.pending_flush = Some(id);
             }
         }
         Ok(())
@@ -124,7 +180,7 @@ impl<T: EventMessage + Unpin> Stream for Connection<T> {
 
             // send the message
             if let Some(call) = pin.pending_flush.take() {
-                if pin.ws.poll_ready_unpin(cx).is_ready() {
+                if pin.transport.poll_ready_unpin(cx).is_ready() {
                     pin.needs_flush = true;
                     // try another flush
                     continue;
@@ -135,23 +191,23 @@ impl<T: EventMessage + Unpin> Stream for Connection<T> {
             break;
         }
 
-        // read from the ws
-        match ready!(pin.ws.poll_next_unpin(cx)) {
-            Some(Ok(msg)) => match serde_json::from_slice::<Message<T>>(&msg.into_data()) {
-                Ok(msg) => {
-                    tracing::trace!("Received {:?}", msg);
-                    Poll::Ready(Some(Ok(msg)))
+        // read from the transport
+        match ready!(pin.transport.poll_next_unpin(cx)) {
+            Some(Ok(msg)) => {
+                tracing::trace!("Received {:?}", msg);
+                if let Some(ref rec) = pin.recorder {
+                    (rec.record_received)(&rec.recorder, &msg);
                 }
-                Err(err) => {
-                    tracing::error!("Failed to deserialize WS response {}", err);
-                    Poll::Ready(Some(Err(err.into())))
+                Poll::Ready(Some(Ok(msg)))
+            }
+            Some(Err(err)) => {
+                if let (Some(rec), CdpError::Serde(err)) = (pin.recorder.as_ref(), &err) {
+                    rec.recorder.record_invalid(err);
                 }
-            },
-            Some(Err(err)) => Poll::Ready(Some(Err(CdpError::Ws(err)))),
-            None => {
-                // ws connection closed
-                Poll::Ready(None)
+                Poll::Ready(Some(Err(err)))
             }
+            // connection closed
+            None => Poll::Ready(None),
         }
     }
 }
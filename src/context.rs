@@ -0,0 +1,471 @@
+use futures::channel::mpsc::Sender;
+use futures::channel::oneshot::channel as oneshot_channel;
+use futures::SinkExt;
+
+use chromiumoxide_cdp::cdp::browser_protocol::browser::{
+    BrowserContextId, GrantPermissionsParams, PermissionType, ResetPermissionsParams,
+};
+use chromiumoxide_cdp::cdp::browser_protocol::network::{Cookie, CookieParam};
+use chromiumoxide_cdp::cdp::browser_protocol::storage::{
+    ClearCookiesParams, GetCookiesParams, SetCookiesParams,
+};
+use chromiumoxide_cdp::cdp::browser_protocol::target::{
+    CreateBrowserContextParams, CreateTargetParams, DisposeBrowserContextParams,
+};
+use chromiumoxide_types::{Command, CommandResponse};
+
+use crate::auth::Credentials;
+use crate::cmd::{to_command_response, CommandMessage};
+use crate::error::Result;
+use crate::handler::browser::BrowserContext as HandlerBrowserContext;
+use crate::handler::HandlerMessage;
+use crate::page::Page;
+
+/// A handle to an isolated browser context created with
+/// [`Browser::new_browser_context`](crate::Browser::new_browser_context).
+///
+/// Pages of different browser contexts don't share cookies, cache, storage or
+/// permissions, so a single browser can host many independent sessions
+/// concurrently.
+#[derive(Debug)]
+pub struct BrowserContext {
+    /// The identifier of the context in the browser
+    id: BrowserContextId,
+    /// The options this context was created with
+    options: BrowserContextOptions,
+    /// The `Sender` to send messages to the connection handler
+    sender: Sender<HandlerMessage>,
+}
+
+impl BrowserContext {
+    /// Creates a new browser context and registers it with the handler.
+    pub(crate) async fn create(
+        options: BrowserContextOptions,
+        mut sender: Sender<HandlerMessage>,
+    ) -> Result<Self> {
+        let id = execute(options.params.clone(), sender.clone())
+            .await?
+            .result
+            .browser_context_id;
+        let ctx = HandlerBrowserContext::from(id.clone());
+        // register the credentials before the context is known, so that no
+        // page of this context is created without them
+        if let Some(credentials) = options.proxy_credentials.clone() {
+            sender
+                .send(HandlerMessage::SetProxyCredentials(
+                    ctx.clone(),
+                    credentials,
+                ))
+                .await?;
+        }
+        sender.send(HandlerMessage::InsertContext(ctx)).await?;
+        Ok(Self {
+            id,
+            options,
+            sender,
+        })
+    }
+
+    /// The identifier of this context
+    pub fn id(&self) -> &BrowserContextId {
+        &self.id
+    }
+
+    /// The options this context was created with
+    pub fn options(&self) -> &BrowserContextOptions {
+        &self.options
+    }
+
+    /// Call a browser method.
+    pub async fn execute<T: Command>(&self, cmd: T) -> Result<CommandResponse<T::Response>> {
+        execute(cmd, self.sender.clone()).await
+    }
+
+    /// Create a new page within this context
+    pub async fn new_page(&self, params: impl Into<CreateTargetParams>) -> Result<Page> {
+        let (tx, rx) = oneshot_channel();
+        let mut params = params.into();
+        params.browser_context_id = Some(self.id.clone());
+
+        self.sender
+            .clone()
+            .send(HandlerMessage::CreatePage(params, tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Return all pages that run within this context
+    pub async fn pages(&self) -> Result<Vec<Page>> {
+        let (tx, rx) = oneshot_channel();
+        self.sender
+            .clone()
+            .send(HandlerMessage::GetPages(tx))
+            .await?;
+        Ok(rx
+            .await?
+            .into_iter()
+            .filter(|page| page.browser_context_id() == Some(&self.id))
+            .collect())
+    }
+
+    /// Returns all cookies of this context
+    pub async fn cookies(&self) -> Result<Vec<Cookie>> {
+        Ok(self
+            .execute(GetCookiesParams {
+                browser_context_id: Some(self.id.clone()),
+            })
+            .await?
+            .result
+            .cookies)
+    }
+
+    /// Sets the given cookies in this context
+    pub async fn set_cookies(&self, cookies: Vec<CookieParam>) -> Result<&Self> {
+        self.execute(SetCookiesParams {
+            cookies,
+            browser_context_id: Some(self.id.clone()),
+        })
+        .await?;
+        Ok(self)
+    }
+
+    /// Deletes all cookies of this context
+    pub async fn clear_cookies(&self) -> Result<&Self> {
+        self.execute(ClearCookiesParams {
+            browser_context_id: Some(self.id.clone()),
+        })
+        .await?;
+        Ok(self)
+    }
+
+    /// Grants the permissions to the given origin for all pages of this
+    /// context and rejects all others.
+    pub async fn grant_permissions(
+        &self,
+        origin: impl Into<String>,
+        permissions: impl IntoIterator<Item = PermissionType>,
+    ) -> Result<&Self> {
+        self.execute(GrantPermissionsParams {
+            permissions: permissions.into_iter().collect(),
+            origin: Some(origin.into()),
+            browser_context_id: Some(self.id.clone()),
+        })
+        .await?;
+        Ok(self)
+    }
+
+    /// Resets all permission overrides of this context
+    pub async fn reset_permissions(&self) -> Result<&Self> {
+        self.execute(ResetPermissionsParams {
+            browser_context_id: Some(self.id.clone()),
+        })
+        .await?;
+        Ok(self)
+    }
+
+    /// Routes all traffic of this context through the given proxy server,
+    /// e.g. `http://proxy.example.com:8080`, and answers its authentication
+    /// challenges with `credentials`.
+    ///
+    /// # Note
+    ///
+    /// The browser only accepts a proxy when the context is created, so this
+    /// creates a fresh context and then disposes the current one including
+    /// all its pages. The identifier of the context changes. If the new
+    /// context can't be created, the current one is left untouched.
+    pub async fn set_proxy(
+        &mut self,
+        proxy_server: impl Into<String>,
+        proxy_bypass_list: Option<String>,
+        credentials: Option<Credentials>,
+    ) -> Result<&mut Self> {
+        let mut options = self.options.clone();
+        options.params.proxy_server = Some(proxy_server.into());
+        options.params.proxy_bypass_list = proxy_bypass_list;
+        options.proxy_credentials = credentials;
+
+        let created = Self::create(options, self.sender.clone()).await?;
+        std::mem::replace(self, created).dispose().await?;
+        Ok(self)
+    }
+
+    /// Disposes this context and closes all of its pages.
+    pub async fn close(self) -> Result<()> {
+        self.dispose().await
+    }
+
+    async fn dispose(&self) -> Result<()> {
+        self.execute(DisposeBrowserContextParams::new(self.id.clone()))
+            .await?;
+        self.sender
+            .clone()
+            .send(HandlerMessage::DisposeContext(HandlerBrowserContext::from(
+                self.id.clone(),
+            )))
+            .await?;
+        Ok(())
+    }
+}
+
+/// How to create a [`BrowserContext`]
+#[derive(Debug, Clone, Default)]
+pub struct BrowserContextOptions {
+    /// The params to create the context in the browser with
+    pub params: CreateBrowserContextParams,
+    /// Credentials to answer authentication challenges of the proxy with
+    pub proxy_credentials: Option<Credentials>,
+}
+
+impl BrowserContextOptions {
+    pub fn builder() -> BrowserContextOptionsBuilder {
+        BrowserContextOptionsBuilder::default()
+    }
+}
+
+impl From<CreateBrowserContextParams> for BrowserContextOptions {
+    fn from(params: CreateBrowserContextParams) -> Self {
+        Self {
+            params,
+            proxy_credentials: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BrowserContextOptionsBuilder {
+    dispose_on_detach: Option<bool>,
+    proxy_server: Option<String>,
+    proxy_bypass_list: Option<String>,
+    proxy_credentials: Option<Credentials>,
+    origins_with_universal_network_access: Option<Vec<String>>,
+}
+
+impl BrowserContextOptionsBuilder {
+    /// Dispose the context when the debugging session disconnects
+    pub fn dispose_on_detach(mut self, dispose_on_detach: bool) -> Self {
+        self.dispose_on_detach = Some(dispose_on_detach);
+        self
+    }
+
+    /// The proxy server for all traffic of the context, similar to the one
+    /// passed to `--proxy-server`
+    pub fn proxy_server(mut self, proxy_server: impl Into<String>) -> Self {
+        self.proxy_server = Some(proxy_server.into());
+        self
+    }
+
+    /// Hosts that bypass the proxy, similar to the list passed to
+    /// `--proxy-bypass-list`
+    pub fn proxy_bypass_list(mut self, proxy_bypass_list: impl Into<String>) -> Self {
+        self.proxy_bypass_list = Some(proxy_bypass_list.into());
+        self
+    }
+
+    /// Credentials the pages of the context answer authentication challenges
+    /// of the proxy with.
+    pub fn proxy_credentials(mut self, credentials: Credentials) -> Self {
+        self.proxy_credentials = Some(credentials);
+        self
+    }
+
+    /// Grant unlimited cross-origin access to this origin
+    pub fn origin_with_universal_network_access(mut self, origin: impl Into<String>) -> Self {
+        self.origins_with_universal_network_access
+            .get_or_insert_with(Vec::new)
+            .push(origin.into());
+        self
+    }
+
+    pub fn build(self) -> BrowserContextOptions {
+        BrowserContextOptions {
+            params: CreateBrowserContextParams {
+                dispose_on_detach: self.dispose_on_detach,
+                proxy_server: self.proxy_server,
+                proxy_bypass_list: self.proxy_bypass_list,
+                origins_with_universal_network_access: self.origins_with_universal_network_access,
+            },
+            proxy_credentials: self.proxy_credentials,
+        }
+    }
+}
+
+async fn execute<T: Command>(
+    cmd: T,
+    mut sender: Sender<HandlerMessage>,
+) -> Result<CommandResponse<T::Response>> {
+    let (tx, rx) = oneshot_channel();
+    let method = cmd.identifier();
+    let msg = CommandMessage::new(cmd, tx)?;
+
+    sender.send(HandlerMessage::Command(msg)).await?;
+    let resp = rx.await??;
+    to_command_response::<T>(resp, method)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::handler::HandlerConfig;
+    use crate::testing::{MockBrowser, MockReply};
+    use crate::Browser;
+
+    async fn connect(mock: &MockBrowser) -> Browser {
+        let (browser, mut handler) =
+            Browser::connect_with_config(mock.ws_url(), HandlerConfig::default())
+                .await
+                .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+        browser
+    }
+
+    #[async_std::test]
+    async fn pages_of_the_context() {
+        let mock = MockBrowser::start().await.unwrap();
+        let browser = connect(&mock).await;
+        let ctx = browser
+            .new_browser_context(BrowserContextOptions::default())
+            .await
+            .unwrap();
+
+        let page = ctx.new_page("about:blank").await.unwrap();
+        browser.new_page("about:blank").await.unwrap();
+
+        assert_eq!(page.browser_context_id(), Some(ctx.id()));
+        let pages = ctx.pages().await.unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].target_id(), page.target_id());
+    }
+
+    #[async_std::test]
+    async fn cookies_of_the_context() {
+        let mock = MockBrowser::builder()
+            .respond("Storage.getCookies", json!({ "cookies": [] }))
+            .start()
+            .await
+            .unwrap();
+        let browser = connect(&mock).await;
+        let ctx = browser
+            .new_browser_context(BrowserContextOptions::default())
+            .await
+            .unwrap();
+
+        ctx.set_cookies(vec![CookieParam::new("name", "value")])
+            .await
+            .unwrap();
+        assert!(ctx.cookies().await.unwrap().is_empty());
+        ctx.clear_cookies().await.unwrap();
+
+        let calls: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.method.starts_with("Storage."))
+            .collect();
+        let methods: Vec<_> = calls.iter().map(|call| call.method.as_str()).collect();
+        assert_eq!(
+            methods,
+            [
+                "Storage.setCookies",
+                "Storage.getCookies",
+                "Storage.clearCookies"
+            ]
+        );
+        assert!(calls
+            .iter()
+            .all(|call| call.params["browserContextId"] == ctx.id().as_ref()));
+        assert_eq!(calls[0].params["cookies"][0]["name"], "name");
+    }
+
+    #[async_std::test]
+    async fn close_disposes_the_pages() {
+        let mock = MockBrowser::start().await.unwrap();
+        let browser = connect(&mock).await;
+        let ctx = browser
+            .new_browser_context(BrowserContextOptions::default())
+            .await
+            .unwrap();
+        let id = ctx.id().clone();
+        ctx.new_page("about:blank").await.unwrap();
+
+        ctx.close().await.unwrap();
+
+        assert!(mock
+            .calls()
+            .iter()
+            .any(|call| call.method == "Target.disposeBrowserContext"
+                && call.params["browserContextId"] == id.as_ref()));
+        for _ in 0..100 {
+            if browser.pages().await.unwrap().is_empty() {
+                return;
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the pages of the context are not removed");
+    }
+
+    #[async_std::test]
+    async fn set_proxy_replaces_the_context() {
+        let mock = MockBrowser::start().await.unwrap();
+        let browser = connect(&mock).await;
+        let mut ctx = browser
+            .new_browser_context(BrowserContextOptions::default())
+            .await
+            .unwrap();
+        let old = ctx.id().clone();
+
+        ctx.set_proxy("http://proxy.example.com:8080", None, None)
+            .await
+            .unwrap();
+
+        assert_ne!(ctx.id(), &old);
+        assert_eq!(
+            ctx.options().params.proxy_server.as_deref(),
+            Some("http://proxy.example.com:8080")
+        );
+        let methods: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.method.ends_with("BrowserContext"))
+            .map(|call| (call.method, call.params))
+            .collect();
+        assert_eq!(methods.len(), 3);
+        assert_eq!(methods[1].0, "Target.createBrowserContext");
+        assert_eq!(methods[1].1["proxyServer"], "http://proxy.example.com:8080");
+        assert_eq!(methods[2].0, "Target.disposeBrowserContext");
+        assert_eq!(methods[2].1["browserContextId"], old.as_ref());
+    }
+
+    #[async_std::test]
+    async fn failed_set_proxy_keeps_the_context() {
+        let mock = MockBrowser::builder()
+            .respond_with("Target.createBrowserContext", |call| {
+                if call.params["proxyServer"].is_string() {
+                    MockReply::error(-32000, "Failed to create browser context")
+                } else {
+                    MockReply::ok(json!({ "browserContextId": "CONTEXT" }))
+                }
+            })
+            .start()
+            .await
+            .unwrap();
+        let browser = connect(&mock).await;
+        let mut ctx = browser
+            .new_browser_context(BrowserContextOptions::default())
+            .await
+            .unwrap();
+
+        assert!(ctx.set_proxy("invalid", None, None).await.is_err());
+
+        assert_eq!(ctx.id().as_ref(), "CONTEXT");
+        assert!(!mock
+            .calls()
+            .iter()
+            .any(|call| call.method == "Target.disposeBrowserContext"));
+        ctx.new_page("about:blank").await.unwrap();
+    }
+}
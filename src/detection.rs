@@ -1,6 +1,14 @@
+use std::collections::HashSet;
 use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::error::DetectionError;
+
+/// Which browsers to detect, built from the default options with the methods
+/// of the same name
 #[derive(Debug, Clone)]
 pub struct DetectionOptions {
     /// Detect Microsoft Edge
@@ -8,6 +16,21 @@ pub struct DetectionOptions {
 
     /// Detect unstable installations (beta, dev, unstable)
     pub unstable: bool,
+
+    /// Detect the chromium packages of linux distributions outside of the
+    /// user path, e.g. on aarch64 where no official builds exist
+    pub distro: bool,
+
+    /// Detect Brave
+    pub brave: bool,
+
+    /// Run the detected executables with `--version` to determine their
+    /// version
+    pub probe_version: bool,
+
+    /// Only detect browsers of at least this major version, implies
+    /// `probe_version`
+    pub min_version: Option<u32>,
 }
 
 impl Default for DetectionOptions {
@@ -15,37 +38,233 @@ impl Default for DetectionOptions {
         Self {
             msedge: true,
             unstable: false,
+            distro: true,
+            brave: true,
+            probe_version: false,
+            min_version: None,
         }
     }
 }
 
-/// Returns the path to Chrome's executable.
+impl DetectionOptions {
+    pub fn msedge(mut self, msedge: bool) -> Self {
+        self.msedge = msedge;
+        self
+    }
+
+    pub fn unstable(mut self, unstable: bool) -> Self {
+        self.unstable = unstable;
+        self
+    }
+
+    pub fn distro(mut self, distro: bool) -> Self {
+        self.distro = distro;
+        self
+    }
+
+    pub fn brave(mut self, brave: bool) -> Self {
+        self.brave = brave;
+        self
+    }
+
+    pub fn probe_version(mut self, probe_version: bool) -> Self {
+        self.probe_version = probe_version;
+        self
+    }
+
+    pub fn min_version(mut self, min_version: impl Into<Option<u32>>) -> Self {
+        self.min_version = min_version.into();
+        self
+    }
+}
+
+/// The chromium based browsers that can be detected, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BrowserKind {
+    Chrome,
+    Chromium,
+    Edge,
+    Brave,
+}
+
+/// The release channel of a detected browser, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Channel {
+    Stable,
+    Beta,
+    /// Also called unstable
+    Dev,
+    /// Also called nightly
+    Canary,
+}
+
+/// The version of a browser, e.g. `118.0.5993.70`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    /// Parses a version of two to four numbers separated by dots
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = [0; 4];
+        let mut len = 0;
+        for part in s.split('.') {
+            *parts.get_mut(len)? = part.parse().ok()?;
+            len += 1;
+        }
+        (len >= 2).then_some(Self {
+            major: parts[0],
+            minor: parts[1],
+            build: parts[2],
+            patch: parts[3],
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.build, self.patch
+        )
+    }
+}
+
+/// A browser installed on this machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedBrowser {
+    pub path: PathBuf,
+    pub kind: BrowserKind,
+    pub channel: Channel,
+    /// Only set if the version was probed and could be determined
+    pub version: Option<Version>,
+}
+
+impl DetectedBrowser {
+    /// Guesses the kind and channel of the browser from its file name, or the
+    /// name of its app bundle on macOS
+    fn new(path: PathBuf) -> Self {
+        let words = words(Path::new(browser_name(&path)));
+        let has = |word: &str| words.iter().any(|w| w == word);
+        let kind = if has("edge") || has("msedge") {
+            BrowserKind::Edge
+        } else if has("brave") {
+            BrowserKind::Brave
+        } else if has("chromium") {
+            BrowserKind::Chromium
+        } else {
+            BrowserKind::Chrome
+        };
+        let channel = if has("canary") || has("nightly") || has("sxs") {
+            Channel::Canary
+        } else if has("beta") {
+            Channel::Beta
+        } else if has("dev") || has("unstable") {
+            Channel::Dev
+        } else {
+            Channel::Stable
+        };
+        Self {
+            path,
+            kind,
+            channel,
+            version: None,
+        }
+    }
+
+    fn is_allowed(&self, options: &DetectionOptions) -> bool {
+        match self.kind {
+            BrowserKind::Edge if !options.msedge => false,
+            BrowserKind::Brave if !options.brave => false,
+            _ => self.channel == Channel::Stable || options.unstable,
+        }
+    }
+}
+
+/// Returns all detected browsers, the best one first.
 ///
 /// The following elements will be checked:
-///   - `CHROME` environment variable
+///   - `CHROME` environment variable, which is always returned first
 ///   - Usual filenames in the user path
 ///   - (Windows) Registry
-///   - (Windows & MacOS) Usual installations paths
-/// If all of the above fail, an error is returned.
-pub fn default_executable(options: DetectionOptions) -> Result<std::path::PathBuf, String> {
-    if let Some(path) = get_by_env_var() {
-        return Ok(path);
-    }
+///   - Usual installations paths, including Flatpak and Snap on linux
+///
+/// Browsers are preferred by channel, then kind and then the latest version.
+/// If `min_version` is set, browsers whose version is lower or unknown are
+/// skipped.
+pub fn detect(options: &DetectionOptions) -> Vec<DetectedBrowser> {
+    detect_all(options).0
+}
 
-    if let Some(path) = get_by_name(&options) {
-        return Ok(path);
+/// Returns the best detected browser, see [`detect`]
+pub fn detect_best(options: &DetectionOptions) -> Result<DetectedBrowser, DetectionError> {
+    let (mut browsers, too_old) = detect_all(options);
+    if !browsers.is_empty() {
+        return Ok(browsers.swap_remove(0));
     }
-
-    #[cfg(windows)]
-    if let Some(path) = get_by_registry() {
-        return Ok(path);
+    match options.min_version {
+        Some(min_version) if !too_old.is_empty() => Err(DetectionError::TooOld {
+            min_version,
+            found: too_old,
+        }),
+        _ => Err(DetectionError::NotFound),
     }
+}
 
-    if let Some(path) = get_by_path(&options) {
-        return Ok(path);
+/// Returns the path to Chrome's executable.
+///
+/// This is the path of the best browser returned by [`detect`]. If no browser
+/// is detected, an error is returned.
+pub fn default_executable(options: DetectionOptions) -> Result<std::path::PathBuf, String> {
+    detect_best(&options)
+        .map(|browser| browser.path)
+        .map_err(|err| err.to_string())
+}
+
+/// Returns the browsers that satisfy the `min_version` and those that don't
+fn detect_all(options: &DetectionOptions) -> (Vec<DetectedBrowser>, Vec<DetectedBrowser>) {
+    let probe = options.probe_version || options.min_version.is_some();
+    let mut seen = HashSet::new();
+    let mut is_new =
+        |path: &PathBuf| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone()));
+
+    let mut from_env = get_by_env_var()
+        .filter(&mut is_new)
+        .map(DetectedBrowser::new);
+    let mut found = get_by_name()
+        .into_iter()
+        .chain(get_by_registry())
+        .chain(get_by_path(options))
+        .filter(&mut is_new)
+        .map(DetectedBrowser::new)
+        .filter(|browser| browser.is_allowed(options))
+        .collect::<Vec<_>>();
+    if probe {
+        for browser in from_env.iter_mut().chain(found.iter_mut()) {
+            browser.version = probe_version(&browser.path);
+        }
     }
+    found.sort_by_key(|browser| {
+        (
+            browser.channel,
+            browser.kind,
+            std::cmp::Reverse(browser.version),
+        )
+    });
 
-    Err("Could not auto detect a chrome executable".to_string())
+    let (browsers, too_old) = from_env.into_iter().chain(found).partition(|browser| {
+        match (options.min_version, browser.version) {
+            (Some(min_version), Some(version)) => version.major >= min_version,
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    });
+    (browsers, too_old)
 }
 
 fn get_by_env_var() -> Option<PathBuf> {
@@ -58,95 +277,126 @@ fn get_by_env_var() -> Option<PathBuf> {
     None
 }
 
-fn get_by_name(options: &DetectionOptions) -> Option<PathBuf> {
+fn get_by_name() -> Vec<PathBuf> {
     let default_apps = [
-        ("chrome", true),
-        ("chrome-browser", true),
-        ("google-chrome-stable", true),
-        ("google-chrome-beta", options.unstable),
-        ("google-chrome-dev", options.unstable),
-        ("google-chrome-unstable", options.unstable),
-        ("chromium", true),
-        ("chromium-browser", true),
-        ("msedge", options.msedge),
-        ("microsoft-edge", options.msedge),
-        ("microsoft-edge-stable", options.msedge),
-        ("microsoft-edge-beta", options.msedge && options.unstable),
-        ("microsoft-edge-dev", options.msedge && options.unstable),
+        "chrome",
+        "chrome-browser",
+        "google-chrome-stable",
+        "google-chrome-beta",
+        "google-chrome-dev",
+        "google-chrome-unstable",
+        "chromium",
+        "chromium-browser",
+        "msedge",
+        "microsoft-edge",
+        "microsoft-edge-stable",
+        "microsoft-edge-beta",
+        "microsoft-edge-dev",
+        "brave",
+        "brave-browser",
+        "brave-browser-beta",
+        "brave-browser-nightly",
     ];
-    for (app, allowed) in default_apps {
-        if !allowed {
-            continue;
-        }
-        if let Ok(path) = which::which(app) {
-            return Some(path);
-        }
-    }
-
-    None
+    default_apps
+        .into_iter()
+        .filter_map(|app| which::which(app).ok())
+        .collect()
 }
 
 #[allow(unused_variables)]
-fn get_by_path(options: &DetectionOptions) -> Option<PathBuf> {
+fn get_by_path(options: &DetectionOptions) -> Vec<PathBuf> {
     #[cfg(all(unix, not(target_os = "macos")))]
-    let default_paths: [(&str, bool); 3] = [
-        ("/opt/chromium.org/chromium", true),
-        ("/opt/google/chrome", true),
-        // test for lambda
-        ("/tmp/aws/lib", true),
-    ];
+    let default_paths = {
+        let mut paths: Vec<(PathBuf, bool)> = vec![
+            ("/opt/chromium.org/chromium/chromium-browser".into(), true),
+            ("/opt/google/chrome/chrome".into(), true),
+            // aws lambda, the shared libraries are extracted to /tmp/aws/lib
+            ("/tmp/chromium".into(), true),
+            // debian, arch
+            ("/usr/lib/chromium/chromium".into(), options.distro),
+            // ubuntu
+            (
+                "/usr/lib/chromium-browser/chromium-browser".into(),
+                options.distro,
+            ),
+            // alpine
+            ("/usr/lib/chromium/chrome".into(), options.distro),
+            // fedora
+            (
+                "/usr/lib64/chromium-browser/chromium-browser".into(),
+                options.distro,
+            ),
+            ("/snap/bin/chromium".into(), options.distro),
+            ("/snap/bin/brave".into(), true),
+        ];
+        let mut flatpak_dirs = vec![PathBuf::from("/var/lib/flatpak/exports/bin")];
+        if let Some(home) = env::var_os("HOME") {
+            flatpak_dirs.push(Path::new(&home).join(".local/share/flatpak/exports/bin"));
+        }
+        for dir in flatpak_dirs {
+            for app in [
+                "com.google.Chrome",
+                "com.google.ChromeDev",
+                "org.chromium.Chromium",
+                "com.microsoft.Edge",
+                "com.brave.Browser",
+            ] {
+                paths.push((dir.join(app), true));
+            }
+        }
+        paths
+    };
     #[cfg(windows)]
-    let default_paths = [(
-        r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
-        options.msedge,
-    )];
-    #[cfg(target_os = "macos")]
-    let default_paths = [
+    let default_paths: Vec<(PathBuf, bool)> = vec![
         (
-            "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+            r"C:\Program Files\Google\Chrome\Application\chrome.exe".into(),
             true,
         ),
         (
-            "/Applications/Google Chrome Beta.app/Contents/MacOS/Google Chrome Beta",
-            options.unstable,
-        ),
-        (
-            "/Applications/Google Chrome Dev.app/Contents/MacOS/Google Chrome Dev",
-            options.unstable,
-        ),
-        (
-            "/Applications/Google Chrome Canary.app/Contents/MacOS/Google Chrome Canary",
-            options.unstable,
-        ),
-        ("/Applications/Chromium.app/Contents/MacOS/Chromium", true),
-        (
-            "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
-            options.msedge,
-        ),
-        (
-            "/Applications/Microsoft Edge Beta.app/Contents/MacOS/Microsoft Edge Beta",
-            options.msedge && options.unstable,
-        ),
-        (
-            "/Applications/Microsoft Edge Dev.app/Contents/MacOS/Microsoft Edge Dev",
-            options.msedge && options.unstable,
+            r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe".into(),
+            true,
         ),
         (
-            "/Applications/Microsoft Edge Canary.app/Contents/MacOS/Microsoft Edge Canary",
-            options.msedge && options.unstable,
+            r"C:\Program Files\BraveSoftware\Brave-Browser\Application\brave.exe".into(),
+            true,
         ),
     ];
+    #[cfg(target_os = "macos")]
+    let default_paths = [
+        "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+        "/Applications/Google Chrome Beta.app/Contents/MacOS/Google Chrome Beta",
+        "/Applications/Google Chrome Dev.app/Contents/MacOS/Google Chrome Dev",
+        "/Applications/Google Chrome Canary.app/Contents/MacOS/Google Chrome Canary",
+        "/Applications/Chromium.app/Contents/MacOS/Chromium",
+        "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        "/Applications/Microsoft Edge Beta.app/Contents/MacOS/Microsoft Edge Beta",
+        "/Applications/Microsoft Edge Dev.app/Contents/MacOS/Microsoft Edge Dev",
+        "/Applications/Microsoft Edge Canary.app/Contents/MacOS/Microsoft Edge Canary",
+        "/Applications/Brave Browser.app/Contents/MacOS/Brave Browser",
+    ]
+    .map(|path| (PathBuf::from(path), true));
 
-    for (path, allowed) in default_paths {
-        if !allowed {
-            continue;
-        }
-        if Path::new(path).exists() {
-            return Some(path.into());
-        }
-    }
+    default_paths
+        .into_iter()
+        .filter(|(path, allowed)| *allowed && is_executable(path))
+        .map(|(path, _)| path)
+        .collect()
+}
 
-    None
+/// Whether the path is a file that can be run, following symlinks
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
 }
 
 #[cfg(windows)]
@@ -161,3 +411,152 @@ fn get_by_registry() -> Option<PathBuf> {
         .map(PathBuf::from)
         .ok()
 }
+
+#[cfg(not(windows))]
+fn get_by_registry() -> Option<PathBuf> {
+    None
+}
+
+/// `chrome.exe --version` opens a window instead of printing the version, but
+/// the installation contains a folder named after the version
+#[cfg(windows)]
+fn probe_version(path: &Path) -> Option<Version> {
+    fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(|entry| Version::parse(entry.ok()?.file_name().to_str()?))
+        .max()
+}
+
+/// Runs the executable with `--version`, e.g. `Google Chrome 118.0.5993.70`
+#[cfg(not(windows))]
+fn probe_version(path: &Path) -> Option<Version> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    let mut child = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    output.split_whitespace().find_map(Version::parse)
+}
+
+/// The name of the app bundle containing `path` or else its file name, the
+/// other directories say nothing about the browser, e.g. a home named `dev`
+fn browser_name(path: &Path) -> &OsStr {
+    path.ancestors()
+        .filter_map(Path::file_name)
+        .find(|name| Path::new(name).extension() == Some(OsStr::new("app")))
+        .or_else(|| path.file_name())
+        .unwrap_or_default()
+}
+
+/// Splits the path into lowercase words, e.g. `com.google.ChromeDev` into
+/// `com`, `google`, `chrome` and `dev`
+fn words(path: &Path) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in path.to_string_lossy().chars() {
+        let camel_case =
+            c.is_uppercase() && matches!(word.chars().last(), Some(last) if last.is_lowercase());
+        if (!c.is_alphanumeric() || camel_case) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_paths() {
+        let browser = |path: &str| {
+            let browser = DetectedBrowser::new(path.into());
+            (browser.kind, browser.channel)
+        };
+        assert_eq!(
+            browser("/usr/bin/google-chrome-stable"),
+            (BrowserKind::Chrome, Channel::Stable)
+        );
+        assert_eq!(
+            browser("/var/lib/flatpak/exports/bin/com.google.ChromeDev"),
+            (BrowserKind::Chrome, Channel::Dev)
+        );
+        assert_eq!(
+            browser("/Applications/Microsoft Edge Canary.app/Contents/MacOS/Microsoft Edge Canary"),
+            (BrowserKind::Edge, Channel::Canary)
+        );
+        assert_eq!(
+            browser("/usr/lib/chromium-browser/chromium-browser"),
+            (BrowserKind::Chromium, Channel::Stable)
+        );
+        assert_eq!(
+            browser("/opt/chromium.org/chromium/chromium-browser"),
+            (BrowserKind::Chromium, Channel::Stable)
+        );
+        assert_eq!(
+            browser("/home/dev/bin/brave-browser-beta"),
+            (BrowserKind::Brave, Channel::Beta)
+        );
+        assert_eq!(
+            browser("/home/dev/unstable/chrome"),
+            (BrowserKind::Chrome, Channel::Stable)
+        );
+        assert_eq!(
+            browser("/Applications/Google Chrome Beta.app/Contents/MacOS/Google Chrome"),
+            (BrowserKind::Chrome, Channel::Beta)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_executable_files_are_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("chromiumoxide-exec-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("chrome");
+        fs::write(&file, "").unwrap();
+        assert!(!is_executable(&dir));
+        assert!(!is_executable(&file));
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(is_executable(&file));
+        assert!(!is_executable(&dir.join("missing")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parses_version() {
+        let version = Version::parse("118.0.5993.70").unwrap();
+        assert_eq!(version.major, 118);
+        assert_eq!(version.to_string(), "118.0.5993.70");
+        assert!(Version::parse("119.0").unwrap() > version);
+        assert_eq!(Version::parse("Chrome"), None);
+        assert_eq!(Version::parse("118"), None);
+        assert_eq!(Version::parse("1.2.3.4.5"), None);
+    }
+}
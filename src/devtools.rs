@@ -0,0 +1,414 @@
+//! A client for the http endpoints of the DevTools server of a browser.
+//!
+//! Besides the websocket a browser started with `--remote-debugging-port`
+//! serves a few json endpoints to discover it and its targets, see
+//! <https://chromedevtools.github.io/devtools-protocol/#endpoints>.
+//!
+//! Remote browsers behind a proxy often require authentication, the client
+//! sends the configured headers and query parameters with every request.
+//! Endpoints served over https need the `rustls` or `native-tls` feature.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use url::Url;
+
+use chromiumoxide_cdp::cdp::browser_protocol::target::TargetId;
+
+use crate::browser::BrowserConnection;
+use crate::compat::RemoteProtocol;
+use crate::error::{DevToolsError, Result};
+
+/// A target as listed by the `/json/list` endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevToolsTarget {
+    pub id: String,
+    /// The kind of the target, e.g. `page`, `iframe` or `service_worker`
+    #[serde(rename = "type")]
+    pub target_type: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub description: String,
+    /// The websocket to talk to this target directly, missing if a client is
+    /// already attached to it
+    pub web_socket_debugger_url: Option<String>,
+    pub devtools_frontend_url: Option<String>,
+    pub favicon_url: Option<String>,
+    pub parent_id: Option<String>,
+}
+
+impl DevToolsTarget {
+    /// The id of this target in the protocol
+    pub fn target_id(&self) -> TargetId {
+        TargetId::new(self.id.clone())
+    }
+}
+
+/// Talks to the `/json/*` endpoints of a browser's DevTools server.
+///
+/// ```no_run
+/// # use chromiumoxide::devtools::DevToolsHttpClient;
+/// # async fn demo() -> chromiumoxide::error::Result<()> {
+/// let client = DevToolsHttpClient::builder("https://chrome.example.com")
+///     .query("token", "secret")
+///     .build()?;
+/// for target in client.list().await? {
+///     println!("{} {}", target.target_type, target.url);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DevToolsHttpClient {
+    /// The root of the endpoints, always ends with `/`
+    base: Url,
+    client: reqwest::Client,
+    headers: HeaderMap,
+}
+
+impl DevToolsHttpClient {
+    /// A client for the DevTools server at `url`.
+    ///
+    /// `url` may be the `http(s)` url of the server or of any of its
+    /// endpoints, or the `ws(s)` url of the browser or of a target.
+    pub fn new(url: impl AsRef<str>) -> Result<Self, DevToolsError> {
+        Self::builder(url).build()
+    }
+
+    pub fn builder(url: impl AsRef<str>) -> DevToolsHttpClientBuilder {
+        DevToolsHttpClientBuilder {
+            url: url.as_ref().to_string(),
+            headers: Vec::new(),
+            query: Vec::new(),
+            timeout: None,
+            client: None,
+        }
+    }
+
+    /// The root of the endpoints
+    pub fn base_url(&self) -> &Url {
+        &self.base
+    }
+
+    /// The headers sent with every request
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Browser version metadata from `/json/version`, including the websocket
+    /// of the browser
+    pub async fn version(&self) -> Result<BrowserConnection, DevToolsError> {
+        let (mut version, remote): (BrowserConnection, _) = self
+            .request(Method::GET, self.endpoint("json/version")?)
+            .await?;
+        version.web_socket_debugger_url = rewrite_ws_url(version.web_socket_debugger_url, remote);
+        Ok(version)
+    }
+
+    /// All targets from `/json/list`
+    pub async fn list(&self) -> Result<Vec<DevToolsTarget>, DevToolsError> {
+        let (mut targets, remote): (Vec<DevToolsTarget>, _) = self
+            .request(Method::GET, self.endpoint("json/list")?)
+            .await?;
+        for target in targets.iter_mut() {
+            target.web_socket_debugger_url = target
+                .web_socket_debugger_url
+                .take()
+                .map(|url| rewrite_ws_url(url, remote));
+        }
+        Ok(targets)
+    }
+
+    /// Opens a new tab via `/json/new`, with `url` or `about:blank`.
+    ///
+    /// The browser reads the whole query as the url to open, so the query
+    /// parameters of [`DevToolsHttpClientBuilder::query`] are not sent.
+    pub async fn new_target(&self, url: Option<&str>) -> Result<DevToolsTarget, DevToolsError> {
+        // newer browsers only accept `PUT` here
+        let (mut target, remote): (DevToolsTarget, _) = self
+            .request(Method::PUT, self.new_target_endpoint(url)?)
+            .await?;
+        target.web_socket_debugger_url = target
+            .web_socket_debugger_url
+            .map(|url| rewrite_ws_url(url, remote));
+        Ok(target)
+    }
+
+    /// Brings the target into the foreground via `/json/activate`
+    pub async fn activate(&self, target_id: impl AsRef<str>) -> Result<(), DevToolsError> {
+        let path = format!("json/activate/{}", target_id.as_ref());
+        self.send(Method::GET, self.endpoint(&path)?).await?;
+        Ok(())
+    }
+
+    /// Closes the target via `/json/close`
+    pub async fn close(&self, target_id: impl AsRef<str>) -> Result<(), DevToolsError> {
+        let path = format!("json/close/{}", target_id.as_ref());
+        self.send(Method::GET, self.endpoint(&path)?).await?;
+        Ok(())
+    }
+
+    /// The protocol definition the browser speaks, from `/json/protocol`
+    pub async fn protocol(&self) -> Result<serde_json::Value, DevToolsError> {
+        Ok(self
+            .request(Method::GET, self.endpoint("json/protocol")?)
+            .await?
+            .0)
+    }
+
+    /// The typed protocol definition for [`ProtocolCompat`](crate::compat::ProtocolCompat)
+    pub(crate) async fn protocol_definition(&self) -> Result<RemoteProtocol, DevToolsError> {
+        Ok(self
+            .request(Method::GET, self.endpoint("json/protocol")?)
+            .await?
+            .0)
+    }
+
+    /// The url of the endpoint at `path`, with the configured query
+    fn endpoint(&self, path: &str) -> Result<Url, DevToolsError> {
+        let mut url = self
+            .base
+            .join(path)
+            .map_err(|_| DevToolsError::InvalidUrl(path.to_string()))?;
+        url.set_query(self.base.query());
+        Ok(url)
+    }
+
+    /// The url of `/json/new`, whose query is the encoded `url` to open
+    fn new_target_endpoint(&self, url: Option<&str>) -> Result<Url, DevToolsError> {
+        let mut endpoint = self.endpoint("json/new")?;
+        let query = url.map(|url| utf8_percent_encode(url, NON_ALPHANUMERIC).to_string());
+        endpoint.set_query(query.as_deref());
+        Ok(endpoint)
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: Url,
+    ) -> Result<(T, Option<SocketAddr>), DevToolsError> {
+        let (url, body, remote) = self.send(method, url).await?;
+        let value = serde_json::from_slice(&body)
+            .map_err(|source| DevToolsError::InvalidResponse { url, source })?;
+        Ok((value, remote))
+    }
+
+    /// Sends the request and returns the url, the body and the address of the
+    /// server that answered
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+    ) -> Result<(String, Vec<u8>, Option<SocketAddr>), DevToolsError> {
+        let resp = self
+            .client
+            .request(method, url.clone())
+            .headers(self.headers.clone())
+            .send()
+            .await?;
+        let status = resp.status();
+        let remote = resp.remote_addr();
+        let body = resp.bytes().await?;
+        if !status.is_success() {
+            return Err(DevToolsError::Status {
+                url: url.to_string(),
+                status: status.as_u16(),
+                body: String::from_utf8_lossy(&body).into_owned(),
+            });
+        }
+        Ok((url.to_string(), body.to_vec(), remote))
+    }
+}
+
+/// Builds a [`DevToolsHttpClient`] for remote browsers
+#[derive(Debug, Clone)]
+pub struct DevToolsHttpClientBuilder {
+    url: String,
+    headers: Vec<(String, String)>,
+    query: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    client: Option<reqwest::Client>,
+}
+
+impl DevToolsHttpClientBuilder {
+    /// Sends the header with every request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Authenticates with a bearer token
+    pub fn bearer_auth(self, token: impl AsRef<str>) -> Self {
+        self.header(AUTHORIZATION.as_str(), format!("Bearer {}", token.as_ref()))
+    }
+
+    /// Authenticates with http basic authentication
+    pub fn basic_auth(self, username: impl AsRef<str>, password: impl AsRef<str>) -> Self {
+        let credentials =
+            crate::utils::base64::encode(format!("{}:{}", username.as_ref(), password.as_ref()));
+        self.header(AUTHORIZATION.as_str(), format!("Basic {credentials}"))
+    }
+
+    /// Appends the query parameter to every request except
+    /// [`DevToolsHttpClient::new_target`], like the `token` of hosted
+    /// browsers. Prefer a header for authentication where the server
+    /// supports it.
+    pub fn query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+
+    /// Fails requests that take longer than `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the requests with `client`, e.g. to configure custom root
+    /// certificates. The `timeout` is ignored then.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn build(self) -> Result<DevToolsHttpClient, DevToolsError> {
+        let mut base = base_url(&self.url)?;
+        if !self.query.is_empty() {
+            base.query_pairs_mut().extend_pairs(self.query);
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| DevToolsError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| DevToolsError::InvalidHeader(name.to_string()))?;
+            headers.append(name, value);
+        }
+
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = reqwest::Client::builder();
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                builder.build()?
+            }
+        };
+
+        Ok(DevToolsHttpClient {
+            base,
+            client,
+            headers,
+        })
+    }
+}
+
+/// The root of the DevTools endpoints for the `url` of the server, any of its
+/// endpoints or websockets
+fn base_url(url: &str) -> Result<Url, DevToolsError> {
+    let invalid = || DevToolsError::InvalidUrl(url.to_string());
+    let mut base = Url::parse(url).map_err(|_| invalid())?;
+    let scheme = match base.scheme() {
+        "http" | "ws" => "http",
+        "https" | "wss" => "https",
+        _ => return Err(invalid()),
+    };
+    base.set_scheme(scheme).map_err(|_| invalid())?;
+
+    // servers behind a proxy may be mounted below a path
+    let path = base.path().to_string();
+    let prefix = ["/json", "/devtools/"]
+        .iter()
+        .filter_map(|endpoint| path.find(endpoint))
+        .min()
+        .map(|idx| &path[..idx])
+        .unwrap_or(&path)
+        .trim_end_matches('/');
+    base.set_path(&format!("{prefix}/"));
+    base.set_fragment(None);
+    Ok(base)
+}
+
+/// Proxies may answer with the websocket of the browser on their loopback
+/// interface, which is reachable at the address that answered instead
+fn rewrite_ws_url(ws_url: String, remote: Option<SocketAddr>) -> String {
+    match remote {
+        Some(remote) => ws_url.replace("127.0.0.1", &remote.ip().to_string()),
+        None => ws_url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_url_of_endpoints() {
+        for url in [
+            "http://localhost:9222",
+            "http://localhost:9222/",
+            "http://localhost:9222/json/version",
+            "ws://localhost:9222/devtools/browser/abc",
+            "ws://localhost:9222/devtools/page/abc",
+        ] {
+            assert_eq!(base_url(url).unwrap().as_str(), "http://localhost:9222/");
+        }
+        assert_eq!(
+            base_url("wss://example.com/chrome/json/list")
+                .unwrap()
+                .as_str(),
+            "https://example.com/chrome/"
+        );
+        assert!(base_url("file:///json").is_err());
+    }
+
+    #[tokio::test]
+    async fn queries_endpoints() {
+        let mock = crate::testing::MockBrowser::start().await.unwrap();
+        let client = DevToolsHttpClient::new(mock.url()).unwrap();
+
+        let version = client.version().await.unwrap();
+        assert_eq!(version.web_socket_debugger_url, mock.ws_url());
+
+        // the mock serves no target list
+        let err = client.list().await.unwrap_err();
+        assert!(
+            matches!(err, DevToolsError::Status { status: 404, .. }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn endpoints_keep_query() {
+        let client = DevToolsHttpClient::builder("https://example.com")
+            .query("token", "secret")
+            .build()
+            .unwrap();
+        assert_eq!(
+            client.endpoint("json/list").unwrap().as_str(),
+            "https://example.com/json/list?token=secret"
+        );
+        assert_eq!(
+            client
+                .new_target_endpoint(Some("https://example.com/?a=1&b=2#top"))
+                .unwrap()
+                .as_str(),
+            "https://example.com/json/new?https%3A%2F%2Fexample%2Ecom%2F%3Fa%3D1%26b%3D2%23top"
+        );
+        assert_eq!(
+            client.new_target_endpoint(None).unwrap().as_str(),
+            "https://example.com/json/new"
+        );
+    }
+}
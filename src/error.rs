@@ -10,7 +10,10 @@ use futures::channel::oneshot::Canceled;
 use thiserror::Error;
 
 use chromiumoxide_cdp::cdp::browser_protocol::page::FrameId;
+use chromiumoxide_cdp::cdp::browser_protocol::target::TargetId;
 
+use crate::compat::ProtocolCompat;
+use crate::detection::DetectedBrowser;
 use crate::handler::frame::NavigationError;
 use chromiumoxide_cdp::cdp::js_protocol::runtime::ExceptionDetails;
 
@@ -40,6 +43,19 @@ pub enum CdpError {
     LaunchIo(#[source] io::Error, BrowserStderr),
     #[error("Request timed out.")]
     Timeout,
+    /// The connection to the browser was lost before the response arrived
+    #[error("The connection to the browser was lost.")]
+    BrowserDisconnected,
+    /// The target the request was sent to crashed
+    #[error("Target {0:?} crashed.")]
+    TargetCrashed(TargetId),
+    /// A bounded event listener fell behind and was closed after dropping
+    /// this many events
+    #[error("The event listener fell behind and was closed after dropping {0} events.")]
+    Lagged(u64),
+    /// The `BrowserPool` was closed
+    #[error("The browser pool is closed.")]
+    PoolClosed,
     #[error("FrameId {0:?} not found.")]
     FrameNotFound(FrameId),
     /// Error message related to a cdp response that is not a
@@ -58,6 +74,16 @@ pub enum CdpError {
     JavascriptException(Box<ExceptionDetails>),
     #[error("{0}")]
     Url(#[from] url::ParseError),
+    /// A request to the http endpoints of the DevTools server failed
+    #[error("{0}")]
+    DevTools(#[from] DevToolsError),
+    /// The protocol of the browser differs from the generated types
+    #[error("{0}")]
+    IncompatibleProtocol(Box<ProtocolCompat>),
+    /// Fetching the browser on launch failed
+    #[cfg(feature = "fetcher")]
+    #[error("{0}")]
+    Fetcher(#[from] chromiumoxide_fetcher::FetcherError),
 }
 impl CdpError {
     pub fn msg(msg: impl Into<String>) -> Self {
@@ -65,6 +91,47 @@ impl CdpError {
     }
 }
 
+/// Errors of the [`DevToolsHttpClient`](crate::devtools::DevToolsHttpClient)
+#[derive(Debug, Error)]
+pub enum DevToolsError {
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{url} responded with status {status}: {body}")]
+    Status {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    #[error("Invalid response from {url}: {source}")]
+    InvalidResponse {
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("{0} is not the url of a DevTools server")]
+    InvalidUrl(String),
+    #[error("Invalid header {0}")]
+    InvalidHeader(String),
+    #[error("{0} does not expose a websocket to connect to")]
+    NoWebSocketUrl(String),
+    /// The browser was launched without the http endpoints, e.g. with a pipe
+    #[error("The browser has no DevTools http endpoints")]
+    Unavailable,
+}
+
+/// Errors of the browser [`detection`](crate::detection)
+#[derive(Debug, Error)]
+pub enum DetectionError {
+    #[error("Could not auto detect a chrome executable")]
+    NotFound,
+    /// Browsers were detected, but none of at least the minimum version
+    #[error("Could not auto detect a chrome executable of at least version {min_version}")]
+    TooOld {
+        min_version: u32,
+        found: Vec<DetectedBrowser>,
+    },
+}
+
 #[derive(Debug, Error)]
 pub enum ChannelError {
     #[error("{0}")]
@@ -8,7 +8,7 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use crate::cmd::{to_command_response, CommandMessage};
+use crate::cmd::{to_command_response, CommandMessage, CommandOptions};
 use crate::error::Result;
 use crate::handler::target::TargetMessage;
 use chromiumoxide_cdp::cdp::browser_protocol::target::SessionId;
@@ -39,16 +39,27 @@ impl<T: Command> CommandFuture<T> {
         cmd: T,
         target_sender: mpsc::Sender<TargetMessage>,
         session: Option<SessionId>,
+    ) -> Result<Self> {
+        Self::with_options(cmd, target_sender, session, CommandOptions::default())
+    }
+
+    /// Creates a future whose timeout is overridden by the `options`, fails
+    /// if the command can't be serialized
+    pub fn with_options(
+        cmd: T,
+        target_sender: mpsc::Sender<TargetMessage>,
+        session: Option<SessionId>,
+        options: CommandOptions,
     ) -> Result<Self> {
         let (tx, rx_command) = oneshot_channel::<Result<Response>>();
         let method = cmd.identifier();
 
-        let message = Some(TargetMessage::Command(CommandMessage::with_session(
-            cmd, tx, session,
-        )?));
+        let message = Some(TargetMessage::Command(
+            CommandMessage::with_session(cmd, tx, session)?.with_timeout(options.timeout),
+        ));
 
-        let delay = futures_timer::Delay::new(std::time::Duration::from_millis(
-            crate::handler::REQUEST_TIMEOUT,
+        let delay = futures_timer::Delay::new(options.timeout.unwrap_or(
+            std::time::Duration::from_millis(crate::handler::REQUEST_TIMEOUT),
         ));
 
         Ok(Self {
@@ -1,8 +1,10 @@
 use chromiumoxide_cdp::cdp::browser_protocol::emulation::{
-    ScreenOrientation, ScreenOrientationType, SetDeviceMetricsOverrideParams,
+    MediaFeature, ScreenOrientation, ScreenOrientationType, SetCpuThrottlingRateParams,
+    SetDeviceMetricsOverrideParams, SetEmulatedVisionDeficiencyParams,
+    SetEmulatedVisionDeficiencyType, SetGeolocationOverrideParams, SetLocaleOverrideParams,
     SetTouchEmulationEnabledParams,
 };
-use chromiumoxide_types::Method;
+use chromiumoxide_types::{Method, MethodId};
 
 use crate::cmd::CommandChain;
 use crate::handler::viewport::Viewport;
@@ -14,6 +16,8 @@ pub struct EmulationManager {
     pub has_touch: bool,
     pub needs_reload: bool,
     pub request_timeout: Duration,
+    /// The media features that are currently emulated
+    pub media_features: Vec<MediaFeature>,
 }
 
 impl EmulationManager {
@@ -23,6 +27,7 @@ impl EmulationManager {
             has_touch: false,
             needs_reload: false,
             request_timeout,
+            media_features: Vec::new(),
         }
     }
 
@@ -62,4 +67,129 @@ impl EmulationManager {
             || self.has_touch != viewport.has_touch;
         chain
     }
+
+    /// Returns the complete set of media features that should be sent to the
+    /// browser, without updating the emulated ones.
+    ///
+    /// If `merge` is set, features with the same name are replaced and all
+    /// other currently emulated features are kept.
+    pub fn merge_media_features(
+        &self,
+        features: Vec<MediaFeature>,
+        merge: bool,
+    ) -> Vec<MediaFeature> {
+        if !merge {
+            return features;
+        }
+        let mut merged = self.media_features.clone();
+        for feature in features {
+            merged.retain(|f| f.name != feature.name);
+            merged.push(feature);
+        }
+        merged
+    }
+
+    /// Updates the emulated media features once the browser applied them
+    pub fn set_media_features(&mut self, features: Vec<MediaFeature>) {
+        self.media_features = features;
+    }
+}
+
+/// The emulation settings of a browser context, that every page opened in
+/// the context is initialized with.
+///
+/// Unset fields are left at the browser's defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextEmulation {
+    pub vision_deficiency: Option<SetEmulatedVisionDeficiencyType>,
+    pub geolocation: Option<SetGeolocationOverrideParams>,
+    pub locale: Option<String>,
+    pub cpu_throttling_rate: Option<f64>,
+}
+
+impl ContextEmulation {
+    /// Overwrites the settings that are set in `update`
+    pub fn merge(&mut self, update: ContextEmulation) {
+        if update.vision_deficiency.is_some() {
+            self.vision_deficiency = update.vision_deficiency;
+        }
+        if update.geolocation.is_some() {
+            self.geolocation = update.geolocation;
+        }
+        if update.locale.is_some() {
+            self.locale = update.locale;
+        }
+        if update.cpu_throttling_rate.is_some() {
+            self.cpu_throttling_rate = update.cpu_throttling_rate;
+        }
+    }
+
+    /// The commands to apply the settings to a new page
+    pub fn init_commands(&self) -> Vec<(MethodId, serde_json::Value)> {
+        let mut cmds = Vec::new();
+        if let Some(deficiency) = self.vision_deficiency.clone() {
+            let params = SetEmulatedVisionDeficiencyParams::new(deficiency);
+            cmds.push((params.identifier(), serde_json::to_value(params).unwrap()));
+        }
+        if let Some(params) = self.geolocation.clone() {
+            cmds.push((params.identifier(), serde_json::to_value(params).unwrap()));
+        }
+        if let Some(locale) = self.locale.clone() {
+            let params = SetLocaleOverrideParams {
+                locale: Some(locale),
+            };
+            cmds.push((params.identifier(), serde_json::to_value(params).unwrap()));
+        }
+        if let Some(rate) = self.cpu_throttling_rate {
+            let params = SetCpuThrottlingRateParams::new(rate);
+            cmds.push((params.identifier(), serde_json::to_value(params).unwrap()));
+        }
+        cmds
+    }
+}
+
+/// The value of the `prefers-color-scheme` media feature to emulate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+    NoPreference,
+}
+
+impl ColorScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+            ColorScheme::NoPreference => "no-preference",
+        }
+    }
+}
+
+impl From<ColorScheme> for MediaFeature {
+    fn from(scheme: ColorScheme) -> Self {
+        MediaFeature::new("prefers-color-scheme", scheme.as_str())
+    }
+}
+
+/// The value of the `prefers-reduced-motion` media feature to emulate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReducedMotion {
+    Reduce,
+    NoPreference,
+}
+
+impl ReducedMotion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReducedMotion::Reduce => "reduce",
+            ReducedMotion::NoPreference => "no-preference",
+        }
+    }
+}
+
+impl From<ReducedMotion> for MediaFeature {
+    fn from(motion: ReducedMotion) -> Self {
+        MediaFeature::new("prefers-reduced-motion", motion.as_str())
+    }
 }
@@ -20,6 +20,8 @@ impl PeriodicJob {
     pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> bool {
         if !Future::poll(Pin::new(&mut self.delay), cx).is_pending() {
             self.delay.reset(self.interval);
+            // register the waker for the next interval
+            let _ = Future::poll(Pin::new(&mut self.delay), cx);
             return true;
         }
         false
@@ -37,3 +39,39 @@ impl Default for PeriodicJob {
         Self::new(Duration::from_millis(REQUEST_TIMEOUT))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use futures::task::{waker, ArcWake};
+
+    use super::*;
+
+    struct CountWakes(AtomicUsize);
+
+    impl ArcWake for CountWakes {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// The handler only polls the job when it is woken, so a ready job must
+    /// wake it again after the next interval, even if nothing else happens
+    #[test]
+    fn wakes_after_every_interval() {
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let waker = waker(Arc::clone(&wakes));
+        let mut cx = Context::from_waker(&waker);
+        let mut job = PeriodicJob::new(Duration::from_millis(20));
+
+        assert!(!job.poll_ready(&mut cx));
+        thread::sleep(Duration::from_millis(100));
+        assert!(job.poll_ready(&mut cx));
+        let woken = wakes.0.load(Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(100));
+        assert!(wakes.0.load(Ordering::SeqCst) > woken);
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Receives measurements of a `Handler`, for example to export them to a
+/// monitoring system.
+///
+/// The methods are called from within `Handler::poll_next` and should return
+/// quickly. All of them do nothing by default. The number of pending commands
+/// and the traffic are reported every `HandlerConfig::request_timeout`, when
+/// the handler evicts timed out commands.
+///
+/// ```
+/// # use std::sync::atomic::{AtomicU64, Ordering};
+/// # use std::time::Duration;
+/// # use chromiumoxide::handler::metrics::Metrics;
+/// #[derive(Debug, Default)]
+/// struct Timeouts(AtomicU64);
+///
+/// impl Metrics for Timeouts {
+///     fn command_timed_out(&self, _method: &str) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait Metrics: fmt::Debug + Send + Sync {
+    /// The number of commands waiting for their response changed
+    fn pending_commands(&self, _count: usize) {}
+
+    /// A command completed after `latency`, either with a response or with an
+    /// error like a timeout or a lost connection
+    fn command_completed(&self, _method: &str, _latency: Duration, _success: bool) {}
+
+    /// A command got no response within the request timeout
+    fn command_timed_out(&self, _method: &str) {}
+
+    /// An event was received from the browser
+    fn event_received(&self, _method: &str) {}
+
+    /// Bytes were sent to the browser since the last report
+    fn bytes_sent(&self, _bytes: u64) {}
+
+    /// Bytes were received from the browser since the last report
+    fn bytes_received(&self, _bytes: u64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::browser::Browser;
+    use crate::handler::HandlerConfig;
+    use crate::testing::MockBrowser;
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        completed: Mutex<Vec<(String, bool)>>,
+        sent: Mutex<u64>,
+        received: Mutex<u64>,
+    }
+
+    impl Metrics for Recorder {
+        fn command_completed(&self, method: &str, _latency: Duration, success: bool) {
+            self.completed
+                .lock()
+                .unwrap()
+                .push((method.to_string(), success));
+        }
+
+        fn bytes_sent(&self, bytes: u64) {
+            *self.sent.lock().unwrap() += bytes;
+        }
+
+        fn bytes_received(&self, bytes: u64) {
+            *self.received.lock().unwrap() += bytes;
+        }
+    }
+
+    #[async_std::test]
+    async fn reports_commands_and_traffic() {
+        let mock = MockBrowser::start().await.unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let config = HandlerConfig {
+            metrics: Some(recorder.clone()),
+            request_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let (browser, mut handler) = Browser::connect_with_config(mock.ws_url(), config)
+            .await
+            .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+
+        browser.version().await.unwrap();
+        assert!(recorder
+            .completed
+            .lock()
+            .unwrap()
+            .contains(&("Browser.getVersion".to_string(), true)));
+
+        // the traffic is reported on the next eviction interval
+        for _ in 0..50 {
+            if *recorder.received.lock().unwrap() > 0 {
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(*recorder.sent.lock().unwrap() > 0);
+        assert!(*recorder.received.lock().unwrap() > 0);
+    }
+}
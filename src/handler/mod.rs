@@ -1,13 +1,16 @@
 use std::collections::{HashMap, HashSet};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use fnv::FnvHashMap;
-use futures::channel::mpsc::Receiver;
+use futures::channel::mpsc::{Receiver, UnboundedSender};
 use futures::channel::oneshot::Sender as OneshotSender;
 use futures::stream::{Fuse, Stream, StreamExt};
 use futures::task::{Context, Poll};
 
+use crate::auth::Credentials;
+use crate::compat::ProtocolCheck;
 use crate::listeners::{EventListenerRequest, EventListeners};
 use chromiumoxide_cdp::cdp::browser_protocol::browser::*;
 use chromiumoxide_cdp::cdp::browser_protocol::target::*;
@@ -21,14 +24,21 @@ use crate::cmd::{to_command_response, CommandMessage};
 use crate::conn::Connection;
 use crate::error::{CdpError, Result};
 use crate::handler::browser::BrowserContext;
+use crate::handler::emulation::ContextEmulation;
 use crate::handler::frame::FrameNavigationRequest;
 use crate::handler::frame::{NavigationError, NavigationId, NavigationOk};
 use crate::handler::job::PeriodicJob;
+use crate::handler::metrics::Metrics;
+use crate::handler::network::NetworkEmulation;
 use crate::handler::session::Session;
+use crate::handler::supervision::{
+    LifecycleEvent, ReconnectPolicy, ReconnectSource, RelaunchedProcess, Supervisor,
+};
 use crate::handler::target::TargetEvent;
 use crate::handler::target::{Target, TargetConfig};
 use crate::handler::viewport::Viewport;
 use crate::page::Page;
+use crate::transport::Recorder;
 
 /// Standard timeout in MS
 pub const REQUEST_TIMEOUT: u64 = 30_000;
@@ -41,9 +51,11 @@ pub mod frame;
 pub mod http;
 pub mod httpfuture;
 mod job;
+pub mod metrics;
 pub mod network;
 mod page;
 mod session;
+pub mod supervision;
 pub mod target;
 pub mod target_message_future;
 pub mod viewport;
@@ -54,9 +66,8 @@ pub mod viewport;
 #[derive(Debug)]
 pub struct Handler {
     /// Commands that are being processed and awaiting a response from the
-    /// chromium instance together with the timestamp when the request
-    /// started.
-    pending_commands: FnvHashMap<CallId, (PendingRequest, MethodId, Instant)>,
+    /// chromium instance
+    pending_commands: FnvHashMap<CallId, PendingCommand>,
     /// Connection to the browser instance
     from_browser: Fuse<Receiver<HandlerMessage>>,
     default_browser_context: BrowserContext,
@@ -83,6 +94,20 @@ pub struct Handler {
     event_listeners: EventListeners,
     /// Keeps track is the browser is closing
     closing: bool,
+    /// The last network emulation state of every browser context, applied to
+    /// new targets in that context
+    network_emulations: HashMap<BrowserContext, NetworkEmulation>,
+    /// The emulation settings of every browser context, applied to new
+    /// targets in that context
+    context_emulations: HashMap<BrowserContext, ContextEmulation>,
+    /// Credentials for the proxy server of a browser context
+    proxy_credentials: HashMap<BrowserContext, Credentials>,
+    /// Whether the connection to the browser is lost
+    disconnected: bool,
+    /// Reconnects to the browser and notifies about lifecycle changes
+    supervisor: Supervisor,
+    /// The measurements last reported to the metrics hook
+    reported: ReportedMetrics,
 }
 
 impl Handler {
@@ -93,12 +118,10 @@ impl Handler {
         rx: Receiver<HandlerMessage>,
         config: HandlerConfig,
     ) -> Self {
-        let discover = SetDiscoverTargetsParams::new(true);
-        let _ = conn.submit_command(
-            discover.identifier(),
-            None,
-            serde_json::to_value(discover).unwrap(),
-        );
+        if let Some(ref recorder) = config.recorder {
+            conn.set_recorder(recorder.clone());
+        }
+        discover_targets(&mut conn);
 
         let browser_contexts = config
             .context_ids
@@ -118,12 +141,29 @@ impl Handler {
             conn,
             evict_command_timeout: PeriodicJob::new(config.request_timeout),
             next_navigation_id: 0,
-            config,
             event_listeners: Default::default(),
             closing: false,
+            network_emulations: Default::default(),
+            context_emulations: Default::default(),
+            proxy_credentials: Default::default(),
+            disconnected: false,
+            supervisor: Supervisor::new(config.reconnect.clone()),
+            reported: Default::default(),
+            config,
         }
     }
 
+    /// Where to reconnect to if the connection is lost and a
+    /// `ReconnectPolicy` is configured
+    pub(crate) fn set_reconnect_source(&mut self, source: ReconnectSource) {
+        self.supervisor.set_source(source);
+    }
+
+    /// Where the processes of relaunches are handed over to the `Browser`
+    pub(crate) fn relaunched_process(&self) -> RelaunchedProcess {
+        self.supervisor.relaunched()
+    }
+
     /// Return the target with the matching `target_id`
     pub fn get_target(&self, target_id: &TargetId) -> Option<&Target> {
         self.targets.get(target_id)
@@ -194,8 +234,15 @@ impl Handler {
 
     /// Received a response to a request.
     fn on_response(&mut self, resp: Response) {
-        if let Some((req, method, _)) = self.pending_commands.remove(&resp.id) {
-            match req {
+        if let Some(cmd) = self.pending_commands.remove(&resp.id) {
+            cmd.finish(
+                self.config.metrics.as_deref(),
+                resp.error.as_ref().map(|err| err.message.as_str()),
+            );
+            let PendingCommand {
+                request, method, ..
+            } = cmd;
+            match request {
                 PendingRequest::CreateTarget(tx) => {
                     match to_command_response::<CreateTargetParams>(resp, method) {
                         Ok(resp) => {
@@ -256,18 +303,61 @@ impl Handler {
         }
     }
 
+    /// Keeps track of a submitted command until its response arrives
+    fn insert_pending(
+        &mut self,
+        call_id: CallId,
+        request: PendingRequest,
+        method: MethodId,
+        session_id: Option<SessionId>,
+        now: Instant,
+        deadline: Option<Instant>,
+    ) {
+        let target_id = match &request {
+            PendingRequest::InternalCommand(target_id) => Some(target_id),
+            _ => session_id
+                .as_ref()
+                .and_then(|session_id| self.sessions.get(session_id))
+                .map(|session| session.target_id()),
+        };
+        let span = tracing::debug_span!(
+            "cdp_command",
+            method = %method,
+            call_id = %call_id,
+            session = ?session_id,
+            target = ?target_id,
+            latency_ms = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        self.pending_commands.insert(
+            call_id,
+            PendingCommand {
+                request,
+                method,
+                session_id,
+                timestamp: now,
+                deadline: deadline.unwrap_or(now + self.config.request_timeout),
+                span,
+            },
+        );
+    }
+
     /// Submit a command initiated via channel
     pub(crate) fn submit_external_command(
         &mut self,
         msg: CommandMessage,
         now: Instant,
     ) -> Result<()> {
-        let call_id = self
-            .conn
-            .submit_command(msg.method.clone(), msg.session_id, msg.params)?;
-        self.pending_commands.insert(
+        let call_id =
+            self.conn
+                .submit_command(msg.method.clone(), msg.session_id.clone(), msg.params)?;
+        self.insert_pending(
             call_id,
-            (PendingRequest::ExternalCommand(msg.sender), msg.method, now),
+            PendingRequest::ExternalCommand(msg.sender),
+            msg.method,
+            msg.session_id,
+            now,
+            msg.deadline,
         );
         Ok(())
     }
@@ -278,14 +368,17 @@ impl Handler {
         req: CdpRequest,
         now: Instant,
     ) -> Result<()> {
-        let call_id = self.conn.submit_command(
-            req.method.clone(),
-            req.session_id.map(Into::into),
-            req.params,
-        )?;
-        self.pending_commands.insert(
+        let session_id: Option<SessionId> = req.session_id.map(Into::into);
+        let call_id =
+            self.conn
+                .submit_command(req.method.clone(), session_id.clone(), req.params)?;
+        self.insert_pending(
             call_id,
-            (PendingRequest::InternalCommand(target_id), req.method, now),
+            PendingRequest::InternalCommand(target_id),
+            req.method,
+            session_id,
+            now,
+            None,
         );
         Ok(())
     }
@@ -298,24 +391,33 @@ impl Handler {
             .submit_command(method.clone(), None, serde_json::to_value(msg).unwrap())
             .unwrap();
 
-        self.pending_commands
-            .insert(call_id, (PendingRequest::GetTargets(tx), method, now));
+        self.insert_pending(
+            call_id,
+            PendingRequest::GetTargets(tx),
+            method,
+            None,
+            now,
+            None,
+        );
     }
 
     /// Send the Request over to the server and store its identifier to handle
     /// the response once received.
     fn submit_navigation(&mut self, id: NavigationId, req: CdpRequest, now: Instant) {
+        let session_id: Option<SessionId> = req.session_id.map(Into::into);
         let call_id = self
             .conn
-            .submit_command(
-                req.method.clone(),
-                req.session_id.map(Into::into),
-                req.params,
-            )
+            .submit_command(req.method.clone(), session_id.clone(), req.params)
             .unwrap();
 
-        self.pending_commands
-            .insert(call_id, (PendingRequest::Navigate(id), req.method, now));
+        self.insert_pending(
+            call_id,
+            PendingRequest::Navigate(id),
+            req.method,
+            session_id,
+            now,
+            None,
+        );
     }
 
     fn submit_close(&mut self, tx: OneshotSender<Result<CloseReturns>>, now: Instant) {
@@ -331,8 +433,14 @@ impl Handler {
             )
             .unwrap();
 
-        self.pending_commands
-            .insert(call_id, (PendingRequest::CloseBrowser(tx), method, now));
+        self.insert_pending(
+            call_id,
+            PendingRequest::CloseBrowser(tx),
+            method,
+            None,
+            now,
+            None,
+        );
     }
 
     /// Process a message received by the target's page via channel
@@ -375,9 +483,13 @@ impl Handler {
                 match serde_json::to_value(params) {
                     Ok(params) => match self.conn.submit_command(method.clone(), None, params) {
                         Ok(call_id) => {
-                            self.pending_commands.insert(
+                            self.insert_pending(
                                 call_id,
-                                (PendingRequest::CreateTarget(tx), method, Instant::now()),
+                                PendingRequest::CreateTarget(tx),
+                                method,
+                                None,
+                                Instant::now(),
+                                None,
                             );
                         }
                         Err(err) => {
@@ -397,7 +509,22 @@ impl Handler {
 
     /// Process an incoming event read from the websocket
     fn on_event(&mut self, event: CdpEventMessage) {
+        let _span = tracing::trace_span!(
+            "cdp_event",
+            method = %event.method,
+            session = ?event.session_id,
+        )
+        .entered();
+        if let Some(metrics) = self.config.metrics.as_deref() {
+            metrics.event_received(&event.method);
+        }
         if let Some(ref session_id) = event.session_id {
+            if let CdpEvent::InspectorTargetCrashed(_) = event.params {
+                if let Some(session) = self.sessions.get(session_id.as_str()) {
+                    let target_id = session.target_id().clone();
+                    self.on_target_crashed(target_id);
+                }
+            }
             if let Some(session) = self.sessions.get(session_id.as_str()) {
                 if let Some(target) = self.targets.get_mut(session.target_id()) {
                     return target.on_event(event);
@@ -410,6 +537,7 @@ impl Handler {
             CdpEvent::TargetAttachedToTarget(ev) => self.on_attached_to_target(ev),
             CdpEvent::TargetTargetDestroyed(ev) => self.on_target_destroyed(ev),
             CdpEvent::TargetDetachedFromTarget(ev) => self.on_detached_from_target(ev),
+            CdpEvent::TargetTargetCrashed(ev) => self.on_target_crashed(ev.target_id),
             _ => {}
         }
         chromiumoxide_cdp::consume_event!(match params {
@@ -429,17 +557,23 @@ impl Handler {
             .map(BrowserContext::from)
             .filter(|id| self.browser_contexts.contains(id))
             .unwrap_or_else(|| self.default_browser_context.clone());
-        let target = Target::new(
-            event.target_info,
-            TargetConfig::new(
-                self.config.ignore_https_errors,
-                self.config.request_timeout,
-                self.config.viewport.clone(),
-                self.config.request_intercept,
-                self.config.cache_enabled,
-            ),
-            browser_ctx,
+        let mut config = TargetConfig::new(
+            self.config.ignore_https_errors,
+            self.config.request_timeout,
+            self.config.viewport.clone(),
+            self.config.request_intercept,
+            self.config.cache_enabled,
         );
+        config.extra_headers = self.config.extra_headers.clone();
+        config.credentials = self.config.credentials.clone();
+        if let Some(emulation) = self.network_emulations.get(&browser_ctx) {
+            config.network_emulation = emulation.clone();
+        }
+        if let Some(emulation) = self.context_emulations.get(&browser_ctx) {
+            config.context_emulation = emulation.clone();
+        }
+        config.proxy_credentials = self.proxy_credentials.get(&browser_ctx).cloned();
+        let target = Target::new(event.target_info, config, browser_ctx);
         self.target_ids.push(target.target_id().clone());
         self.targets.insert(target.target_id().clone(), target);
     }
@@ -477,48 +611,215 @@ impl Handler {
 
     /// House keeping of commands
     ///
-    /// Remove all commands where `now` > `timestamp of command starting point +
-    /// request timeout` and notify the senders that their request timed out.
+    /// Remove all commands where `now` > their deadline, which is the
+    /// timestamp of the command starting point + request timeout unless the
+    /// command overrides it, and notify the senders that their request timed
+    /// out.
     fn evict_timed_out_commands(&mut self, now: Instant) {
         let timed_out = self
             .pending_commands
             .iter()
-            .filter(|(_, (_, _, timestamp))| now > (*timestamp + self.config.request_timeout))
+            .filter(|(_, cmd)| now > cmd.deadline)
             .map(|(k, _)| *k)
             .collect::<Vec<_>>();
         for call in timed_out {
-            if let Some((req, _, _)) = self.pending_commands.remove(&call) {
-                match req {
-                    PendingRequest::CreateTarget(tx) => {
-                        let _ = tx.send(Err(CdpError::Timeout));
-                    }
-                    PendingRequest::GetTargets(tx) => {
-                        let _ = tx.send(Err(CdpError::Timeout));
-                    }
-                    PendingRequest::Navigate(nav) => {
-                        if let Some(nav) = self.navigations.remove(&nav) {
-                            match nav {
-                                NavigationRequest::Navigate(nav) => {
-                                    let _ = nav.tx.send(Err(CdpError::Timeout));
-                                }
-                            }
+            if let Some(cmd) = self.pending_commands.remove(&call) {
+                if let Some(metrics) = self.config.metrics.as_deref() {
+                    metrics.command_timed_out(&cmd.method);
+                }
+                self.fail_command(cmd, CdpError::Timeout);
+            }
+        }
+    }
+
+    /// Removes the commands whose initiator is no longer waiting for the
+    /// response, e.g. because its `CommandFuture` was dropped.
+    fn evict_canceled_commands(&mut self) {
+        let canceled = self
+            .pending_commands
+            .iter()
+            .filter_map(|(id, cmd)| match cmd.request {
+                PendingRequest::ExternalCommand(ref tx) => tx.is_canceled().then_some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for call in canceled {
+            if let Some(cmd) = self.pending_commands.remove(&call) {
+                cmd.finish(self.config.metrics.as_deref(), Some("canceled"));
+            }
+        }
+    }
+
+    /// Notifies the sender of the command that it failed with `err`
+    fn fail_command(&mut self, cmd: PendingCommand, err: CdpError) {
+        cmd.finish(self.config.metrics.as_deref(), Some(&err.to_string()));
+        self.fail_request(cmd.request, err);
+    }
+
+    /// Notifies the sender of the request that it failed with `err`
+    fn fail_request(&mut self, req: PendingRequest, err: CdpError) {
+        match req {
+            PendingRequest::CreateTarget(tx) => {
+                let _ = tx.send(Err(err));
+            }
+            PendingRequest::GetTargets(tx) => {
+                let _ = tx.send(Err(err));
+            }
+            PendingRequest::Navigate(nav) => {
+                if let Some(nav) = self.navigations.remove(&nav) {
+                    match nav {
+                        NavigationRequest::Navigate(nav) => {
+                            let _ = nav.tx.send(Err(err));
                         }
                     }
-                    PendingRequest::ExternalCommand(tx) => {
-                        let _ = tx.send(Err(CdpError::Timeout));
-                    }
-                    PendingRequest::InternalCommand(_) => {}
-                    PendingRequest::CloseBrowser(tx) => {
-                        let _ = tx.send(Err(CdpError::Timeout));
-                    }
                 }
             }
+            PendingRequest::ExternalCommand(tx) => {
+                let _ = tx.send(Err(err));
+            }
+            PendingRequest::InternalCommand(_) => {}
+            PendingRequest::CloseBrowser(tx) => {
+                let _ = tx.send(Err(err));
+            }
         }
     }
 
+    /// The target crashed, fail everything that waits on it
+    fn on_target_crashed(&mut self, target_id: TargetId) {
+        tracing::warn!("Target {:?} crashed", target_id);
+        let failed = self
+            .pending_commands
+            .iter()
+            .filter(|(_, cmd)| match &cmd.request {
+                PendingRequest::InternalCommand(id) => *id == target_id,
+                _ => cmd
+                    .session_id
+                    .as_ref()
+                    .and_then(|session_id| self.sessions.get(session_id))
+                    .map(|session| *session.target_id() == target_id)
+                    .unwrap_or_default(),
+            })
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+        for call in failed {
+            if let Some(cmd) = self.pending_commands.remove(&call) {
+                self.fail_command(cmd, CdpError::TargetCrashed(target_id.clone()));
+            }
+        }
+        if let Some(target) = self.targets.get_mut(&target_id) {
+            target.fail_initiator(CdpError::TargetCrashed(target_id.clone()));
+        }
+        self.supervisor
+            .emit(LifecycleEvent::TargetCrashed(target_id));
+    }
+
+    /// The connection to the browser is lost, fail all pending requests and
+    /// forget about all targets
+    fn on_disconnected(&mut self) {
+        self.disconnected = true;
+        for (_, cmd) in std::mem::take(&mut self.pending_commands) {
+            self.fail_command(cmd, CdpError::BrowserDisconnected);
+        }
+        for (_, nav) in std::mem::take(&mut self.navigations) {
+            match nav {
+                NavigationRequest::Navigate(nav) => {
+                    let _ = nav.tx.send(Err(CdpError::BrowserDisconnected));
+                }
+            }
+        }
+        for (_, mut target) in std::mem::take(&mut self.targets) {
+            target.fail_initiator(CdpError::BrowserDisconnected);
+        }
+        self.target_ids.clear();
+        self.sessions.clear();
+        // contexts created over the lost connection are gone with it
+        self.browser_contexts = self
+            .config
+            .context_ids
+            .iter()
+            .map(|id| BrowserContext::from(id.clone()))
+            .collect();
+        self.network_emulations.clear();
+        self.context_emulations.clear();
+        self.proxy_credentials.clear();
+        self.supervisor.emit(LifecycleEvent::Disconnected);
+    }
+
+    /// A new connection to the browser was established
+    fn on_reconnected(&mut self, mut conn: Connection<CdpEventMessage>) {
+        if let Some(ref recorder) = self.config.recorder {
+            conn.set_recorder(recorder.clone());
+        }
+        discover_targets(&mut conn);
+        self.conn = conn;
+        self.disconnected = false;
+        // the traffic is counted per connection
+        self.reported.sent = 0;
+        self.reported.received = 0;
+    }
+
+    /// Answers messages that need the browser connection with an error while
+    /// disconnected, returns all others.
+    fn fail_if_disconnected(&mut self, msg: HandlerMessage) -> Option<HandlerMessage> {
+        if !self.disconnected {
+            return Some(msg);
+        }
+        match msg {
+            HandlerMessage::Command(cmd) => {
+                let _ = cmd.sender.send(Err(CdpError::BrowserDisconnected));
+            }
+            HandlerMessage::CreatePage(_, tx) => {
+                let _ = tx.send(Err(CdpError::BrowserDisconnected));
+            }
+            HandlerMessage::FetchTargets(tx) => {
+                let _ = tx.send(Err(CdpError::BrowserDisconnected));
+            }
+            HandlerMessage::CloseBrowser(tx) => {
+                let _ = tx.send(Err(CdpError::BrowserDisconnected));
+            }
+            msg => return Some(msg),
+        }
+        None
+    }
+
     pub fn event_listeners_mut(&mut self) -> &mut EventListeners {
         &mut self.event_listeners
     }
+
+    /// Reports the number of pending commands and the traffic since the last
+    /// report to the metrics hook
+    fn report_metrics(&mut self) {
+        let Some(metrics) = self.config.metrics.as_deref() else {
+            return;
+        };
+        let pending = self.pending_commands.len();
+        if pending != self.reported.pending_commands {
+            self.reported.pending_commands = pending;
+            metrics.pending_commands(pending);
+        }
+        if let Some(traffic) = self.conn.traffic() {
+            let (sent, received) = (traffic.sent(), traffic.received());
+            if sent > self.reported.sent {
+                metrics.bytes_sent(sent - self.reported.sent);
+                self.reported.sent = sent;
+            }
+            if received > self.reported.received {
+                metrics.bytes_received(received - self.reported.received);
+                self.reported.received = received;
+            }
+        }
+    }
+
+    /// Flushes the listeners that block the handler when they are full,
+    /// returns `true` if any of them is still full and no new messages should
+    /// be read from the connection.
+    fn listeners_blocked(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut blocked = self.event_listeners.poll_blocked(cx);
+        for target in self.targets.values_mut() {
+            blocked |= target.event_listeners_mut().poll_blocked(cx);
+        }
+        blocked
+    }
 }
 
 impl Stream for Handler {
@@ -533,6 +834,10 @@ impl Stream for Handler {
             // through the already pinned self. with the receivers we can also
             // safely ignore exhaustion as those are fused.
             while let Poll::Ready(Some(msg)) = Pin::new(&mut pin.from_browser).poll_next(cx) {
+                let msg = match pin.fail_if_disconnected(msg) {
+                    Some(msg) => msg,
+                    None => continue,
+                };
                 match msg {
                     HandlerMessage::Command(cmd) => {
                         pin.submit_external_command(cmd, now)?;
@@ -559,7 +864,13 @@ impl Stream for Handler {
                     HandlerMessage::InsertContext(ctx) => {
                         pin.browser_contexts.insert(ctx);
                     }
+                    HandlerMessage::SetProxyCredentials(ctx, credentials) => {
+                        pin.proxy_credentials.insert(ctx, credentials);
+                    }
                     HandlerMessage::DisposeContext(ctx) => {
+                        pin.network_emulations.remove(&ctx);
+                        pin.context_emulations.remove(&ctx);
+                        pin.proxy_credentials.remove(&ctx);
                         pin.browser_contexts.remove(&ctx);
                     }
                     HandlerMessage::GetPage(target_id, tx) => {
@@ -573,6 +884,18 @@ impl Stream for Handler {
                     HandlerMessage::AddEventListener(req) => {
                         pin.event_listeners.add_listener(req);
                     }
+                    HandlerMessage::AddLifecycleListener(tx) => {
+                        pin.supervisor.add_listener(tx);
+                    }
+                }
+            }
+
+            if pin.disconnected {
+                match pin.supervisor.poll_reconnect(cx) {
+                    Poll::Ready(Some(Ok(conn))) => pin.on_reconnected(conn),
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                    Poll::Ready(None) => return Poll::Ready(None),
+                    Poll::Pending => return Poll::Pending,
                 }
             }
 
@@ -597,6 +920,16 @@ impl Stream for Handler {
                             TargetEvent::NavigationResult(res) => {
                                 pin.on_navigation_lifecycle_completed(res)
                             }
+                            TargetEvent::NetworkEmulation(emulation) => {
+                                pin.network_emulations
+                                    .insert(target.browser_context().clone(), emulation);
+                            }
+                            TargetEvent::ContextEmulation(update) => {
+                                pin.context_emulations
+                                    .entry(target.browser_context().clone())
+                                    .or_default()
+                                    .merge(update);
+                            }
                         }
                     }
 
@@ -612,29 +945,51 @@ impl Stream for Handler {
 
             let mut done = true;
 
-            while let Poll::Ready(Some(ev)) = Pin::new(&mut pin.conn).poll_next(cx) {
+            while !pin.listeners_blocked(cx) {
+                let ev = match Pin::new(&mut pin.conn).poll_next(cx) {
+                    Poll::Ready(ev) => ev,
+                    Poll::Pending => break,
+                };
                 match ev {
-                    Ok(Message::Response(resp)) => {
+                    Some(Ok(Message::Response(resp))) => {
                         pin.on_response(resp);
                         if pin.closing {
                             // handler should stop processing
                             return Poll::Ready(None);
                         }
                     }
-                    Ok(Message::Event(ev)) => {
+                    Some(Ok(Message::Event(ev))) => {
                         pin.on_event(ev);
                     }
-                    Err(err) => {
+                    Some(Err(err)) => {
                         tracing::error!("WS Connection error: {:?}", err);
+                        if matches!(err, CdpError::Ws(_) | CdpError::Io(_)) {
+                            pin.on_disconnected();
+                            if pin.supervisor.start() {
+                                done = false;
+                                break;
+                            }
+                        }
                         return Poll::Ready(Some(Err(err)));
                     }
+                    None => {
+                        // the browser closed the connection
+                        pin.on_disconnected();
+                        if pin.supervisor.start() {
+                            done = false;
+                            break;
+                        }
+                        return Poll::Ready(None);
+                    }
                 }
                 done = false;
             }
 
             if pin.evict_command_timeout.poll_ready(cx) {
-                // evict all commands that timed out
+                // evict all commands that timed out or were given up
                 pin.evict_timed_out_commands(now);
+                pin.evict_canceled_commands();
+                pin.report_metrics();
             }
 
             if done {
@@ -660,6 +1015,22 @@ pub struct HandlerConfig {
     pub request_intercept: bool,
     /// Whether to enable cache
     pub cache_enabled: bool,
+    /// Extra http headers every new target sends with its requests
+    pub extra_headers: HashMap<String, String>,
+    /// Credentials every new target answers http authentication challenges
+    /// with
+    pub credentials: Option<Credentials>,
+    /// How to recover from a lost connection to the browser, the `Handler`
+    /// stops if this is `None`
+    pub reconnect: Option<ReconnectPolicy>,
+    /// Receives measurements of the handler
+    pub metrics: Option<Arc<dyn Metrics>>,
+    /// Records all messages exchanged with the browser, also across
+    /// reconnects
+    pub recorder: Option<Recorder>,
+    /// Whether to compare the protocol of the browser with the generated
+    /// types when connecting
+    pub protocol_check: ProtocolCheck,
 }
 
 impl Default for HandlerConfig {
@@ -671,10 +1042,26 @@ impl Default for HandlerConfig {
             request_timeout: Duration::from_millis(REQUEST_TIMEOUT),
             request_intercept: false,
             cache_enabled: true,
+            extra_headers: Default::default(),
+            credentials: None,
+            reconnect: None,
+            metrics: None,
+            recorder: None,
+            protocol_check: ProtocolCheck::Ignore,
         }
     }
 }
 
+/// Enables the discovery of targets on a new connection
+fn discover_targets(conn: &mut Connection<CdpEventMessage>) {
+    let discover = SetDiscoverTargetsParams::new(true);
+    let _ = conn.submit_command(
+        discover.identifier(),
+        None,
+        serde_json::to_value(discover).unwrap(),
+    );
+}
+
 /// Wraps the sender half of the channel who requested a navigation
 #[derive(Debug)]
 pub struct NavigationInProgress<T> {
@@ -706,6 +1093,49 @@ impl<T> NavigationInProgress<T> {
     }
 }
 
+/// A command waiting for its response
+#[derive(Debug)]
+struct PendingCommand {
+    request: PendingRequest,
+    method: MethodId,
+    /// The session the command was sent to
+    session_id: Option<SessionId>,
+    /// When the command was sent
+    timestamp: Instant,
+    /// When the command times out
+    deadline: Instant,
+    /// Traces the command until it completes
+    span: tracing::Span,
+}
+
+impl PendingCommand {
+    /// Records the latency and the error, if any, of the completed command
+    fn finish(&self, metrics: Option<&dyn Metrics>, error: Option<&str>) {
+        let latency = self.timestamp.elapsed();
+        self.span.record("latency_ms", latency.as_millis() as u64);
+        match error {
+            Some(error) => {
+                self.span.record("error", error);
+                tracing::debug!(parent: &self.span, "Command failed");
+            }
+            None => tracing::trace!(parent: &self.span, "Command completed"),
+        }
+        if let Some(metrics) = metrics {
+            metrics.command_completed(&self.method, latency, error.is_none());
+        }
+    }
+}
+
+/// The values last reported to the `Metrics` hook
+#[derive(Debug, Default)]
+struct ReportedMetrics {
+    pending_commands: usize,
+    /// Bytes sent over the current connection
+    sent: u64,
+    /// Bytes received over the current connection
+    received: u64,
+}
+
 /// Request type for navigation
 #[derive(Debug)]
 enum NavigationRequest {
@@ -748,9 +1178,117 @@ pub(crate) enum HandlerMessage {
     FetchTargets(OneshotSender<Result<Vec<TargetInfo>>>),
     InsertContext(BrowserContext),
     DisposeContext(BrowserContext),
+    SetProxyCredentials(BrowserContext, Credentials),
     GetPages(OneshotSender<Vec<Page>>),
     Command(CommandMessage),
     GetPage(TargetId, OneshotSender<Option<Page>>),
     AddEventListener(EventListenerRequest),
+    AddLifecycleListener(UnboundedSender<LifecycleEvent>),
     CloseBrowser(OneshotSender<Result<CloseReturns>>),
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::mpsc::channel;
+
+    use super::*;
+    use crate::transport::memory_pair;
+
+    #[async_std::test]
+    async fn disconnect_forgets_browser_contexts() {
+        let (transport, _peer) = memory_pair::<CdpEventMessage>();
+        let (_tx, rx) = channel(1);
+        let configured = BrowserContext::from(BrowserContextId::new("configured"));
+        let config = HandlerConfig {
+            context_ids: vec![BrowserContextId::new("configured")],
+            ..Default::default()
+        };
+        let mut handler = Handler::new(Connection::new(transport), rx, config);
+
+        let created = BrowserContext::from(BrowserContextId::new("created"));
+        handler.browser_contexts.insert(created.clone());
+        handler
+            .network_emulations
+            .insert(created.clone(), Default::default());
+        handler
+            .context_emulations
+            .insert(created.clone(), Default::default());
+        handler.proxy_credentials.insert(
+            created,
+            Credentials {
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            },
+        );
+
+        handler.on_disconnected();
+        assert_eq!(
+            handler.browser_contexts().collect::<Vec<_>>(),
+            [&configured]
+        );
+        assert!(handler.network_emulations.is_empty());
+        assert!(handler.context_emulations.is_empty());
+        assert!(handler.proxy_credentials.is_empty());
+    }
+
+    #[async_std::test]
+    async fn new_pages_inherit_the_context_emulation() {
+        use crate::testing::MockBrowser;
+        use crate::Browser;
+
+        let mock = MockBrowser::start().await.unwrap();
+        let (browser, mut handler) =
+            Browser::connect_with_config(mock.ws_url(), HandlerConfig::default())
+                .await
+                .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+
+        let page = browser.new_page("about:blank").await.unwrap();
+        page.set_locale("de_DE").await.unwrap();
+        page.set_cpu_throttling_rate(4.).await.unwrap();
+        let other = browser.new_page("about:blank").await.unwrap();
+
+        let session_id = Some(other.session_id().as_ref().to_string());
+        let calls: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.session_id == session_id)
+            .collect();
+        assert!(calls
+            .iter()
+            .any(|call| call.method == "Emulation.setLocaleOverride"
+                && call.params["locale"] == "de_DE"));
+        assert!(calls.iter().any(
+            |call| call.method == "Emulation.setCPUThrottlingRate" && call.params["rate"] == 4.
+        ));
+    }
+
+    #[async_std::test]
+    async fn rejected_network_emulation_is_not_inherited() {
+        use crate::testing::{MockBrowser, MockReply};
+        use crate::Browser;
+
+        let mock = MockBrowser::builder()
+            .respond_with("Network.emulateNetworkConditions", |_| {
+                MockReply::error(-32000, "not supported")
+            })
+            .start()
+            .await
+            .unwrap();
+        let (browser, mut handler) =
+            Browser::connect_with_config(mock.ws_url(), HandlerConfig::default())
+                .await
+                .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+
+        let page = browser.new_page("about:blank").await.unwrap();
+        assert!(page.set_offline(true).await.is_err());
+        let other = browser.new_page("about:blank").await.unwrap();
+
+        let session_id = Some(other.session_id().as_ref().to_string());
+        assert!(
+            !mock.calls().iter().any(|call| call.session_id == session_id
+                && call.method == "Network.emulateNetworkConditions")
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use chromiumoxide_cdp::cdp::browser_protocol::fetch::{
-    self, AuthChallengeResponse, AuthChallengeResponseResponse, ContinueRequestParams,
-    ContinueWithAuthParams, DisableParams, EventAuthRequired, EventRequestPaused, RequestPattern,
+    self, AuthChallengeResponse, AuthChallengeResponseResponse, AuthChallengeSource,
+    ContinueRequestParams, ContinueWithAuthParams, DisableParams, EventAuthRequired,
+    EventRequestPaused, RequestPattern,
 };
 use chromiumoxide_cdp::cdp::browser_protocol::network::{
-    EmulateNetworkConditionsParams, EventLoadingFailed, EventLoadingFinished,
+    ConnectionType, EmulateNetworkConditionsParams, EventLoadingFailed, EventLoadingFinished,
     EventRequestServedFromCache, EventRequestWillBeSent, EventResponseReceived, Headers,
     InterceptionId, RequestId, Response, SetCacheDisabledParams, SetExtraHttpHeadersParams,
 };
@@ -29,10 +30,12 @@ pub struct NetworkManager {
     request_id_to_interception_id: HashMap<RequestId, InterceptionId>,
     user_cache_disabled: bool,
     attempted_authentications: HashSet<RequestId>,
+    attempted_proxy_authentications: HashSet<RequestId>,
     credentials: Option<Credentials>,
+    proxy_credentials: Option<Credentials>,
     user_request_interception_enabled: bool,
     protocol_request_interception_enabled: bool,
-    offline: bool,
+    emulation: NetworkEmulation,
     request_timeout: Duration,
 }
 
@@ -47,25 +50,27 @@ impl NetworkManager {
             request_id_to_interception_id: Default::default(),
             user_cache_disabled: false,
             attempted_authentications: Default::default(),
+            attempted_proxy_authentications: Default::default(),
             credentials: None,
+            proxy_credentials: None,
             user_request_interception_enabled: false,
             protocol_request_interception_enabled: false,
-            offline: false,
+            emulation: Default::default(),
             request_timeout,
         }
     }
 
     pub fn init_commands(&self) -> CommandChain {
         let enable = EnableParams::default();
-        let cmds = if self.ignore_httpserrors {
+        let mut cmds = vec![(enable.identifier(), serde_json::to_value(enable).unwrap())];
+        if self.ignore_httpserrors {
             let ignore = SetIgnoreCertificateErrorsParams::new(true);
-            vec![
-                (enable.identifier(), serde_json::to_value(enable).unwrap()),
-                (ignore.identifier(), serde_json::to_value(ignore).unwrap()),
-            ]
-        } else {
-            vec![(enable.identifier(), serde_json::to_value(enable).unwrap())]
-        };
+            cmds.push((ignore.identifier(), serde_json::to_value(ignore).unwrap()));
+        }
+        if self.emulation.is_enabled() {
+            let emulate = self.emulation.to_params();
+            cmds.push((emulate.identifier(), serde_json::to_value(emulate).unwrap()));
+        }
         CommandChain::new(cmds, self.request_timeout)
     }
 
@@ -112,11 +117,24 @@ impl NetworkManager {
         self.update_protocol_request_interception()
     }
 
+    /// Sets the credentials used to answer authentication challenges of a
+    /// proxy server, as opposed to challenges of the requested site.
+    ///
+    /// Without them, proxy challenges are answered with the credentials of
+    /// [`NetworkManager::authenticate`].
+    pub fn authenticate_proxy(&mut self, credentials: Credentials) {
+        self.proxy_credentials = Some(credentials);
+        self.update_protocol_request_interception()
+    }
+
     fn update_protocol_request_interception(&mut self) {
-        let enabled = self.user_request_interception_enabled || self.credentials.is_some();
+        let enabled = self.user_request_interception_enabled
+            || self.credentials.is_some()
+            || self.proxy_credentials.is_some();
         if enabled == self.protocol_request_interception_enabled {
             return;
         }
+        self.protocol_request_interception_enabled = enabled;
         self.update_protocol_cache_disabled();
         if enabled {
             self.push_cdp_request(
@@ -147,21 +165,33 @@ impl NetworkManager {
     }
 
     pub fn on_fetch_auth_required(&mut self, event: &EventAuthRequired) {
-        let response = if self
-            .attempted_authentications
-            .contains(event.request_id.as_ref())
-        {
+        let (attempted, credentials) =
+            if event.auth_challenge.source == Some(AuthChallengeSource::Proxy) {
+                (
+                    &mut self.attempted_proxy_authentications,
+                    // a proxy of `--proxy-server` is answered like a server
+                    self.proxy_credentials
+                        .clone()
+                        .or_else(|| self.credentials.clone()),
+                )
+            } else {
+                (
+                    &mut self.attempted_authentications,
+                    self.credentials.clone(),
+                )
+            };
+
+        let response = if attempted.contains(event.request_id.as_ref()) {
             AuthChallengeResponseResponse::CancelAuth
-        } else if self.credentials.is_some() {
-            self.attempted_authentications
-                .insert(event.request_id.clone().into());
+        } else if credentials.is_some() {
+            attempted.insert(event.request_id.clone().into());
             AuthChallengeResponseResponse::ProvideCredentials
         } else {
             AuthChallengeResponseResponse::Default
         };
 
         let mut auth = AuthChallengeResponse::new(response);
-        if let Some(creds) = self.credentials.clone() {
+        if let Some(creds) = credentials {
             auth.username = Some(creds.username);
             auth.password = Some(creds.password);
         }
@@ -169,19 +199,24 @@ impl NetworkManager {
     }
 
     pub fn set_offline_mode(&mut self, value: bool) {
-        if self.offline == value {
+        if self.emulation.offline == value {
             return;
         }
-        self.offline = value;
-        self.push_cdp_request(
-            EmulateNetworkConditionsParams::builder()
-                .offline(self.offline)
-                .latency(0)
-                .download_throughput(-1.)
-                .upload_throughput(-1.)
-                .build()
-                .unwrap(),
-        );
+        self.emulation.offline = value;
+        self.push_cdp_request(self.emulation.to_params());
+    }
+
+    /// The currently emulated network state
+    pub fn network_emulation(&self) -> &NetworkEmulation {
+        &self.emulation
+    }
+
+    /// Replaces the emulated network state without issuing a request.
+    ///
+    /// The new state is applied by `NetworkManager::init_commands` or by
+    /// sending the params of `NetworkEmulation::to_params`.
+    pub fn set_network_emulation(&mut self, emulation: NetworkEmulation) {
+        self.emulation = emulation;
     }
 
     /// Request interception doesn't happen for data URLs with Network Service.
@@ -219,8 +254,7 @@ impl NetworkManager {
     pub fn on_network_loading_finished(&mut self, event: &EventLoadingFinished) {
         if let Some(request) = self.requests.remove(event.request_id.as_ref()) {
             if let Some(interception_id) = request.interception_id.as_ref() {
-                self.attempted_authentications
-                    .remove(interception_id.as_ref());
+                self.forget_authentications(interception_id);
             }
             self.queued_events
                 .push_back(NetworkEvent::RequestFinished(request));
@@ -231,8 +265,7 @@ impl NetworkManager {
         if let Some(mut request) = self.requests.remove(event.request_id.as_ref()) {
             request.failure_text = Some(event.error_text.clone());
             if let Some(interception_id) = request.interception_id.as_ref() {
-                self.attempted_authentications
-                    .remove(interception_id.as_ref());
+                self.forget_authentications(interception_id);
             }
             self.queued_events
                 .push_back(NetworkEvent::RequestFailed(request));
@@ -268,10 +301,16 @@ impl NetworkManager {
     fn handle_request_redirect(&mut self, request: &mut HttpRequest, response: Response) {
         request.set_response(response);
         if let Some(interception_id) = request.interception_id.as_ref() {
-            self.attempted_authentications
-                .remove(interception_id.as_ref());
+            self.forget_authentications(interception_id);
         }
     }
+
+    fn forget_authentications(&mut self, interception_id: &InterceptionId) {
+        self.attempted_authentications
+            .remove(interception_id.as_ref());
+        self.attempted_proxy_authentications
+            .remove(interception_id.as_ref());
+    }
 }
 
 #[derive(Debug)]
@@ -282,3 +321,139 @@ pub enum NetworkEvent {
     RequestFailed(HttpRequest),
     RequestFinished(HttpRequest),
 }
+
+/// Throttling settings to emulate a specific network connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConditions {
+    /// Minimum latency from request sent to response headers received (ms).
+    pub latency: f64,
+    /// Maximal aggregated download throughput (bytes/sec). -1 disables
+    /// download throttling.
+    pub download: f64,
+    /// Maximal aggregated upload throughput (bytes/sec). -1 disables upload
+    /// throttling.
+    pub upload: f64,
+    /// Connection type if known.
+    pub connection_type: Option<ConnectionType>,
+}
+
+impl NetworkConditions {
+    /// Disables all throttling.
+    pub const NO_THROTTLING: NetworkConditions = NetworkConditions {
+        latency: 0.,
+        download: -1.,
+        upload: -1.,
+        connection_type: None,
+    };
+
+    /// Same as the "Slow 3G" preset of the chrome devtools.
+    pub const SLOW_3G: NetworkConditions = NetworkConditions {
+        latency: 400. * 5.,
+        download: 500. * 1000. / 8. * 0.8,
+        upload: 500. * 1000. / 8. * 0.8,
+        connection_type: Some(ConnectionType::Cellular3g),
+    };
+
+    /// Same as the "Fast 3G" preset of the chrome devtools.
+    pub const FAST_3G: NetworkConditions = NetworkConditions {
+        latency: 150. * 3.75,
+        download: 1.6 * 1000. * 1000. / 8. * 0.9,
+        upload: 750. * 1000. / 8. * 0.9,
+        connection_type: Some(ConnectionType::Cellular3g),
+    };
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        Self::NO_THROTTLING
+    }
+}
+
+/// The emulated network state of a target.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkEmulation {
+    /// Whether to emulate internet disconnection
+    pub offline: bool,
+    /// The throttling to apply, if any
+    pub conditions: Option<NetworkConditions>,
+}
+
+impl NetworkEmulation {
+    /// Whether this differs from the browser's default network state
+    pub fn is_enabled(&self) -> bool {
+        self.offline || self.conditions.is_some()
+    }
+
+    /// The request that applies this state
+    pub fn to_params(&self) -> EmulateNetworkConditionsParams {
+        let conditions = self.conditions.clone().unwrap_or_default();
+        EmulateNetworkConditionsParams {
+            offline: self.offline,
+            latency: conditions.latency,
+            download_throughput: conditions.download,
+            upload_throughput: conditions.upload,
+            connection_type: conditions.connection_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_required(source: &str) -> EventAuthRequired {
+        serde_json::from_value(serde_json::json!({
+            "requestId": "1",
+            "request": {
+                "url": "http://example.com/",
+                "method": "GET",
+                "headers": {},
+                "initialPriority": "High",
+                "referrerPolicy": "no-referrer"
+            },
+            "frameId": "frame",
+            "resourceType": "Document",
+            "authChallenge": {
+                "source": source,
+                "origin": "http://proxy:8080",
+                "scheme": "basic",
+                "realm": ""
+            }
+        }))
+        .unwrap()
+    }
+
+    fn username(manager: &mut NetworkManager) -> Option<serde_json::Value> {
+        while let Some(event) = manager.poll() {
+            if let NetworkEvent::SendCdpRequest((method, params)) = event {
+                if method == ContinueWithAuthParams::IDENTIFIER {
+                    return Some(params["authChallengeResponse"]["username"].clone());
+                }
+            }
+        }
+        None
+    }
+
+    fn credentials(username: &str) -> Credentials {
+        Credentials {
+            username: username.to_string(),
+            password: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn proxy_challenge_falls_back_to_credentials() {
+        let mut manager = NetworkManager::new(false, Duration::from_secs(1));
+        manager.authenticate(credentials("user"));
+        manager.on_fetch_auth_required(&auth_required("Proxy"));
+        assert_eq!(username(&mut manager), Some("user".into()));
+
+        let mut manager = NetworkManager::new(false, Duration::from_secs(1));
+        manager.authenticate(credentials("user"));
+        manager.authenticate_proxy(credentials("proxy"));
+        manager.on_fetch_auth_required(&auth_required("Proxy"));
+        assert_eq!(username(&mut manager), Some("proxy".into()));
+        manager.on_fetch_auth_required(&auth_required("Server"));
+        assert_eq!(username(&mut manager), Some("user".into()));
+    }
+}
@@ -5,14 +5,16 @@ use futures::channel::oneshot::channel as oneshot_channel;
 use futures::stream::Fuse;
 use futures::{SinkExt, StreamExt};
 
-use chromiumoxide_cdp::cdp::browser_protocol::browser::{GetVersionParams, GetVersionReturns};
+use chromiumoxide_cdp::cdp::browser_protocol::browser::{
+    BrowserContextId, GetVersionParams, GetVersionReturns,
+};
 use chromiumoxide_cdp::cdp::browser_protocol::dom::{
     DiscardSearchResultsParams, GetSearchResultsParams, NodeId, PerformSearchParams,
     QuerySelectorAllParams, QuerySelectorParams, Rgba,
 };
 use chromiumoxide_cdp::cdp::browser_protocol::emulation::{
-    ClearDeviceMetricsOverrideParams, SetDefaultBackgroundColorOverrideParams,
-    SetDeviceMetricsOverrideParams,
+    ClearDeviceMetricsOverrideParams, MediaFeature, SetDefaultBackgroundColorOverrideParams,
+    SetDeviceMetricsOverrideParams, SetEmulatedMediaParams,
 };
 use chromiumoxide_cdp::cdp::browser_protocol::input::{
     DispatchKeyEventParams, DispatchKeyEventType, DispatchMouseEventParams, DispatchMouseEventType,
@@ -27,12 +29,14 @@ use chromiumoxide_cdp::cdp::js_protocol::runtime::{
 };
 use chromiumoxide_types::{Command, CommandResponse};
 
-use crate::cmd::{to_command_response, CommandMessage};
+use crate::cmd::{to_command_response, CommandMessage, CommandOptions};
 use crate::error::{CdpError, Result};
 use crate::handler::commandfuture::CommandFuture;
 use crate::handler::domworld::DOMWorldKind;
+use crate::handler::emulation::ContextEmulation;
 use crate::handler::httpfuture::HttpFuture;
-use crate::handler::target::{GetExecutionContext, TargetMessage};
+use crate::handler::network::{NetworkConditions, NetworkEmulation};
+use crate::handler::target::{GetExecutionContext, SetMediaFeatures, TargetMessage};
 use crate::handler::target_message_future::TargetMessageFuture;
 use crate::js::EvaluationResult;
 use crate::layout::Point;
@@ -46,11 +50,16 @@ pub struct PageHandle {
 }
 
 impl PageHandle {
-    pub fn new(target_id: TargetId, session_id: SessionId) -> Self {
+    pub fn new(
+        target_id: TargetId,
+        session_id: SessionId,
+        browser_context_id: Option<BrowserContextId>,
+    ) -> Self {
         let (commands, rx) = channel(1);
         let page = PageInner {
             target_id,
             session_id,
+            browser_context_id,
             sender: commands,
         };
         Self {
@@ -68,6 +77,8 @@ impl PageHandle {
 pub(crate) struct PageInner {
     target_id: TargetId,
     session_id: SessionId,
+    /// The browser context this page runs in, `None` for the default context
+    browser_context_id: Option<BrowserContextId>,
     sender: Sender<TargetMessage>,
 }
 
@@ -77,11 +88,34 @@ impl PageInner {
         execute(cmd, self.sender.clone(), Some(self.session_id.clone())).await
     }
 
+    /// Execute a PDL command outside of this page's session, i.e. on the
+    /// browser target
+    pub(crate) async fn execute_browser<T: Command>(
+        &self,
+        cmd: T,
+    ) -> Result<CommandResponse<T::Response>> {
+        execute(cmd, self.sender.clone(), None).await
+    }
+
     /// Create a PDL command future
     pub(crate) fn command_future<T: Command>(&self, cmd: T) -> Result<CommandFuture<T>> {
         CommandFuture::new(cmd, self.sender.clone(), Some(self.session_id.clone()))
     }
 
+    /// Execute a command with overridden `options`
+    pub(crate) fn command_future_with<T: Command>(
+        &self,
+        cmd: T,
+        options: CommandOptions,
+    ) -> Result<CommandFuture<T>> {
+        CommandFuture::with_options(
+            cmd,
+            self.sender.clone(),
+            Some(self.session_id.clone()),
+            options,
+        )
+    }
+
     /// This creates navigation future with the final http response when the page is loaded
     pub(crate) fn wait_for_navigation(&self) -> TargetMessageFuture<ArcHttpRequest> {
         TargetMessageFuture::<ArcHttpRequest>::wait_for_navigation(self.sender.clone())
@@ -106,6 +140,12 @@ impl PageInner {
         &self.session_id
     }
 
+    /// The identifier of the browser context this page runs in, if it is not
+    /// the default context
+    pub fn browser_context_id(&self) -> Option<&BrowserContextId> {
+        self.browser_context_id.as_ref()
+    }
+
     pub(crate) fn sender(&self) -> &Sender<TargetMessage> {
         &self.sender
     }
@@ -368,6 +408,80 @@ impl PageInner {
         Ok(rx.await?)
     }
 
+    /// Emulates the given media features.
+    ///
+    /// If `merge` is set, the features are combined with the features that
+    /// are currently emulated for this page, otherwise they replace them.
+    pub async fn emulate_media_features(
+        &self,
+        features: Vec<MediaFeature>,
+        merge: bool,
+    ) -> Result<&Self> {
+        let (tx, rx) = oneshot_channel();
+        self.sender
+            .clone()
+            .send(TargetMessage::MediaFeatures(SetMediaFeatures {
+                features,
+                merge,
+                tx,
+            }))
+            .await?;
+        let features = rx.await?;
+        self.execute(
+            SetEmulatedMediaParams::builder()
+                .features(features.clone())
+                .build(),
+        )
+        .await?;
+        self.sender
+            .clone()
+            .send(TargetMessage::MediaFeaturesEmulated(features))
+            .await?;
+        Ok(self)
+    }
+
+    /// Records emulation settings after the browser applied them to this page,
+    /// so that pages opened later in the same browser context get them too
+    pub async fn record_context_emulation(&self, update: ContextEmulation) -> Result<&Self> {
+        self.sender
+            .clone()
+            .send(TargetMessage::ContextEmulation(update))
+            .await?;
+        Ok(self)
+    }
+
+    /// Enables or disables the offline mode of the network emulation
+    pub async fn set_offline(&self, offline: bool) -> Result<&Self> {
+        let (tx, rx) = oneshot_channel();
+        self.sender
+            .clone()
+            .send(TargetMessage::SetOffline(offline, tx))
+            .await?;
+        self.apply_network_emulation(rx.await?).await
+    }
+
+    /// Replaces the emulated network conditions, `None` disables throttling
+    pub async fn emulate_network_conditions(
+        &self,
+        conditions: Option<NetworkConditions>,
+    ) -> Result<&Self> {
+        let (tx, rx) = oneshot_channel();
+        self.sender
+            .clone()
+            .send(TargetMessage::EmulateNetworkConditions(conditions, tx))
+            .await?;
+        self.apply_network_emulation(rx.await?).await
+    }
+
+    async fn apply_network_emulation(&self, emulation: NetworkEmulation) -> Result<&Self> {
+        self.execute(emulation.to_params()).await?;
+        self.sender
+            .clone()
+            .send(TargetMessage::NetworkEmulated(emulation))
+            .await?;
+        Ok(self)
+    }
+
     /// Returns metrics relating to the layout of the page
     pub async fn layout_metrics(&self) -> Result<GetLayoutMetricsReturns> {
         Ok(self
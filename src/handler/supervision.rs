@@ -0,0 +1,227 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use async_tungstenite::tungstenite::http::HeaderMap;
+use futures::channel::mpsc::UnboundedSender;
+use futures::future::BoxFuture;
+use futures_timer::Delay;
+
+use chromiumoxide_cdp::cdp::browser_protocol::target::TargetId;
+use chromiumoxide_cdp::cdp::CdpEventMessage;
+
+use crate::async_process::Child;
+use crate::browser::BrowserConfig;
+use crate::conn::Connection;
+use crate::error::Result;
+use crate::profile::TempUserDataDir;
+
+/// How the `Handler` recovers from a lost connection to the browser.
+///
+/// Without a policy the `Handler` stream ends once the connection is lost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// How often to try to reconnect before giving up
+    pub max_attempts: u32,
+    /// How long to wait before each attempt
+    pub delay: Duration,
+    /// Whether to launch a new browser process with the same `BrowserConfig`
+    /// instead of reconnecting to the websocket of the old one.
+    ///
+    /// Only applies to browsers started with `Browser::launch`. The new
+    /// process replaces the one of the `Browser`, which controls it with
+    /// `Browser::kill`, `Browser::wait` and the like.
+    pub relaunch: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            delay: Duration::from_secs(1),
+            relaunch: false,
+        }
+    }
+}
+
+/// Changes of the state of the browser connection, see
+/// [`Browser::lifecycle_events`](crate::Browser::lifecycle_events).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// The connection to the browser was lost, all pending commands failed
+    /// with [`CdpError::BrowserDisconnected`](crate::error::CdpError::BrowserDisconnected)
+    /// and all pages are gone.
+    Disconnected,
+    /// Trying to reconnect for the `attempt`th time
+    Reconnecting { attempt: u32 },
+    /// The connection was re-established, new pages can be created.
+    Reconnected,
+    /// All reconnection attempts failed, the `Handler` stops.
+    ReconnectFailed,
+    /// The target crashed, its pending commands failed with
+    /// [`CdpError::TargetCrashed`](crate::error::CdpError::TargetCrashed)
+    TargetCrashed(TargetId),
+}
+
+/// Where to reconnect to once the connection is lost
+#[derive(Debug, Clone)]
+pub(crate) enum ReconnectSource {
+    /// Connect to the same websocket again, with the headers of the first
+    /// handshake
+    WebSocket(String, HeaderMap),
+    /// Launch a new browser process
+    Launch(Box<BrowserConfig>),
+}
+
+/// A browser process launched by the `Supervisor`
+#[derive(Debug)]
+pub(crate) struct Relaunched {
+    pub child: Child,
+    pub debug_ws_url: String,
+    pub temp_user_data_dir: Option<TempUserDataDir>,
+}
+
+/// Hands the process of the last relaunch over to the `Browser`
+pub(crate) type RelaunchedProcess = Arc<Mutex<Option<Relaunched>>>;
+
+type Reconnecting = BoxFuture<'static, Result<(Connection<CdpEventMessage>, Option<Relaunched>)>>;
+
+impl ReconnectSource {
+    fn connect(&self, delay: Duration) -> Reconnecting {
+        let source = self.clone();
+        Box::pin(async move {
+            Delay::new(delay).await;
+            match source {
+                ReconnectSource::WebSocket(url, headers) => {
+                    let conn =
+                        Connection::<CdpEventMessage>::connect_with_headers(&url, headers).await?;
+                    Ok((conn, None))
+                }
+                ReconnectSource::Launch(config) => {
+                    let (child, debug_ws_url, conn, temp_user_data_dir) = config.spawn().await?;
+                    let relaunched = Relaunched {
+                        child,
+                        debug_ws_url,
+                        temp_user_data_dir,
+                    };
+                    Ok((conn, Some(relaunched)))
+                }
+            }
+        })
+    }
+}
+
+/// Keeps track of the reconnection attempts and notifies the lifecycle
+/// listeners.
+#[derive(Default)]
+pub(crate) struct Supervisor {
+    policy: Option<ReconnectPolicy>,
+    source: Option<ReconnectSource>,
+    /// The number of the current attempt
+    attempt: u32,
+    /// The attempt in progress
+    reconnecting: Option<Reconnecting>,
+    /// The process launched by the last successful relaunch, until the
+    /// `Browser` takes it over
+    relaunched: RelaunchedProcess,
+    listeners: Vec<UnboundedSender<LifecycleEvent>>,
+}
+
+impl Supervisor {
+    pub fn new(policy: Option<ReconnectPolicy>) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn relaunched(&self) -> RelaunchedProcess {
+        self.relaunched.clone()
+    }
+
+    pub fn set_source(&mut self, source: ReconnectSource) {
+        self.source = Some(source);
+    }
+
+    pub fn add_listener(&mut self, listener: UnboundedSender<LifecycleEvent>) {
+        self.listeners.push(listener);
+    }
+
+    /// Sends the event to all listeners and drops the closed ones
+    pub fn emit(&mut self, event: LifecycleEvent) {
+        self.listeners
+            .retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+    }
+
+    /// Starts reconnecting, returns `false` if reconnecting is not configured
+    pub fn start(&mut self) -> bool {
+        self.attempt = 0;
+        self.next_attempt()
+    }
+
+    fn next_attempt(&mut self) -> bool {
+        let (policy, source) = match (&self.policy, &self.source) {
+            (Some(policy), Some(source)) => (policy, source),
+            _ => return false,
+        };
+        if self.attempt >= policy.max_attempts {
+            return false;
+        }
+        self.reconnecting = Some(source.connect(policy.delay));
+        self.attempt += 1;
+        self.emit(LifecycleEvent::Reconnecting {
+            attempt: self.attempt,
+        });
+        true
+    }
+
+    /// Drives the current reconnection attempt.
+    ///
+    /// Resolves to `None` if no attempt is in progress and to the error of the
+    /// last attempt once all attempts failed.
+    pub fn poll_reconnect(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Connection<CdpEventMessage>>>> {
+        loop {
+            let fut = match self.reconnecting.as_mut() {
+                Some(fut) => fut,
+                None => return Poll::Ready(None),
+            };
+            let res = ready!(fut.as_mut().poll(cx));
+            self.reconnecting = None;
+            match res {
+                Ok((conn, relaunched)) => {
+                    if relaunched.is_some() {
+                        // a process the `Browser` did not take over yet is
+                        // killed when dropped
+                        *self.relaunched.lock().unwrap() = relaunched;
+                    }
+                    self.emit(LifecycleEvent::Reconnected);
+                    return Poll::Ready(Some(Ok(conn)));
+                }
+                Err(err) => {
+                    tracing::warn!("Reconnect attempt {} failed: {:?}", self.attempt, err);
+                    if !self.next_attempt() {
+                        self.emit(LifecycleEvent::ReconnectFailed);
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("policy", &self.policy)
+            .field("source", &self.source)
+            .field("attempt", &self.attempt)
+            .field("reconnecting", &self.reconnecting.is_some())
+            .field("relaunched", &self.relaunched)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
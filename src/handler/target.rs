@@ -11,6 +11,7 @@ use futures::task::{Context, Poll};
 use chromiumoxide_cdp::cdp::browser_protocol::page::{FrameId, GetFrameTreeParams};
 use chromiumoxide_cdp::cdp::browser_protocol::{
     browser::BrowserContextId,
+    emulation::MediaFeature,
     log as cdplog, performance,
     target::{AttachToTargetParams, SessionId, SetAutoAttachParams, TargetId, TargetInfo},
 };
@@ -18,18 +19,19 @@ use chromiumoxide_cdp::cdp::events::CdpEvent;
 use chromiumoxide_cdp::cdp::CdpEventMessage;
 use chromiumoxide_types::{Command, Method, Request, Response};
 
+use crate::auth::Credentials;
 use crate::cdp::browser_protocol::target::CloseTargetParams;
 use crate::cmd::CommandChain;
 use crate::cmd::CommandMessage;
 use crate::error::{CdpError, Result};
 use crate::handler::browser::BrowserContext;
 use crate::handler::domworld::DOMWorldKind;
-use crate::handler::emulation::EmulationManager;
+use crate::handler::emulation::{ContextEmulation, EmulationManager};
 use crate::handler::frame::{
     FrameEvent, FrameManager, NavigationError, NavigationId, NavigationOk,
 };
 use crate::handler::frame::{FrameNavigationRequest, UTILITY_WORLD_NAME};
-use crate::handler::network::{NetworkEvent, NetworkManager};
+use crate::handler::network::{NetworkConditions, NetworkEmulation, NetworkEvent, NetworkManager};
 use crate::handler::page::PageHandle;
 use crate::handler::viewport::Viewport;
 use crate::handler::{PageInner, REQUEST_TIMEOUT};
@@ -38,6 +40,7 @@ use crate::{page::Page, ArcHttpRequest};
 use chromiumoxide_cdp::cdp::js_protocol::runtime::{
     ExecutionContextId, RunIfWaitingForDebuggerParams,
 };
+use std::collections::HashMap;
 use std::time::Duration;
 
 macro_rules! advance_state {
@@ -103,6 +106,16 @@ impl Target {
 
         network_manager.set_cache_enabled(config.cache_enabled);
         network_manager.set_request_interception(config.request_intercept);
+        network_manager.set_network_emulation(config.network_emulation.clone());
+        if !config.extra_headers.is_empty() {
+            network_manager.set_extra_headers(config.extra_headers.clone());
+        }
+        if let Some(credentials) = config.credentials.clone() {
+            network_manager.authenticate(credentials);
+        }
+        if let Some(credentials) = config.proxy_credentials.clone() {
+            network_manager.authenticate_proxy(credentials);
+        }
 
         Self {
             info,
@@ -161,7 +174,11 @@ impl Target {
     fn create_page(&mut self) {
         if self.page.is_none() {
             if let Some(session) = self.session_id.clone() {
-                let handle = PageHandle::new(self.target_id().clone(), session);
+                let handle = PageHandle::new(
+                    self.target_id().clone(),
+                    session,
+                    self.browser_context.id().cloned(),
+                );
                 self.page = Some(handle);
             }
         }
@@ -382,12 +399,7 @@ impl Target {
                     cx,
                     now,
                     cmds,
-                    match self.config.viewport.as_ref() {
-                        Some(viewport) => TargetInit::InitializingEmulation(
-                            self.emulation_manager.init_commands(viewport)
-                        ),
-                        None => TargetInit::Initialized,
-                    }
+                    TargetInit::InitializingEmulation(self.emulation_init_commands())
                 );
             }
             TargetInit::InitializingEmulation(cmds) => {
@@ -428,6 +440,7 @@ impl Target {
                 return Some(ev);
             }
 
+            let mut network_acks = Vec::new();
             if let Some(handle) = self.page.as_mut() {
                 while let Poll::Ready(Some(msg)) = Pin::new(&mut handle.rx).poll_next(cx) {
                     match msg {
@@ -487,6 +500,51 @@ impl Target {
                             // register a new listener
                             self.event_listeners.add_listener(req);
                         }
+                        TargetMessage::SetOffline(offline, tx) => {
+                            let mut emulation = self.network_manager.network_emulation().clone();
+                            emulation.offline = offline;
+                            let _ = tx.send(emulation);
+                        }
+                        TargetMessage::EmulateNetworkConditions(conditions, tx) => {
+                            let mut emulation = self.network_manager.network_emulation().clone();
+                            emulation.conditions = conditions;
+                            let _ = tx.send(emulation);
+                        }
+                        TargetMessage::NetworkEmulated(emulation) => {
+                            self.network_manager
+                                .set_network_emulation(emulation.clone());
+                            // report it to the `Handler`, so that targets created later in the
+                            // same context inherit it
+                            self.queued_events
+                                .push_back(TargetEvent::NetworkEmulation(emulation));
+                        }
+                        TargetMessage::SetExtraHeaders(headers, tx) => {
+                            self.network_manager.set_extra_headers(headers);
+                            network_acks.push(tx);
+                        }
+                        TargetMessage::Authenticate(credentials, tx) => {
+                            self.network_manager.authenticate(credentials);
+                            network_acks.push(tx);
+                        }
+                        TargetMessage::MediaFeatures(req) => {
+                            let SetMediaFeatures {
+                                features,
+                                merge,
+                                tx,
+                            } = req;
+                            let _ = tx
+                                .send(self.emulation_manager.merge_media_features(features, merge));
+                        }
+                        TargetMessage::MediaFeaturesEmulated(features) => {
+                            self.emulation_manager.set_media_features(features);
+                        }
+                        TargetMessage::ContextEmulation(update) => {
+                            self.config.context_emulation.merge(update.clone());
+                            // report it to the `Handler`, so that targets created later in the
+                            // same context are initialized with it
+                            self.queued_events
+                                .push_back(TargetEvent::ContextEmulation(update));
+                        }
                         TargetMessage::GetExecutionContext(ctx) => {
                             let GetExecutionContext {
                                 dom_world,
@@ -537,6 +595,10 @@ impl Target {
                     }
                 }
             }
+            // the requests are queued now, ahead of later commands of the page
+            for tx in network_acks {
+                let _ = tx.send(());
+            }
 
             while let Some(event) = self.frame_manager.poll(now) {
                 match event {
@@ -563,6 +625,26 @@ impl Target {
         self.initiator = Some(tx);
     }
 
+    /// Notifies the initiator, if any, that the page could not be created
+    pub(crate) fn fail_initiator(&mut self, err: CdpError) {
+        if let Some(initiator) = self.initiator.take() {
+            let _ = initiator.send(Err(err));
+        }
+    }
+
+    /// The commands to set up the viewport and the emulation settings of the
+    /// browser context
+    fn emulation_init_commands(&mut self) -> CommandChain {
+        let mut cmds = match self.config.viewport.as_ref() {
+            Some(viewport) => self.emulation_manager.init_commands(viewport),
+            None => CommandChain::new(Vec::new(), self.config.request_timeout),
+        };
+        for (method, params) in self.config.context_emulation.init_commands() {
+            cmds.push_back(method, params);
+        }
+        cmds
+    }
+
     pub(crate) fn page_init_commands(timeout: Duration) -> CommandChain {
         let attach = SetAutoAttachParams::builder()
             .flatten(true)
@@ -597,6 +679,17 @@ pub struct TargetConfig {
     pub viewport: Option<Viewport>,
     pub request_intercept: bool,
     pub cache_enabled: bool,
+    /// The network emulation state to initialize the target with
+    pub network_emulation: NetworkEmulation,
+    /// The emulation settings of the browser context to initialize the
+    /// target with
+    pub context_emulation: ContextEmulation,
+    /// Extra http headers to send with every request
+    pub extra_headers: HashMap<String, String>,
+    /// Credentials to answer http authentication challenges with
+    pub credentials: Option<Credentials>,
+    /// Credentials to answer authentication challenges of the proxy with
+    pub proxy_credentials: Option<Credentials>,
 }
 
 impl TargetConfig {
@@ -613,6 +706,11 @@ impl TargetConfig {
             viewport,
             request_intercept,
             cache_enabled,
+            network_emulation: Default::default(),
+            context_emulation: Default::default(),
+            extra_headers: Default::default(),
+            credentials: None,
+            proxy_credentials: None,
         }
     }
 }
@@ -625,6 +723,11 @@ impl Default for TargetConfig {
             viewport: Default::default(),
             request_intercept: false,
             cache_enabled: true,
+            network_emulation: Default::default(),
+            context_emulation: Default::default(),
+            extra_headers: Default::default(),
+            credentials: None,
+            proxy_credentials: None,
         }
     }
 }
@@ -694,6 +797,11 @@ pub(crate) enum TargetEvent {
     NavigationResult(Result<NavigationOk, NavigationError>),
     /// A new command arrived via a channel
     Command(CommandMessage),
+    /// The network emulation state of the target changed
+    NetworkEmulation(NetworkEmulation),
+    /// Emulation settings were applied to the target that also apply to the
+    /// targets created later in its browser context
+    ContextEmulation(ContextEmulation),
 }
 
 // TODO this can be moved into the classes?
@@ -772,6 +880,16 @@ pub struct GetParent {
     pub tx: Sender<Option<FrameId>>,
 }
 
+#[derive(Debug)]
+pub struct SetMediaFeatures {
+    /// The media features to emulate
+    pub features: Vec<MediaFeature>,
+    /// Whether to keep the currently emulated features that are not replaced
+    pub merge: bool,
+    /// Sender half of the channel to send the resulting set of features back
+    pub tx: Sender<Vec<MediaFeature>>,
+}
+
 #[derive(Debug)]
 pub enum TargetMessage {
     /// Execute a command within the session of this target
@@ -793,4 +911,24 @@ pub enum TargetMessage {
     AddEventListener(EventListenerRequest),
     /// Get the `ExecutionContext` if available
     GetExecutionContext(GetExecutionContext),
+    /// Return the media features to emulate
+    MediaFeatures(SetMediaFeatures),
+    /// Update the tracked emulated media features after the browser applied
+    /// them
+    MediaFeaturesEmulated(Vec<MediaFeature>),
+    /// Record emulation settings for the browser context after the browser
+    /// applied them to this target
+    ContextEmulation(ContextEmulation),
+    /// Return the network emulation state with the offline mode changed
+    SetOffline(bool, Sender<NetworkEmulation>),
+    /// Return the network emulation state with the conditions replaced,
+    /// `None` disables throttling
+    EmulateNetworkConditions(Option<NetworkConditions>, Sender<NetworkEmulation>),
+    /// Update the tracked network emulation state after the browser applied
+    /// it
+    NetworkEmulated(NetworkEmulation),
+    /// Replace the extra http headers sent with every request
+    SetExtraHeaders(HashMap<String, String>, Sender<()>),
+    /// Answer http authentication challenges with these credentials
+    Authenticate(Credentials, Sender<()>),
 }
@@ -68,20 +68,28 @@ use std::sync::Arc;
 pub use chromiumoxide_cdp::cdp;
 pub use chromiumoxide_types::{self as types, Binary, Command, Method, MethodType};
 
-pub use crate::browser::{Browser, BrowserConfig};
+pub use crate::browser::{Browser, BrowserConfig, ExecutableSource, HeadlessMode, LaunchTransport};
+pub use crate::cmd::CommandOptions;
+pub use crate::compat::{ProtocolCheck, ProtocolCompat};
 pub use crate::conn::Connection;
+pub use crate::context::{BrowserContext, BrowserContextOptions};
 pub use crate::element::Element;
 pub use crate::error::Result;
 #[cfg(feature = "fetcher")]
 pub use crate::fetcher::{BrowserFetcher, BrowserFetcherOptions};
+pub use crate::handler::supervision::{LifecycleEvent, ReconnectPolicy};
 pub use crate::handler::Handler;
 pub use crate::page::Page;
+pub use crate::pool::{BrowserPool, PageLease, PoolConfig};
 
 pub mod auth;
 pub mod browser;
 pub(crate) mod cmd;
+pub mod compat;
 pub mod conn;
+pub mod context;
 pub mod detection;
+pub mod devtools;
 pub mod element;
 pub mod error;
 #[cfg(feature = "fetcher")]
@@ -95,6 +103,11 @@ pub mod keys;
 pub mod layout;
 pub mod listeners;
 pub mod page;
+pub mod pool;
+pub(crate) mod profile;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transport;
 pub(crate) mod utils;
 
 pub type ArcHttpRequest = Option<Arc<HttpRequest>>;
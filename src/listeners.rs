@@ -2,20 +2,24 @@ use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 
-use futures::channel::mpsc::{SendError, UnboundedReceiver, UnboundedSender};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::{Sink, Stream};
 
 use chromiumoxide_cdp::cdp::{Event, EventKind, IntoEventKind};
 use chromiumoxide_types::MethodId;
 
+use crate::error::CdpError;
+
 /// All the currently active listeners
 #[derive(Debug, Default)]
 pub struct EventListeners {
     /// Tracks the listeners for each event identified by the key
     listeners: HashMap<MethodId, Vec<EventListener>>,
+    /// The number of listeners that block the handler when they are full
+    blocking: usize,
 }
 
 impl EventListeners {
@@ -26,6 +30,9 @@ impl EventListeners {
             method,
             kind,
         } = req;
+        if listener.is_blocking() {
+            self.blocking += 1;
+        }
         let subs = self.listeners.entry(method).or_default();
         subs.push(EventListener {
             listener,
@@ -83,9 +90,10 @@ impl EventListeners {
             for n in (0..subscriptions.len()).rev() {
                 let mut sub = subscriptions.swap_remove(n);
                 match sub.poll(cx) {
-                    Poll::Ready(Err(err)) => {
-                        if !err.is_disconnected() {
-                            subscriptions.push(sub)
+                    Poll::Ready(Err(_)) => {
+                        // disconnected
+                        if sub.listener.is_blocking() {
+                            self.blocking -= 1;
                         }
                     }
                     _ => subscriptions.push(sub),
@@ -93,10 +101,64 @@ impl EventListeners {
             }
         }
     }
+
+    /// Drains the queued events if there are listeners that block the handler
+    /// when they are full, returns `true` if any of them is still full.
+    pub fn poll_blocked(&mut self, cx: &mut Context<'_>) -> bool {
+        if self.blocking == 0 {
+            return false;
+        }
+        self.poll(cx);
+        self.listeners
+            .values()
+            .flatten()
+            .any(|sub| sub.listener.is_blocking() && !sub.queued_events.is_empty())
+    }
+}
+
+/// What a bounded listener does with a new event when it is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Stop reading from the browser until the listener has room again.
+    ///
+    /// This applies backpressure to the whole connection, every other
+    /// listener and command is delayed as well.
+    #[default]
+    Block,
+    /// Drop the oldest buffered event to make room for the new one
+    DropOldest,
+    /// Drop the new event
+    DropNewest,
+    /// Close the stream, see [`EventStream::error`]
+    Close,
+}
+
+/// How to buffer the events of a listener
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListenerOptions {
+    /// How many events are buffered at most, unlimited if `None`
+    pub capacity: Option<usize>,
+    /// What to do with new events once `capacity` is reached
+    pub overflow: OverflowPolicy,
+}
+
+impl ListenerOptions {
+    /// Buffer all events, the default
+    pub fn unbounded() -> Self {
+        Self::default()
+    }
+
+    /// Buffer at most `capacity` events
+    pub fn bounded(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            capacity: Some(capacity.max(1)),
+            overflow,
+        }
+    }
 }
 
 pub struct EventListenerRequest {
-    listener: UnboundedSender<Arc<dyn Event>>,
+    listener: ListenerSender,
     method: MethodId,
     kind: EventKind,
 }
@@ -104,11 +166,41 @@ pub struct EventListenerRequest {
 impl EventListenerRequest {
     pub fn new<T: IntoEventKind>(listener: UnboundedSender<Arc<dyn Event>>) -> Self {
         Self {
-            listener,
+            listener: ListenerSender::Unbounded(listener),
             method: T::method_id(),
             kind: T::event_kind(),
         }
     }
+
+    /// Creates the request for a new listener together with its stream
+    pub fn with_options<T: IntoEventKind>(options: ListenerOptions) -> (Self, EventStream<T>) {
+        let (listener, events) = match options.capacity {
+            Some(capacity) => {
+                let channel = Arc::new(Bounded::new(capacity, options.overflow));
+                (
+                    ListenerSender::Bounded(Arc::clone(&channel)),
+                    ListenerReceiver::Bounded(channel),
+                )
+            }
+            None => {
+                let (tx, rx) = unbounded();
+                (
+                    ListenerSender::Unbounded(tx),
+                    ListenerReceiver::Unbounded(rx),
+                )
+            }
+        };
+        let req = Self {
+            listener,
+            method: T::method_id(),
+            kind: T::event_kind(),
+        };
+        let stream = EventStream {
+            events,
+            _marker: PhantomData,
+        };
+        (req, stream)
+    }
 }
 
 impl fmt::Debug for EventListenerRequest {
@@ -123,7 +215,7 @@ impl fmt::Debug for EventListenerRequest {
 /// Represents a single event listener
 pub struct EventListener {
     /// the sender half of the event channel
-    listener: UnboundedSender<Arc<dyn Event>>,
+    listener: ListenerSender,
     /// currently queued events
     queued_events: VecDeque<Arc<dyn Event>>,
     /// For what kind of event this event is for
@@ -138,25 +230,30 @@ impl EventListener {
 
     /// Drains all queued events and begins the process of sending them to the
     /// sink.
-    pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
-        loop {
-            match Sink::poll_ready(Pin::new(&mut self.listener), cx) {
-                Poll::Ready(Ok(_)) => {}
-                Poll::Ready(Err(err)) => {
-                    // disconnected
-                    return Poll::Ready(Err(err));
-                }
-                Poll::Pending => {
-                    return Poll::Pending;
+    ///
+    /// Fails if the receiver is gone.
+    pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        match &mut self.listener {
+            ListenerSender::Unbounded(listener) => loop {
+                match Sink::poll_ready(Pin::new(&mut *listener), cx) {
+                    Poll::Ready(Ok(_)) => {}
+                    Poll::Ready(Err(_)) => {
+                        // disconnected
+                        return Poll::Ready(Err(Closed));
+                    }
+                    Poll::Pending => {
+                        return Poll::Pending;
+                    }
                 }
-            }
-            if let Some(event) = self.queued_events.pop_front() {
-                if let Err(err) = Sink::start_send(Pin::new(&mut self.listener), event) {
-                    return Poll::Ready(Err(err));
+                if let Some(event) = self.queued_events.pop_front() {
+                    if Sink::start_send(Pin::new(&mut *listener), event).is_err() {
+                        return Poll::Ready(Err(Closed));
+                    }
+                } else {
+                    return Poll::Ready(Ok(()));
                 }
-            } else {
-                return Poll::Ready(Ok(()));
-            }
+            },
+            ListenerSender::Bounded(channel) => channel.poll_send(&mut self.queued_events, cx),
         }
     }
 }
@@ -167,9 +264,162 @@ impl fmt::Debug for EventListener {
     }
 }
 
+/// The receiver of a listener is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+/// The sending half of a listener
+enum ListenerSender {
+    Unbounded(UnboundedSender<Arc<dyn Event>>),
+    Bounded(Arc<Bounded>),
+}
+
+impl ListenerSender {
+    fn is_blocking(&self) -> bool {
+        matches!(self, ListenerSender::Bounded(channel) if channel.overflow == OverflowPolicy::Block)
+    }
+}
+
+impl Drop for ListenerSender {
+    fn drop(&mut self) {
+        if let ListenerSender::Bounded(channel) = self {
+            let mut state = channel.state();
+            state.sender_alive = false;
+            if let Some(waker) = state.receiver_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The receiving half of a listener
+enum ListenerReceiver {
+    Unbounded(UnboundedReceiver<Arc<dyn Event>>),
+    Bounded(Arc<Bounded>),
+}
+
+impl Drop for ListenerReceiver {
+    fn drop(&mut self) {
+        if let ListenerReceiver::Bounded(channel) = self {
+            let mut state = channel.state();
+            state.receiver_alive = false;
+            state.events.clear();
+            if let Some(waker) = state.sender_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A channel that buffers a limited number of events
+struct Bounded {
+    capacity: usize,
+    overflow: OverflowPolicy,
+    state: Mutex<BoundedState>,
+}
+
+struct BoundedState {
+    events: VecDeque<Arc<dyn Event>>,
+    /// How many events were dropped
+    lagged: u64,
+    /// Whether the channel was closed because it was full
+    overflowed: bool,
+    sender_alive: bool,
+    receiver_alive: bool,
+    /// The handler waiting for room in the buffer
+    sender_waker: Option<Waker>,
+    /// The stream waiting for new events
+    receiver_waker: Option<Waker>,
+}
+
+impl Bounded {
+    fn new(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            capacity,
+            overflow,
+            state: Mutex::new(BoundedState {
+                events: VecDeque::with_capacity(capacity),
+                lagged: 0,
+                overflowed: false,
+                sender_alive: true,
+                receiver_alive: true,
+                sender_waker: None,
+                receiver_waker: None,
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BoundedState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Moves the queued events into the buffer according to the overflow
+    /// policy
+    fn poll_send(
+        &self,
+        queued: &mut VecDeque<Arc<dyn Event>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Closed>> {
+        let mut state = self.state();
+        if !state.receiver_alive || state.overflowed {
+            queued.clear();
+            return Poll::Ready(Err(Closed));
+        }
+        let mut res = Poll::Ready(Ok(()));
+        while let Some(event) = queued.pop_front() {
+            if state.events.len() < self.capacity {
+                state.events.push_back(event);
+                continue;
+            }
+            match self.overflow {
+                OverflowPolicy::Block => {
+                    queued.push_front(event);
+                    state.sender_waker = Some(cx.waker().clone());
+                    res = Poll::Pending;
+                    break;
+                }
+                OverflowPolicy::DropOldest => {
+                    state.events.pop_front();
+                    state.events.push_back(event);
+                    state.lagged += 1;
+                }
+                OverflowPolicy::DropNewest => {
+                    state.lagged += 1;
+                }
+                OverflowPolicy::Close => {
+                    state.lagged += 1 + queued.len() as u64;
+                    state.overflowed = true;
+                    queued.clear();
+                    res = Poll::Ready(Err(Closed));
+                    break;
+                }
+            }
+        }
+        if let Some(waker) = state.receiver_waker.take() {
+            waker.wake();
+        }
+        res
+    }
+
+    fn poll_next(&self, cx: &mut Context<'_>) -> Poll<Option<Arc<dyn Event>>> {
+        let mut state = self.state();
+        if let Some(event) = state.events.pop_front() {
+            if let Some(waker) = state.sender_waker.take() {
+                waker.wake();
+            }
+            Poll::Ready(Some(event))
+        } else if state.overflowed || !state.sender_alive {
+            Poll::Ready(None)
+        } else {
+            state.receiver_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
 /// The receiver part of an event subscription
 pub struct EventStream<T: IntoEventKind> {
-    events: UnboundedReceiver<Arc<dyn Event>>,
+    events: ListenerReceiver,
     _marker: PhantomData<T>,
 }
 
@@ -182,10 +432,31 @@ impl<T: IntoEventKind> fmt::Debug for EventStream<T> {
 impl<T: IntoEventKind> EventStream<T> {
     pub fn new(events: UnboundedReceiver<Arc<dyn Event>>) -> Self {
         Self {
-            events,
+            events: ListenerReceiver::Unbounded(events),
             _marker: PhantomData,
         }
     }
+
+    /// How many events were dropped because this bounded listener was full,
+    /// always `0` for unbounded listeners
+    pub fn lagged(&self) -> u64 {
+        match &self.events {
+            ListenerReceiver::Unbounded(_) => 0,
+            ListenerReceiver::Bounded(channel) => channel.state().lagged,
+        }
+    }
+
+    /// The reason the stream ended early, if it was closed because it fell
+    /// behind with [`OverflowPolicy::Close`]
+    pub fn error(&self) -> Option<CdpError> {
+        match &self.events {
+            ListenerReceiver::Bounded(channel) => {
+                let state = channel.state();
+                state.overflowed.then_some(CdpError::Lagged(state.lagged))
+            }
+            ListenerReceiver::Unbounded(_) => None,
+        }
+    }
 }
 
 impl<T: IntoEventKind + Unpin> Stream for EventStream<T> {
@@ -193,7 +464,11 @@ impl<T: IntoEventKind + Unpin> Stream for EventStream<T> {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let pin = self.get_mut();
-        match Stream::poll_next(Pin::new(&mut pin.events), cx) {
+        let next = match &mut pin.events {
+            ListenerReceiver::Unbounded(events) => Stream::poll_next(Pin::new(events), cx),
+            ListenerReceiver::Bounded(channel) => channel.poll_next(cx),
+        };
+        match next {
             Poll::Ready(Some(event)) => {
                 if let Ok(e) = event.into_any_arc().downcast() {
                     Poll::Ready(Some(e))
@@ -271,7 +546,7 @@ mod tests {
         listeners.add_listener(EventListenerRequest {
             method: EventAnimationCanceled::method_id(),
             kind: EventAnimationCanceled::event_kind(),
-            listener: tx,
+            listener: ListenerSender::Unbounded(tx),
         });
 
         listeners.start_send(event.clone());
@@ -291,4 +566,63 @@ mod tests {
         let next = stream.next().await.unwrap();
         assert_eq!(&*next, &event);
     }
+
+    fn animation_canceled(id: usize) -> EventAnimationCanceled {
+        EventAnimationCanceled { id: id.to_string() }
+    }
+
+    /// Registers a bounded listener and sends it `count` events
+    fn bounded_listener(
+        overflow: OverflowPolicy,
+        count: usize,
+    ) -> (EventListeners, EventStream<EventAnimationCanceled>) {
+        let (req, stream) = EventListenerRequest::with_options::<EventAnimationCanceled>(
+            ListenerOptions::bounded(2, overflow),
+        );
+        let mut listeners = EventListeners::default();
+        listeners.add_listener(req);
+        for id in 0..count {
+            listeners.start_send(animation_canceled(id));
+        }
+        (listeners, stream)
+    }
+
+    #[async_std::test]
+    async fn bounded_listener_drops_oldest() {
+        let (mut listeners, mut stream) = bounded_listener(OverflowPolicy::DropOldest, 5);
+        futures::future::poll_fn(|cx| {
+            listeners.poll(cx);
+            Poll::Ready(())
+        })
+        .await;
+        assert_eq!(stream.lagged(), 3);
+        assert_eq!(stream.next().await.unwrap().id, "3");
+        assert_eq!(stream.next().await.unwrap().id, "4");
+    }
+
+    #[async_std::test]
+    async fn bounded_listener_blocks_when_full() {
+        let (mut listeners, mut stream) = bounded_listener(OverflowPolicy::Block, 3);
+        let blocked = futures::future::poll_fn(|cx| Poll::Ready(listeners.poll_blocked(cx))).await;
+        assert!(blocked);
+
+        assert_eq!(stream.next().await.unwrap().id, "0");
+        let blocked = futures::future::poll_fn(|cx| Poll::Ready(listeners.poll_blocked(cx))).await;
+        assert!(!blocked);
+        assert_eq!(stream.lagged(), 0);
+    }
+
+    #[async_std::test]
+    async fn bounded_listener_closes_on_overflow() {
+        let (mut listeners, mut stream) = bounded_listener(OverflowPolicy::Close, 3);
+        futures::future::poll_fn(|cx| {
+            listeners.poll(cx);
+            Poll::Ready(())
+        })
+        .await;
+        assert_eq!(stream.next().await.unwrap().id, "0");
+        assert_eq!(stream.next().await.unwrap().id, "1");
+        assert!(stream.next().await.is_none());
+        assert!(matches!(stream.error(), Some(CdpError::Lagged(1))));
+    }
 }
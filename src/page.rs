@@ -1,13 +1,18 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 
-use futures::channel::mpsc::unbounded;
 use futures::channel::oneshot::channel as oneshot_channel;
 use futures::{stream, SinkExt, StreamExt};
 
+use chromiumoxide_cdp::cdp::browser_protocol::browser::{
+    BrowserContextId, GrantPermissionsParams, PermissionType, ResetPermissionsParams,
+};
 use chromiumoxide_cdp::cdp::browser_protocol::dom::*;
 use chromiumoxide_cdp::cdp::browser_protocol::emulation::{
-    MediaFeature, SetEmulatedMediaParams, SetTimezoneOverrideParams,
+    MediaFeature, SetCpuThrottlingRateParams, SetEmulatedVisionDeficiencyParams,
+    SetEmulatedVisionDeficiencyType, SetGeolocationOverrideParams, SetLocaleOverrideParams,
+    SetTimezoneOverrideParams,
 };
 use chromiumoxide_cdp::cdp::browser_protocol::network::{
     Cookie, CookieParam, DeleteCookiesParams, GetCookiesParams, SetCookiesParams,
@@ -25,16 +30,20 @@ use chromiumoxide_cdp::cdp::js_protocol::runtime::{
 use chromiumoxide_cdp::cdp::{browser_protocol, IntoEventKind};
 use chromiumoxide_types::*;
 
+use crate::auth::Credentials;
+use crate::cmd::CommandOptions;
 use crate::element::Element;
 use crate::error::{CdpError, Result};
 use crate::handler::commandfuture::CommandFuture;
 use crate::handler::domworld::DOMWorldKind;
+use crate::handler::emulation::{ColorScheme, ContextEmulation, ReducedMotion};
 use crate::handler::httpfuture::HttpFuture;
+use crate::handler::network::NetworkConditions;
 use crate::handler::target::{GetName, GetParent, GetUrl, TargetMessage};
 use crate::handler::PageInner;
 use crate::js::{Evaluation, EvaluationResult};
 use crate::layout::Point;
-use crate::listeners::{EventListenerRequest, EventStream};
+use crate::listeners::{EventListenerRequest, EventStream, ListenerOptions};
 use crate::{utils, ArcHttpRequest};
 
 #[derive(Debug, Clone)]
@@ -180,6 +189,36 @@ impl Page {
         self.command_future(cmd)?.await
     }
 
+    /// Execute a command with overridden `options` and return the
+    /// `Command::Response`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use chromiumoxide::page::Page;
+    /// # use chromiumoxide::error::Result;
+    /// # use chromiumoxide::CommandOptions;
+    /// # use chromiumoxide_cdp::cdp::browser_protocol::page::PrintToPdfParams;
+    /// # async fn demo(page: Page) -> Result<()> {
+    /// // rendering a large document takes longer than the default timeout
+    /// let pdf = page
+    ///     .execute_with(
+    ///         PrintToPdfParams::default(),
+    ///         CommandOptions::timeout(Duration::from_secs(120)),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_with<T: Command>(
+        &self,
+        cmd: T,
+        options: CommandOptions,
+    ) -> Result<CommandResponse<T::Response>> {
+        self.inner.command_future_with(cmd, options)?.await
+    }
+
     /// Execute a command and return the `Command::Response`
     pub fn command_future<T: Command>(&self, cmd: T) -> Result<CommandFuture<T>> {
         self.inner.command_future(cmd)
@@ -255,16 +294,48 @@ impl Page {
     /// # }
     /// ```
     pub async fn event_listener<T: IntoEventKind>(&self) -> Result<EventStream<T>> {
-        let (tx, rx) = unbounded();
+        self.event_listener_with(ListenerOptions::unbounded()).await
+    }
+
+    /// Returns an event listener that buffers events according to `options`.
+    ///
+    /// A bounded listener keeps a slow consumer from growing the memory
+    /// without limit, see [`OverflowPolicy`](crate::listeners::OverflowPolicy)
+    /// for what happens when it is full.
+    ///
+    /// # Example Drop the oldest requests if the consumer falls behind
+    /// ```no_run
+    /// # use chromiumoxide::page::Page;
+    /// # use chromiumoxide::error::Result;
+    /// # use chromiumoxide::listeners::{ListenerOptions, OverflowPolicy};
+    /// # use chromiumoxide_cdp::cdp::browser_protocol::network::EventRequestWillBeSent;
+    /// # use futures::StreamExt;
+    /// # async fn demo(page: Page) -> Result<()> {
+    ///     let mut events = page
+    ///         .event_listener_with::<EventRequestWillBeSent>(ListenerOptions::bounded(
+    ///             256,
+    ///             OverflowPolicy::DropOldest,
+    ///         ))
+    ///         .await?;
+    ///     while let Some(event) = events.next().await {
+    ///         //..
+    ///     }
+    ///     println!("dropped {} events", events.lagged());
+    ///     # Ok(())
+    /// # }
+    /// ```
+    pub async fn event_listener_with<T: IntoEventKind>(
+        &self,
+        options: ListenerOptions,
+    ) -> Result<EventStream<T>> {
+        let (req, stream) = EventListenerRequest::with_options::<T>(options);
         self.inner
             .sender()
             .clone()
-            .send(TargetMessage::AddEventListener(
-                EventListenerRequest::new::<T>(tx),
-            ))
+            .send(TargetMessage::AddEventListener(req))
             .await?;
 
-        Ok(EventStream::new(rx))
+        Ok(stream)
     }
 
     pub async fn expose_function(
@@ -324,6 +395,12 @@ impl Page {
         self.inner.session_id()
     }
 
+    /// The identifier of the browser context this page belongs to, `None` if
+    /// it runs in the default context
+    pub fn browser_context_id(&self) -> Option<&BrowserContextId> {
+        self.inner.browser_context_id()
+    }
+
     /// Returns the name of the frame
     pub async fn frame_name(&self, frame_id: FrameId) -> Result<Option<String>> {
         let (tx, rx) = oneshot_channel();
@@ -396,6 +473,44 @@ impl Page {
         Ok(rx.await?)
     }
 
+    /// Sets extra http headers that this page sends with every request.
+    ///
+    /// This replaces all previously set extra headers.
+    pub async fn set_extra_http_headers<I, K, V>(&self, headers: I) -> Result<&Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let headers: HashMap<String, String> = headers
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        let (tx, rx) = oneshot_channel();
+        self.inner
+            .sender()
+            .clone()
+            .send(TargetMessage::SetExtraHeaders(headers, tx))
+            .await?;
+        rx.await?;
+        Ok(self)
+    }
+
+    /// Provides credentials for http authentication challenges.
+    ///
+    /// This enables request interception for the page, every paused request
+    /// that isn't intercepted by the user is continued automatically.
+    pub async fn authenticate(&self, credentials: Credentials) -> Result<&Self> {
+        let (tx, rx) = oneshot_channel();
+        self.inner
+            .sender()
+            .clone()
+            .send(TargetMessage::Authenticate(credentials, tx))
+            .await?;
+        rx.await?;
+        Ok(self)
+    }
+
     /// Allows overriding user agent with the given string.
     pub async fn set_user_agent(
         &self,
@@ -616,8 +731,50 @@ impl Page {
     }
 
     /// Emulates the given media type or media feature for CSS media queries
+    ///
+    /// This replaces all media features that are currently emulated, including
+    /// those set with `Page::emulate_color_scheme` and
+    /// `Page::emulate_reduced_motion`.
     pub async fn emulate_media_features(&self, features: Vec<MediaFeature>) -> Result<&Self> {
-        self.execute(SetEmulatedMediaParams::builder().features(features).build())
+        self.inner.emulate_media_features(features, false).await?;
+        Ok(self)
+    }
+
+    /// Emulates the `prefers-color-scheme` media feature.
+    ///
+    /// Other emulated media features are kept.
+    pub async fn emulate_color_scheme(&self, scheme: ColorScheme) -> Result<&Self> {
+        self.inner
+            .emulate_media_features(vec![scheme.into()], true)
+            .await?;
+        Ok(self)
+    }
+
+    /// Emulates the `prefers-reduced-motion` media feature.
+    ///
+    /// Other emulated media features are kept.
+    pub async fn emulate_reduced_motion(&self, motion: ReducedMotion) -> Result<&Self> {
+        self.inner
+            .emulate_media_features(vec![motion.into()], true)
+            .await?;
+        Ok(self)
+    }
+
+    /// Emulates the given vision deficiency, use
+    /// `SetEmulatedVisionDeficiencyType::None` to disable it again.
+    ///
+    /// Pages opened later in the same browser context emulate it as well.
+    pub async fn emulate_vision_deficiency(
+        &self,
+        deficiency: SetEmulatedVisionDeficiencyType,
+    ) -> Result<&Self> {
+        self.execute(SetEmulatedVisionDeficiencyParams::new(deficiency.clone()))
+            .await?;
+        self.inner
+            .record_context_emulation(ContextEmulation {
+                vision_deficiency: Some(deficiency),
+                ..Default::default()
+            })
             .await?;
         Ok(self)
     }
@@ -631,6 +788,146 @@ impl Page {
         Ok(self)
     }
 
+    /// Overrides the geolocation position of the page.
+    ///
+    /// Note that the page also needs the `PermissionType::Geolocation`
+    /// permission to read the position, see `Page::grant_permissions`.
+    ///
+    /// Pages opened later in the same browser context get the position as
+    /// well.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use chromiumoxide::page::Page;
+    /// # use chromiumoxide::error::Result;
+    /// # use chromiumoxide_cdp::cdp::browser_protocol::browser::PermissionType;
+    /// # async fn demo(page: Page) -> Result<()> {
+    ///     page.grant_permissions("https://example.com", [PermissionType::Geolocation])
+    ///         .await?
+    ///         .set_geolocation(48.8584, 2.2945, 10.)
+    ///         .await?;
+    ///     # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_geolocation(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        accuracy: f64,
+    ) -> Result<&Self> {
+        let geolocation = SetGeolocationOverrideParams {
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            accuracy: Some(accuracy),
+        };
+        self.execute(geolocation.clone()).await?;
+        self.inner
+            .record_context_emulation(ContextEmulation {
+                geolocation: Some(geolocation),
+                ..Default::default()
+            })
+            .await?;
+        Ok(self)
+    }
+
+    /// Overrides the default host system locale with an ICU style C locale
+    /// (e.g. "en_US").
+    ///
+    /// Pages opened later in the same browser context use the locale as well.
+    pub async fn set_locale(&self, locale: impl Into<String>) -> Result<&Self> {
+        let locale = locale.into();
+        self.execute(SetLocaleOverrideParams {
+            locale: Some(locale.clone()),
+        })
+        .await?;
+        self.inner
+            .record_context_emulation(ContextEmulation {
+                locale: Some(locale),
+                ..Default::default()
+            })
+            .await?;
+        Ok(self)
+    }
+
+    /// Throttles the CPU to emulate slower devices.
+    ///
+    /// The rate is a slowdown factor, 1 is no throttle, 2 is 2x slowdown, etc.
+    /// Pages opened later in the same browser context are throttled as well.
+    pub async fn set_cpu_throttling_rate(&self, rate: f64) -> Result<&Self> {
+        self.execute(SetCpuThrottlingRateParams::new(rate)).await?;
+        self.inner
+            .record_context_emulation(ContextEmulation {
+                cpu_throttling_rate: Some(rate),
+                ..Default::default()
+            })
+            .await?;
+        Ok(self)
+    }
+
+    /// Grants the permissions to the given origin and rejects all others.
+    ///
+    /// Permissions are granted for the browser context this page runs in, so
+    /// they also apply to all other pages of the same context.
+    pub async fn grant_permissions(
+        &self,
+        origin: impl Into<String>,
+        permissions: impl IntoIterator<Item = PermissionType>,
+    ) -> Result<&Self> {
+        self.inner
+            .execute_browser(GrantPermissionsParams {
+                permissions: permissions.into_iter().collect(),
+                origin: Some(origin.into()),
+                browser_context_id: self.inner.browser_context_id().cloned(),
+            })
+            .await?;
+        Ok(self)
+    }
+
+    /// Resets all permission overrides of the browser context this page runs
+    /// in.
+    pub async fn reset_permissions(&self) -> Result<&Self> {
+        self.inner
+            .execute_browser(ResetPermissionsParams {
+                browser_context_id: self.inner.browser_context_id().cloned(),
+            })
+            .await?;
+        Ok(self)
+    }
+
+    /// Emulates a disconnected network for this page.
+    ///
+    /// Pages that are created afterwards in the same browser context start in
+    /// the same network state.
+    pub async fn set_offline(&self, offline: bool) -> Result<&Self> {
+        self.inner.set_offline(offline).await?;
+        Ok(self)
+    }
+
+    /// Throttles the network of this page, `None` disables the throttling.
+    ///
+    /// Pages that are created afterwards in the same browser context start in
+    /// the same network state.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use chromiumoxide::page::Page;
+    /// # use chromiumoxide::error::Result;
+    /// # use chromiumoxide::handler::network::NetworkConditions;
+    /// # async fn demo(page: Page) -> Result<()> {
+    ///     page.emulate_network_conditions(NetworkConditions::SLOW_3G).await?;
+    ///     # Ok(())
+    /// # }
+    /// ```
+    pub async fn emulate_network_conditions(
+        &self,
+        conditions: impl Into<Option<NetworkConditions>>,
+    ) -> Result<&Self> {
+        self.inner
+            .emulate_network_conditions(conditions.into())
+            .await?;
+        Ok(self)
+    }
+
     /// Reloads given page
     ///
     /// To reload ignoring cache run:
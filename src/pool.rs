@@ -0,0 +1,810 @@
+//! A pool of browser processes that leases out pages.
+//!
+//! The [`BrowserPool`] launches browsers on demand, up to a configured limit,
+//! and hands out [`PageLease`]s on them. Browsers are recycled after serving a
+//! number of pages, when a page used too much memory, or when their `Handler`
+//! stopped because the browser crashed.
+//!
+//! ```no_run
+//! # use chromiumoxide::browser::BrowserConfig;
+//! # use chromiumoxide::pool::{BrowserPool, PoolConfig};
+//! # async fn demo() -> chromiumoxide::Result<()> {
+//! let pool = BrowserPool::new(
+//!     BrowserConfig::builder().build().unwrap(),
+//!     PoolConfig::builder()
+//!         .max_browsers(4)
+//!         .max_pages_per_browser(8)
+//!         .recycle_after_pages(500)
+//!         .build(),
+//! );
+//!
+//! let page = pool.lease().await?;
+//! page.goto("https://example.com").await?;
+//! page.release().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+
+use chromiumoxide_cdp::cdp::js_protocol::runtime::GetHeapUsageParams;
+
+use crate::browser::{Browser, BrowserConfig};
+use crate::context::{BrowserContext, BrowserContextOptions};
+use crate::error::{CdpError, Result};
+use crate::handler::Handler;
+use crate::page::Page;
+use crate::utils;
+
+type Launcher = Box<dyn Fn() -> BoxFuture<'static, Result<(Browser, Handler)>> + Send + Sync>;
+
+/// How a [`BrowserPool`] manages its browsers
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    /// How many browsers may run at the same time
+    pub max_browsers: usize,
+    /// How many pages may be leased from a single browser at the same time
+    pub max_pages_per_browser: usize,
+    /// Replace a browser once it served this many pages
+    pub recycle_after_pages: Option<usize>,
+    /// Replace a browser once the JS heap of a page released to it exceeds
+    /// this many bytes
+    pub max_heap_size: Option<u64>,
+    /// Whether every lease gets its own isolated `BrowserContext`
+    pub context_per_lease: bool,
+    /// The url new pages are opened with
+    pub url: String,
+}
+
+impl PoolConfig {
+    pub fn builder() -> PoolConfigBuilder {
+        PoolConfigBuilder::default()
+    }
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolConfigBuilder {
+    max_browsers: usize,
+    max_pages_per_browser: usize,
+    recycle_after_pages: Option<usize>,
+    max_heap_size: Option<u64>,
+    context_per_lease: bool,
+    url: String,
+}
+
+impl Default for PoolConfigBuilder {
+    fn default() -> Self {
+        Self {
+            max_browsers: 1,
+            max_pages_per_browser: 4,
+            recycle_after_pages: None,
+            max_heap_size: None,
+            context_per_lease: false,
+            url: "about:blank".to_string(),
+        }
+    }
+}
+
+impl PoolConfigBuilder {
+    pub fn max_browsers(mut self, max_browsers: usize) -> Self {
+        self.max_browsers = max_browsers.max(1);
+        self
+    }
+
+    pub fn max_pages_per_browser(mut self, max_pages: usize) -> Self {
+        self.max_pages_per_browser = max_pages.max(1);
+        self
+    }
+
+    /// Replace a browser once it served `pages` pages
+    pub fn recycle_after_pages(mut self, pages: usize) -> Self {
+        self.recycle_after_pages = Some(pages.max(1));
+        self
+    }
+
+    /// Replace a browser once the JS heap of a page released to it exceeds
+    /// `bytes`
+    pub fn max_heap_size(mut self, bytes: u64) -> Self {
+        self.max_heap_size = Some(bytes);
+        self
+    }
+
+    /// Open every leased page in a new isolated `BrowserContext`, which is
+    /// disposed on release
+    pub fn context_per_lease(mut self) -> Self {
+        self.context_per_lease = true;
+        self
+    }
+
+    /// The url new pages are opened with, `about:blank` by default
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    pub fn build(self) -> PoolConfig {
+        PoolConfig {
+            max_browsers: self.max_browsers,
+            max_pages_per_browser: self.max_pages_per_browser,
+            recycle_after_pages: self.recycle_after_pages,
+            max_heap_size: self.max_heap_size,
+            context_per_lease: self.context_per_lease,
+            url: self.url,
+        }
+    }
+}
+
+/// A snapshot of the utilization of a [`BrowserPool`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Running browsers, including those waiting to be recycled
+    pub browsers: usize,
+    /// Browsers currently being launched
+    pub launching: usize,
+    /// Pages currently leased
+    pub leased_pages: usize,
+    /// How many pages can be leased at the same time
+    pub capacity: usize,
+    /// Callers waiting for a page
+    pub waiting: usize,
+    /// Pages leased since the pool was created
+    pub total_leases: u64,
+    /// Browsers replaced because of their page count or memory usage
+    pub recycled_browsers: u64,
+    /// Browsers replaced because they crashed or failed a health check
+    pub crashed_browsers: u64,
+}
+
+impl PoolMetrics {
+    /// The share of the capacity that is leased, between `0.0` and `1.0`
+    pub fn utilization(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.leased_pages as f64 / self.capacity as f64
+    }
+}
+
+/// Manages a set of browsers and leases out pages on them.
+///
+/// Cloning the pool is cheap, all clones share the same browsers.
+#[derive(Clone)]
+pub struct BrowserPool {
+    inner: Arc<Inner>,
+}
+
+impl BrowserPool {
+    /// Creates a pool that launches its browsers with `browser`
+    pub fn new(browser: BrowserConfig, config: PoolConfig) -> Self {
+        Self::with_launcher(config, move || Browser::launch(browser.clone()))
+    }
+
+    /// Creates a pool that gets its browsers from `launcher`, for example by
+    /// connecting to remote browsers with [`Browser::connect`].
+    ///
+    /// The pool drives the returned `Handler` in the background.
+    pub fn with_launcher<F, Fut>(config: PoolConfig, launcher: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(Browser, Handler)>> + Send + 'static,
+    {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                launcher: Box::new(move || Box::pin(launcher())),
+                state: Default::default(),
+            }),
+        }
+    }
+
+    /// The configuration of this pool
+    pub fn config(&self) -> &PoolConfig {
+        &self.inner.config
+    }
+
+    /// Leases a new page.
+    ///
+    /// Launches a new browser if all running browsers are at their page limit
+    /// and there is room for another one, otherwise waits until a page is
+    /// released.
+    pub async fn lease(&self) -> Result<PageLease> {
+        loop {
+            let next = {
+                let mut state = self.inner.state();
+                if state.closed {
+                    return Err(CdpError::PoolClosed);
+                }
+                self.inner.next_lease(&mut state)
+            };
+            match next {
+                NextLease::Slot(id, browser) => {
+                    let reservation = Reservation::new(&self.inner, Reserved::Slot(id));
+                    let lease = self.open_page(id, browser).await;
+                    reservation.disarm();
+                    return lease;
+                }
+                NextLease::Launch => {
+                    let reservation = Reservation::new(&self.inner, Reserved::Launch);
+                    let launched = self.launch().await;
+                    reservation.disarm();
+                    let mut state = self.inner.state();
+                    state.launching -= 1;
+                    match launched {
+                        Ok(mut slot) => {
+                            // the pool may have been closed in the meantime
+                            slot.retiring = state.closed;
+                            state.slots.push(slot);
+                            state.reap();
+                        }
+                        Err(err) => {
+                            state.wake_one();
+                            return Err(err);
+                        }
+                    }
+                }
+                NextLease::Wait(rx) => {
+                    let _ = rx.await;
+                }
+            }
+        }
+    }
+
+    /// Checks whether every browser still responds and recycles those that
+    /// don't.
+    ///
+    /// Returns the number of browsers that failed the check.
+    pub async fn health_check(&self) -> usize {
+        let browsers: Vec<_> = self
+            .inner
+            .state()
+            .slots
+            .iter()
+            .filter(|slot| !slot.retiring)
+            .map(|slot| (slot.id, Arc::clone(&slot.browser)))
+            .collect();
+
+        let mut failed = Vec::new();
+        for (id, browser) in browsers {
+            if let Err(err) = browser.version().await {
+                tracing::warn!("Pooled browser failed the health check: {:?}", err);
+                failed.push(id);
+            }
+        }
+
+        let mut state = self.inner.state();
+        for slot in state.slots.iter_mut() {
+            if failed.contains(&slot.id) {
+                slot.retiring = true;
+                slot.alive.store(false, Ordering::Release);
+            }
+        }
+        state.reap();
+        failed.len()
+    }
+
+    /// Current utilization of the pool
+    pub fn metrics(&self) -> PoolMetrics {
+        let mut state = self.inner.state();
+        state.waiters.retain(|tx| !tx.is_canceled());
+        PoolMetrics {
+            browsers: state.slots.len(),
+            launching: state.launching,
+            leased_pages: state.slots.iter().map(|slot| slot.leased).sum(),
+            capacity: self.inner.config.max_browsers * self.inner.config.max_pages_per_browser,
+            waiting: state.waiters.len(),
+            total_leases: state.total_leases,
+            recycled_browsers: state.recycled_browsers,
+            crashed_browsers: state.crashed_browsers,
+        }
+    }
+
+    /// Stops leasing pages and closes all browsers.
+    ///
+    /// Browsers with leased pages are closed once their last page is
+    /// released.
+    pub async fn close(&self) {
+        let idle = {
+            let mut state = self.inner.state();
+            state.closed = true;
+            for slot in state.slots.iter_mut() {
+                slot.retiring = true;
+            }
+            while let Some(tx) = state.waiters.pop_front() {
+                let _ = tx.send(());
+            }
+            state.take_retired()
+        };
+        for browser in idle {
+            close_browser(browser).await;
+        }
+    }
+
+    /// Launches a new browser and drives its handler in the background
+    async fn launch(&self) -> Result<Slot> {
+        let (browser, mut handler) = (self.inner.launcher)().await?;
+        let alive = Arc::new(AtomicBool::new(true));
+        let id = {
+            let mut state = self.inner.state();
+            state.next_id += 1;
+            state.next_id
+        };
+
+        let flag = Arc::clone(&alive);
+        let pool = Arc::downgrade(&self.inner);
+        utils::spawn(async move {
+            while handler.next().await.is_some() {}
+            flag.store(false, Ordering::Release);
+            if let Some(pool) = Weak::upgrade(&pool) {
+                pool.state().reap();
+            }
+        });
+
+        Ok(Slot {
+            id,
+            browser: Arc::new(browser),
+            alive,
+            leased: 0,
+            served: 0,
+            retiring: false,
+        })
+    }
+
+    /// Opens a new page on the browser of the slot, which already counts the
+    /// lease
+    async fn open_page(&self, id: usize, browser: Arc<Browser>) -> Result<PageLease> {
+        let url = self.inner.config.url.as_str();
+        let opened = if self.inner.config.context_per_lease {
+            match browser
+                .new_browser_context(BrowserContextOptions::default())
+                .await
+            {
+                Ok(context) => match context.new_page(url).await {
+                    Ok(page) => Ok((page, Some(context))),
+                    Err(err) => {
+                        let _ = context.close().await;
+                        Err(err)
+                    }
+                },
+                Err(err) => Err(err),
+            }
+        } else {
+            browser.new_page(url).await.map(|page| (page, None))
+        };
+        drop(browser);
+
+        match opened {
+            Ok((page, context)) => Ok(PageLease {
+                page: Some(page),
+                context,
+                slot: id,
+                pool: Arc::clone(&self.inner),
+            }),
+            Err(err) => {
+                self.inner.release(id, false);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for BrowserPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrowserPool")
+            .field("config", &self.inner.config)
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+/// A page leased from a [`BrowserPool`].
+///
+/// Dereferences to the [`Page`]. The page is closed and its slot returned to
+/// the pool on [`PageLease::release`] or, in the background, when the lease
+/// is dropped.
+pub struct PageLease {
+    page: Option<Page>,
+    /// The context created for this lease
+    context: Option<BrowserContext>,
+    slot: usize,
+    pool: Arc<Inner>,
+}
+
+impl PageLease {
+    /// The leased page
+    pub fn page(&self) -> &Page {
+        self.page.as_ref().expect("page is only taken on release")
+    }
+
+    /// The isolated context of the page, if the pool is configured with
+    /// `context_per_lease`
+    pub fn context(&self) -> Option<&BrowserContext> {
+        self.context.as_ref()
+    }
+
+    /// Closes the page and returns its slot to the pool
+    pub async fn release(mut self) {
+        if let Some(page) = self.page.take() {
+            release(Arc::clone(&self.pool), self.slot, page, self.context.take()).await;
+        }
+    }
+}
+
+impl Deref for PageLease {
+    type Target = Page;
+
+    fn deref(&self) -> &Self::Target {
+        self.page()
+    }
+}
+
+impl Drop for PageLease {
+    fn drop(&mut self) {
+        if let Some(page) = self.page.take() {
+            let released = utils::try_spawn(release(
+                Arc::clone(&self.pool),
+                self.slot,
+                page,
+                self.context.take(),
+            ));
+            if !released {
+                // without a runtime the page can't be closed, return at least
+                // its slot
+                drop(self.pool.release(self.slot, false));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PageLease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageLease")
+            .field("page", &self.page)
+            .field("context", &self.context)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
+/// Closes a leased page and returns its slot
+async fn release(pool: Arc<Inner>, slot: usize, page: Page, context: Option<BrowserContext>) {
+    let mut recycle = false;
+    if let Some(max_heap_size) = pool.config.max_heap_size {
+        if let Ok(usage) = page.execute(GetHeapUsageParams::default()).await {
+            recycle = usage.result.used_size > max_heap_size as f64;
+        }
+    }
+    match context {
+        // closes all pages of the context
+        Some(context) => {
+            let _ = context.close().await;
+        }
+        None => {
+            let _ = page.close().await;
+        }
+    }
+    let retired = pool.release(slot, recycle);
+    for browser in retired {
+        close_browser(browser).await;
+    }
+}
+
+/// Closes a browser that was removed from the pool
+async fn close_browser(browser: Arc<Browser>) {
+    if let Ok(mut browser) = Arc::try_unwrap(browser) {
+        if browser.close().await.is_ok() {
+            let _ = browser.wait().await;
+        } else {
+            let _ = browser.kill().await;
+        }
+    }
+}
+
+struct Inner {
+    config: PoolConfig,
+    launcher: Launcher,
+    state: Mutex<PoolState>,
+}
+
+impl Inner {
+    fn state(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Picks the least busy browser with room for another page
+    fn next_lease(&self, state: &mut PoolState) -> NextLease {
+        state.reap();
+        let config = &self.config;
+        if let Some(slot) = state
+            .slots
+            .iter_mut()
+            .filter(|slot| slot.is_available(config))
+            .min_by_key(|slot| slot.leased)
+        {
+            slot.leased += 1;
+            slot.served += 1;
+            if matches!(config.recycle_after_pages, Some(max) if slot.served >= max) {
+                slot.retiring = true;
+            }
+            state.total_leases += 1;
+            let (id, browser) = (slot.id, Arc::clone(&slot.browser));
+            return NextLease::Slot(id, browser);
+        }
+
+        let running = state.slots.iter().filter(|slot| !slot.retiring).count();
+        if running + state.launching < config.max_browsers {
+            state.launching += 1;
+            return NextLease::Launch;
+        }
+
+        let (tx, rx) = oneshot::channel();
+        state.waiters.push_back(tx);
+        NextLease::Wait(rx)
+    }
+
+    /// Returns the slot of a released page, returns the browsers to close
+    fn release(&self, id: usize, recycle: bool) -> Vec<Arc<Browser>> {
+        let mut state = self.state();
+        if let Some(slot) = state.slots.iter_mut().find(|slot| slot.id == id) {
+            slot.leased -= 1;
+            slot.retiring |= recycle;
+        }
+        let retired = state.take_retired();
+        state.wake_one();
+        retired
+    }
+}
+
+/// What [`Inner::next_lease`] reserved for a lease
+enum Reserved {
+    /// A page on the browser of this slot
+    Slot(usize),
+    /// A browser to launch
+    Launch,
+}
+
+/// Undoes a reservation of [`Inner::next_lease`] if the `lease` future is
+/// dropped before the reservation was used
+struct Reservation<'a> {
+    pool: &'a Inner,
+    reserved: Option<Reserved>,
+}
+
+impl<'a> Reservation<'a> {
+    fn new(pool: &'a Inner, reserved: Reserved) -> Self {
+        Self {
+            pool,
+            reserved: Some(reserved),
+        }
+    }
+
+    /// The reservation was used, or already undone
+    fn disarm(mut self) {
+        self.reserved = None;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let Some(reserved) = self.reserved.take() else {
+            return;
+        };
+        let mut state = self.pool.state();
+        match reserved {
+            Reserved::Slot(id) => {
+                if let Some(slot) = state.slots.iter_mut().find(|slot| slot.id == id) {
+                    slot.leased -= 1;
+                    slot.served -= 1;
+                }
+                state.total_leases -= 1;
+            }
+            Reserved::Launch => state.launching -= 1,
+        }
+        state.reap();
+        state.wake_one();
+    }
+}
+
+enum NextLease {
+    /// Open the page on this browser
+    Slot(usize, Arc<Browser>),
+    /// Launch a new browser first
+    Launch,
+    /// Wait until a page was released
+    Wait(oneshot::Receiver<()>),
+}
+
+#[derive(Default)]
+struct PoolState {
+    slots: Vec<Slot>,
+    /// Browsers currently being launched
+    launching: usize,
+    next_id: usize,
+    /// Callers waiting for a slot
+    waiters: VecDeque<oneshot::Sender<()>>,
+    closed: bool,
+    total_leases: u64,
+    recycled_browsers: u64,
+    crashed_browsers: u64,
+}
+
+impl PoolState {
+    /// Wakes the next caller waiting for a slot
+    fn wake_one(&mut self) {
+        while let Some(tx) = self.waiters.pop_front() {
+            if tx.send(()).is_ok() {
+                break;
+            }
+        }
+    }
+
+    /// Marks crashed browsers for recycling and closes the idle ones in the
+    /// background
+    fn reap(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if !slot.alive.load(Ordering::Acquire) {
+                slot.retiring = true;
+            }
+        }
+        let retired = self.take_retired();
+        if !retired.is_empty() {
+            self.wake_one();
+        }
+        for browser in retired {
+            utils::try_spawn(close_browser(browser));
+        }
+    }
+
+    /// Removes all browsers that are marked for recycling and have no leased
+    /// pages anymore
+    fn take_retired(&mut self) -> Vec<Arc<Browser>> {
+        let mut retired = Vec::new();
+        let mut n = 0;
+        while n < self.slots.len() {
+            if self.slots[n].retiring && self.slots[n].leased == 0 {
+                let slot = self.slots.swap_remove(n);
+                if slot.alive.load(Ordering::Acquire) {
+                    self.recycled_browsers += 1;
+                } else {
+                    self.crashed_browsers += 1;
+                }
+                retired.push(slot.browser);
+            } else {
+                n += 1;
+            }
+        }
+        retired
+    }
+}
+
+/// A browser managed by the pool
+struct Slot {
+    id: usize,
+    browser: Arc<Browser>,
+    /// Whether the handler of the browser is still running
+    alive: Arc<AtomicBool>,
+    /// Pages currently leased
+    leased: usize,
+    /// Pages leased in total
+    served: usize,
+    /// Whether the browser is closed once its last page is released
+    retiring: bool,
+}
+
+impl Slot {
+    fn is_available(&self, config: &PoolConfig) -> bool {
+        !self.retiring
+            && self.alive.load(Ordering::Acquire)
+            && self.leased < config.max_pages_per_browser
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::handler::HandlerConfig;
+    use crate::testing::{MockBrowser, MockReply};
+
+    fn pool(mock: &Arc<MockBrowser>, config: PoolConfig) -> BrowserPool {
+        let mock = Arc::clone(mock);
+        BrowserPool::with_launcher(config, move || {
+            Browser::connect_with_config(mock.ws_url(), HandlerConfig::default())
+        })
+    }
+
+    #[async_std::test]
+    async fn leases_wait_for_capacity() {
+        let mock = Arc::new(MockBrowser::start().await.unwrap());
+        let pool = pool(
+            &mock,
+            PoolConfig::builder()
+                .max_browsers(1)
+                .max_pages_per_browser(2)
+                .build(),
+        );
+
+        let first = pool.lease().await.unwrap();
+        let _second = pool.lease().await.unwrap();
+        assert_eq!(pool.metrics().leased_pages, 2);
+
+        let waiting = pool.clone();
+        let third = async_std::task::spawn(async move { waiting.lease().await });
+        async_std::task::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pool.metrics().waiting, 1);
+
+        first.release().await;
+        third.await.unwrap();
+        let metrics = pool.metrics();
+        assert_eq!(metrics.browsers, 1);
+        assert_eq!(metrics.leased_pages, 2);
+        assert_eq!(metrics.total_leases, 3);
+    }
+
+    #[async_std::test]
+    async fn cancelled_leases_return_capacity() {
+        let launching = BrowserPool::with_launcher(
+            PoolConfig::builder().max_browsers(1).build(),
+            futures::future::pending,
+        );
+        let lease = async_std::future::timeout(Duration::from_millis(50), launching.lease());
+        assert!(lease.await.is_err());
+        assert_eq!(launching.metrics().launching, 0);
+
+        let mock = Arc::new(
+            MockBrowser::builder()
+                .respond_with("Target.createTarget", |_| MockReply::none())
+                .start()
+                .await
+                .unwrap(),
+        );
+        let pool = pool(
+            &mock,
+            PoolConfig::builder()
+                .max_browsers(1)
+                .max_pages_per_browser(1)
+                .build(),
+        );
+        let lease = async_std::future::timeout(Duration::from_millis(200), pool.lease());
+        assert!(lease.await.is_err());
+        let metrics = pool.metrics();
+        assert_eq!(metrics.browsers, 1);
+        assert_eq!(metrics.leased_pages, 0);
+        assert_eq!(metrics.total_leases, 0);
+    }
+
+    #[async_std::test]
+    async fn recycles_browsers_after_page_limit() {
+        let mock = Arc::new(MockBrowser::start().await.unwrap());
+        let pool = pool(
+            &mock,
+            PoolConfig::builder()
+                .max_browsers(1)
+                .recycle_after_pages(2)
+                .build(),
+        );
+
+        for _ in 0..3 {
+            pool.lease().await.unwrap().release().await;
+        }
+        let metrics = pool.metrics();
+        assert_eq!(metrics.recycled_browsers, 1);
+        assert_eq!(metrics.browsers, 1);
+        assert_eq!(metrics.leased_pages, 0);
+    }
+}
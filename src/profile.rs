@@ -0,0 +1,121 @@
+//! Temporary user data directories of launched browsers
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The user data directory created for a single launch of a browser.
+///
+/// The directory is removed when dropped, unless it should be kept.
+#[derive(Debug)]
+pub(crate) struct TempUserDataDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl TempUserDataDir {
+    /// Creates a new directory in the temp dir of the system, seeded with a
+    /// copy of `template`.
+    ///
+    /// On unix only the current user can access the directory, it holds the
+    /// cookies and sessions of the browser.
+    pub fn create(template: Option<&Path>, keep: bool) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        let path = loop {
+            let path = std::env::temp_dir().join(format!(
+                "chromiumoxide-runner-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match builder.create(&path) {
+                Ok(()) => break path,
+                // left behind by an earlier process with the same id
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        };
+        // removes the directory again if the template can't be copied
+        let dir = Self { path, keep };
+        if let Some(template) = template {
+            copy_dir(template, &dir.path)?;
+        }
+        Ok(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempUserDataDir {
+    fn drop(&mut self) {
+        if self.keep {
+            tracing::info!("Keeping the user data dir {}", self.path.display());
+        } else if let Err(err) = fs::remove_dir_all(&self.path) {
+            tracing::warn!(
+                "Failed to remove the user data dir {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Copies the files and directories of `from` into `to`.
+///
+/// Symlinks are skipped, a profile links its lock files, which must not be
+/// shared by the copies.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_and_removes_dirs() {
+        let template = TempUserDataDir::create(None, false).unwrap();
+        fs::create_dir(template.path().join("Default")).unwrap();
+        fs::write(template.path().join("Default").join("Preferences"), "{}").unwrap();
+
+        let dir = TempUserDataDir::create(Some(template.path()), false).unwrap();
+        assert_ne!(dir.path(), template.path());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let preferences = dir.path().join("Default").join("Preferences");
+        assert_eq!(fs::read_to_string(preferences).unwrap(), "{}");
+
+        let path = dir.path().to_path_buf();
+        drop(dir);
+        assert!(!path.exists());
+
+        let kept = TempUserDataDir::create(None, true).unwrap();
+        let path = kept.path().to_path_buf();
+        drop(kept);
+        assert!(path.exists());
+        fs::remove_dir(path).unwrap();
+    }
+}
@@ -0,0 +1,891 @@
+//! A scriptable fake browser to test code built on chromiumoxide without a
+//! chromium instance.
+//!
+//! [`MockBrowser`] serves the `/json/version` endpoint and a CDP websocket.
+//! By default it simulates just enough of a browser to create pages, navigate
+//! them and evaluate scripts; every command can be answered with a canned or
+//! closure-generated [`MockReply`] instead, which may also emit events.
+//!
+//! This module is available with the `testing` feature.
+//!
+//! ```no_run
+//! # use chromiumoxide::browser::Browser;
+//! # use chromiumoxide::testing::{MockBrowser, MockEvent, MockReply};
+//! # use futures::StreamExt;
+//! # async fn demo() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mock = MockBrowser::builder()
+//!         .respond_with("Runtime.evaluate", |call| {
+//!             MockReply::ok(serde_json::json!({
+//!                 "result": { "type": "number", "value": 42 }
+//!             }))
+//!             .event(
+//!                 MockEvent::new("Runtime.consoleAPICalled", serde_json::json!({}))
+//!                     .session_id(call.session_id.clone()),
+//!             )
+//!         })
+//!         .start()
+//!         .await?;
+//!
+//!     let (browser, mut handler) = Browser::connect(mock.ws_url()).await?;
+//!     async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+//!     let page = browser.new_page("https://example.com").await?;
+//!     let answer: u32 = page.evaluate("6 * 7").await?.into_value()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use async_tungstenite::tungstenite::handshake::derive_accept_key;
+use async_tungstenite::tungstenite::protocol::Role;
+use async_tungstenite::tungstenite::Message as WsMessage;
+use async_tungstenite::WebSocketStream;
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::channel::oneshot;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::{FutureExt, SinkExt, StreamExt};
+use serde_json::{json, Value};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "async-std-runtime")] {
+        use async_std::net::TcpListener;
+        use async_std::task::spawn;
+    } else if #[cfg(feature = "tokio-runtime")] {
+        use async_tungstenite::tokio::TokioAdapter;
+        use tokio::net::TcpListener;
+        use tokio::spawn;
+    }
+}
+
+type CallHandler = Box<dyn Fn(&MockCall) -> MockReply + Send + Sync>;
+
+/// A command received by the [`MockBrowser`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    /// The identifier of the command
+    pub id: usize,
+    /// The method, like `Page.navigate`
+    pub method: String,
+    /// The session the command was sent to, `None` for the browser
+    pub session_id: Option<String>,
+    /// The params of the command
+    pub params: Value,
+}
+
+/// An event emitted by the [`MockBrowser`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockEvent {
+    /// The method of the event, like `Page.loadEventFired`
+    pub method: String,
+    /// The params of the event
+    pub params: Value,
+    /// The session to emit the event in, `None` for the browser
+    pub session_id: Option<String>,
+}
+
+impl MockEvent {
+    pub fn new(method: impl Into<String>, params: Value) -> Self {
+        Self {
+            method: method.into(),
+            params,
+            session_id: None,
+        }
+    }
+
+    /// Emit the event in this session
+    pub fn session_id(mut self, session_id: Option<String>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    fn to_json(&self) -> String {
+        let mut msg = json!({
+            "method": self.method,
+            "params": self.params,
+        });
+        if let Some(session_id) = self.session_id.as_ref() {
+            msg["sessionId"] = json!(session_id);
+        }
+        msg.to_string()
+    }
+}
+
+/// How the [`MockBrowser`] answers a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockReply {
+    /// The result or the error to respond with, `None` to never respond
+    pub response: Option<Result<Value, chromiumoxide_types::Error>>,
+    /// Events emitted before the response
+    pub events_before: Vec<MockEvent>,
+    /// Events emitted after the response
+    pub events: Vec<MockEvent>,
+}
+
+impl MockReply {
+    /// Respond with this result
+    pub fn ok(result: Value) -> Self {
+        Self {
+            response: Some(Ok(result)),
+            events_before: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Respond with an error
+    pub fn error(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            response: Some(Err(chromiumoxide_types::Error {
+                code,
+                message: message.into(),
+            })),
+            events_before: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Never respond, useful to test timeouts
+    pub fn none() -> Self {
+        Self {
+            response: None,
+            events_before: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Emit this event after the response
+    pub fn event(mut self, event: MockEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Emit this event before the response
+    pub fn event_before(mut self, event: MockEvent) -> Self {
+        self.events_before.push(event);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct MockBrowserBuilder {
+    handlers: HashMap<String, CallHandler>,
+    websocket_only: bool,
+}
+
+impl MockBrowserBuilder {
+    /// Answer all commands with this `method` with `result`
+    pub fn respond(self, method: impl Into<String>, result: Value) -> Self {
+        self.respond_with(method, move |_| MockReply::ok(result.clone()))
+    }
+
+    /// Answer all commands with this `method` with the reply of `f`
+    pub fn respond_with<F>(mut self, method: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(&MockCall) -> MockReply + Send + Sync + 'static,
+    {
+        self.handlers.insert(method.into(), Box::new(f));
+        self
+    }
+
+    /// Don't serve `/json/version`, like hosts that only expose the
+    /// websockets of pages
+    pub fn websocket_only(mut self) -> Self {
+        self.websocket_only = true;
+        self
+    }
+
+    /// Starts the server on a random local port
+    pub async fn start(self) -> io::Result<MockBrowser> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            addr,
+            handlers: self.handlers,
+            websocket_only: self.websocket_only,
+            state: Default::default(),
+        });
+        let (shutdown, mut shutdown_rx) = oneshot::channel::<()>();
+
+        let server = Arc::clone(&shared);
+        spawn(async move {
+            loop {
+                futures::select! {
+                    _ = shutdown_rx => break,
+                    conn = listener.accept().fuse() => {
+                        let Ok((stream, _)) = conn else { break };
+                        cfg_if::cfg_if! {
+                            if #[cfg(feature = "tokio-runtime")] {
+                                let stream = TokioAdapter::new(stream);
+                            }
+                        }
+                        spawn(handle_connection(stream, Arc::clone(&server)));
+                    }
+                }
+            }
+        });
+
+        Ok(MockBrowser {
+            shared,
+            _shutdown: shutdown,
+        })
+    }
+}
+
+impl fmt::Debug for MockBrowserBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockBrowserBuilder")
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("websocket_only", &self.websocket_only)
+            .finish()
+    }
+}
+
+/// A fake browser serving the devtools endpoints on a local port.
+///
+/// The server shuts down when this is dropped.
+#[derive(Debug)]
+pub struct MockBrowser {
+    shared: Arc<Shared>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockBrowser {
+    pub fn builder() -> MockBrowserBuilder {
+        MockBrowserBuilder::default()
+    }
+
+    /// Starts a server that answers all commands with the default behavior
+    pub async fn start() -> io::Result<Self> {
+        Self::builder().start().await
+    }
+
+    /// The address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.shared.addr
+    }
+
+    /// The http url of the server, to pass to [`crate::Browser::connect`]
+    pub fn url(&self) -> String {
+        format!("http://{}", self.shared.addr)
+    }
+
+    /// The url of the CDP websocket
+    pub fn ws_url(&self) -> String {
+        self.shared.ws_url()
+    }
+
+    /// The url of the page-level websocket of a target, which is served like
+    /// the websocket of the browser
+    pub fn page_ws_url(&self, target_id: impl AsRef<str>) -> String {
+        format!(
+            "ws://{}/devtools/page/{}",
+            self.shared.addr,
+            target_id.as_ref()
+        )
+    }
+
+    /// All commands received so far
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.shared.state.lock().unwrap().calls.clone()
+    }
+
+    /// Emits the event to all connected clients
+    pub fn emit(&self, event: MockEvent) {
+        let msg = event.to_json();
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .clients
+            .retain(|client| client.unbounded_send(msg.clone()).is_ok());
+    }
+}
+
+struct Shared {
+    addr: SocketAddr,
+    handlers: HashMap<String, CallHandler>,
+    websocket_only: bool,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn ws_url(&self) -> String {
+        format!("ws://{}/devtools/browser/mock", self.addr)
+    }
+}
+
+impl fmt::Debug for Shared {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shared")
+            .field("addr", &self.addr)
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("websocket_only", &self.websocket_only)
+            .finish()
+    }
+}
+
+/// The simulated browser
+#[derive(Debug, Default)]
+struct State {
+    next_id: usize,
+    targets: Vec<MockTarget>,
+    calls: Vec<MockCall>,
+    clients: Vec<UnboundedSender<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct MockTarget {
+    target_id: String,
+    session_id: Option<String>,
+    url: String,
+    loader_id: String,
+    browser_context_id: Option<String>,
+}
+
+impl MockTarget {
+    fn info(&self) -> Value {
+        let mut info = json!({
+            "targetId": self.target_id,
+            "type": "page",
+            "title": self.url,
+            "url": self.url,
+            "attached": self.session_id.is_some(),
+            "canAccessOpener": false,
+        });
+        if let Some(id) = self.browser_context_id.as_ref() {
+            info["browserContextId"] = json!(id);
+        }
+        info
+    }
+
+    /// The main frame, its id is the target id like in chromium
+    fn frame(&self) -> Value {
+        json!({
+            "id": self.target_id,
+            "loaderId": self.loader_id,
+            "url": self.url,
+            "domainAndRegistry": "",
+            "securityOrigin": self.url,
+            "mimeType": "text/html",
+            "secureContextType": "Secure",
+            "crossOriginIsolatedContextType": "NotIsolated",
+            "gatedAPIFeatures": [],
+        })
+    }
+
+    /// The lifecycle events of a completed load of the main frame
+    fn load_events(&self) -> Vec<MockEvent> {
+        let session_id = self.session_id.clone();
+        let mut events: Vec<_> = ["init", "DOMContentLoaded", "load"]
+            .into_iter()
+            .map(|name| {
+                MockEvent::new(
+                    "Page.lifecycleEvent",
+                    json!({
+                        "frameId": self.target_id,
+                        "loaderId": self.loader_id,
+                        "name": name,
+                        "timestamp": 0.0,
+                    }),
+                )
+                .session_id(session_id.clone())
+            })
+            .collect();
+        events.push(
+            MockEvent::new(
+                "Page.frameStoppedLoading",
+                json!({ "frameId": self.target_id }),
+            )
+            .session_id(session_id),
+        );
+        events
+    }
+}
+
+impl State {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}-{}", self.next_id)
+    }
+
+    fn target_by_session(&mut self, session_id: Option<&String>) -> Option<&mut MockTarget> {
+        let session_id = session_id?;
+        self.targets
+            .iter_mut()
+            .find(|t| t.session_id.as_ref() == Some(session_id))
+    }
+
+    /// Answers the call like a browser would
+    fn default_reply(&mut self, call: &MockCall) -> MockReply {
+        let param = |name: &str| call.params[name].as_str().map(str::to_string);
+        match call.method.as_str() {
+            "Browser.getVersion" => MockReply::ok(json!({
+                "protocolVersion": "1.3",
+                "product": "MockBrowser/1.0",
+                "revision": "0",
+                "userAgent": "MockBrowser",
+                "jsVersion": "0",
+            })),
+            "Target.createTarget" => {
+                let target = MockTarget {
+                    target_id: self.next_id("TARGET"),
+                    session_id: None,
+                    url: param("url").unwrap_or_else(|| "about:blank".to_string()),
+                    loader_id: self.next_id("LOADER"),
+                    browser_context_id: param("browserContextId"),
+                };
+                let reply = MockReply::ok(json!({ "targetId": target.target_id })).event_before(
+                    MockEvent::new(
+                        "Target.targetCreated",
+                        json!({ "targetInfo": target.info() }),
+                    ),
+                );
+                self.targets.push(target);
+                reply
+            }
+            "Target.attachToTarget" => {
+                let session_id = self.next_id("SESSION");
+                let target_id = param("targetId");
+                match self
+                    .targets
+                    .iter_mut()
+                    .find(|t| Some(&t.target_id) == target_id.as_ref())
+                {
+                    Some(target) => {
+                        target.session_id = Some(session_id.clone());
+                        MockReply::ok(json!({ "sessionId": session_id })).event_before(
+                            MockEvent::new(
+                                "Target.attachedToTarget",
+                                json!({
+                                    "sessionId": session_id,
+                                    "targetInfo": target.info(),
+                                    "waitingForDebugger": false,
+                                }),
+                            ),
+                        )
+                    }
+                    None => MockReply::error(-32602, "No target with given id found"),
+                }
+            }
+            "Target.closeTarget" => {
+                let target_id = param("targetId");
+                self.targets
+                    .retain(|t| Some(&t.target_id) != target_id.as_ref());
+                MockReply::ok(json!({ "success": true })).event(MockEvent::new(
+                    "Target.targetDestroyed",
+                    json!({ "targetId": target_id }),
+                ))
+            }
+            "Target.setDiscoverTargets" => {
+                let mut reply = MockReply::ok(json!({}));
+                if call.params["discover"] == true {
+                    // like chromium, announce the targets that already exist
+                    reply.events = self
+                        .targets
+                        .iter()
+                        .map(|target| {
+                            MockEvent::new(
+                                "Target.targetCreated",
+                                json!({ "targetInfo": target.info() }),
+                            )
+                        })
+                        .collect();
+                }
+                reply
+            }
+            "Target.getTargets" => MockReply::ok(json!({
+                "targetInfos": self.targets.iter().map(MockTarget::info).collect::<Vec<_>>(),
+            })),
+            "Target.createBrowserContext" => {
+                MockReply::ok(json!({ "browserContextId": self.next_id("CONTEXT") }))
+            }
+            "Target.disposeBrowserContext" => {
+                let context_id = param("browserContextId");
+                let mut reply = MockReply::ok(json!({}));
+                self.targets.retain(|t| {
+                    if t.browser_context_id != context_id {
+                        return true;
+                    }
+                    reply.events.push(MockEvent::new(
+                        "Target.targetDestroyed",
+                        json!({ "targetId": t.target_id }),
+                    ));
+                    false
+                });
+                reply
+            }
+            "Page.getFrameTree" => match self.target_by_session(call.session_id.as_ref()) {
+                Some(target) => {
+                    let mut reply = MockReply::ok(json!({
+                        "frameTree": { "frame": target.frame() }
+                    }));
+                    reply.events = target.load_events();
+                    reply
+                }
+                None => MockReply::ok(json!({})),
+            },
+            "Page.navigate" => {
+                let url = param("url").unwrap_or_default();
+                let loader_id = self.next_id("LOADER");
+                match self.target_by_session(call.session_id.as_ref()) {
+                    Some(target) => {
+                        target.url = url;
+                        target.loader_id = loader_id;
+                        let mut reply = MockReply::ok(json!({
+                            "frameId": target.target_id,
+                            "loaderId": target.loader_id,
+                        }))
+                        .event(
+                            MockEvent::new(
+                                "Page.frameNavigated",
+                                json!({ "frame": target.frame(), "type": "Navigation" }),
+                            )
+                            .session_id(target.session_id.clone()),
+                        );
+                        reply.events.extend(target.load_events());
+                        reply
+                    }
+                    None => MockReply::error(-32000, "No target for session"),
+                }
+            }
+            "Page.createIsolatedWorld" => {
+                self.next_id += 1;
+                MockReply::ok(json!({ "executionContextId": self.next_id }))
+            }
+            "Page.addScriptToEvaluateOnNewDocument" => {
+                MockReply::ok(json!({ "identifier": self.next_id("SCRIPT") }))
+            }
+            "Runtime.evaluate" | "Runtime.callFunctionOn" => {
+                MockReply::ok(json!({ "result": { "type": "undefined" } }))
+            }
+            _ => MockReply::ok(json!({})),
+        }
+    }
+}
+
+/// Serves a single http request or websocket connection
+async fn handle_connection<S>(mut stream: S, shared: Arc<Shared>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let Ok(head) = read_head(&mut stream).await else {
+        return;
+    };
+    let mut lines = head.lines();
+    let path = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default()
+        .to_string();
+    let headers: HashMap<_, _> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    if let Some(key) = headers.get("sec-websocket-key") {
+        let resp = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        if stream.write_all(resp.as_bytes()).await.is_err() {
+            return;
+        }
+        let ws = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+        serve_cdp(ws, shared).await;
+    } else if !shared.websocket_only && path.trim_end_matches('/') == "/json/version" {
+        let body = json!({
+            "Browser": "MockBrowser/1.0",
+            "Protocol-Version": "1.3",
+            "User-Agent": "MockBrowser",
+            "V8-Version": "0",
+            "WebKit-Version": "0",
+            "webSocketDebuggerUrl": shared.ws_url(),
+        })
+        .to_string();
+        let resp = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = stream.write_all(resp.as_bytes()).await;
+        let _ = stream.close().await;
+    } else {
+        let _ = stream
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await;
+        let _ = stream.close().await;
+    }
+}
+
+/// Reads the head of a http request
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 || head.len() > 16 * 1024 {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        head.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Answers the CDP commands of a websocket client
+async fn serve_cdp<S>(ws: WebSocketStream<S>, shared: Arc<Shared>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sink, mut stream) = ws.split();
+    let (tx, mut rx) = unbounded::<String>();
+    shared.state.lock().unwrap().clients.push(tx.clone());
+
+    spawn(async move {
+        while let Some(msg) = rx.next().await {
+            if sink.send(WsMessage::Text(msg)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(msg)) = stream.next().await {
+        let WsMessage::Text(text) = msg else {
+            continue;
+        };
+        let Ok(value) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        let call = MockCall {
+            id: value["id"].as_u64().unwrap_or_default() as usize,
+            method: value["method"].as_str().unwrap_or_default().to_string(),
+            session_id: value["sessionId"].as_str().map(str::to_string),
+            params: value.get("params").cloned().unwrap_or_else(|| json!({})),
+        };
+
+        shared.state.lock().unwrap().calls.push(call.clone());
+        // handlers may use the `MockBrowser`, which takes the lock itself
+        let reply = match shared.handlers.get(&call.method) {
+            Some(handler) => handler(&call),
+            None => shared.state.lock().unwrap().default_reply(&call),
+        };
+
+        for event in reply.events_before {
+            let _ = tx.unbounded_send(event.to_json());
+        }
+        if let Some(response) = reply.response {
+            let mut msg = match response {
+                Ok(result) => json!({ "id": call.id, "result": result }),
+                Err(err) => json!({ "id": call.id, "error": err }),
+            };
+            if let Some(session_id) = call.session_id.as_ref() {
+                msg["sessionId"] = json!(session_id);
+            }
+            let _ = tx.unbounded_send(msg.to_string());
+        }
+        for event in reply.events {
+            let _ = tx.unbounded_send(event.to_json());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::auth::Credentials;
+    use crate::browser::Browser;
+    use crate::error::CdpError;
+    use crate::handler::HandlerConfig;
+
+    async fn connect(mock: &MockBrowser, config: HandlerConfig) -> Browser {
+        let (browser, mut handler) = Browser::connect_with_config(mock.ws_url(), config)
+            .await
+            .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+        browser
+    }
+
+    #[async_std::test]
+    async fn create_and_navigate_page() {
+        let mock = MockBrowser::builder()
+            .respond(
+                "Runtime.evaluate",
+                json!({ "result": { "type": "number", "value": 42 } }),
+            )
+            .start()
+            .await
+            .unwrap();
+        let browser = connect(&mock, HandlerConfig::default()).await;
+
+        let page = browser.new_page("https://example.com").await.unwrap();
+        page.goto("https://example.org").await.unwrap();
+        assert_eq!(
+            page.url().await.unwrap().as_deref(),
+            Some("https://example.org")
+        );
+
+        let answer: u32 = page.evaluate("6 * 7").await.unwrap().into_value().unwrap();
+        assert_eq!(answer, 42);
+        assert!(mock
+            .calls()
+            .iter()
+            .any(|call| call.method == "Page.navigate"));
+    }
+
+    #[async_std::test]
+    async fn unanswered_command_times_out() {
+        let mock = MockBrowser::builder()
+            .respond_with("Browser.getVersion", |_| MockReply::none())
+            .start()
+            .await
+            .unwrap();
+        let config = HandlerConfig {
+            request_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let browser = connect(&mock, config).await;
+
+        let err = browser.version().await.unwrap_err();
+        assert!(matches!(err, CdpError::Timeout), "{err:?}");
+    }
+
+    #[async_std::test]
+    async fn command_timeout_overrides_request_timeout() {
+        use chromiumoxide_cdp::cdp::browser_protocol::page::PrintToPdfParams;
+
+        use crate::cmd::CommandOptions;
+
+        let mock = MockBrowser::builder()
+            .respond_with("Page.printToPDF", |_| MockReply::none())
+            .start()
+            .await
+            .unwrap();
+        let browser = connect(&mock, HandlerConfig::default()).await;
+        let page = browser.new_page("https://example.com").await.unwrap();
+
+        let res = page
+            .execute_with(
+                PrintToPdfParams::default(),
+                CommandOptions::timeout(Duration::from_millis(100)),
+            )
+            .await;
+        let err = res.err().unwrap();
+        assert!(matches!(err, CdpError::Timeout), "{err:?}");
+        // the page is still usable
+        page.url().await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn network_settings_are_sent_before_later_commands() {
+        let mock = MockBrowser::start().await.unwrap();
+        let browser = connect(&mock, HandlerConfig::default()).await;
+        let page = browser.new_page("https://example.com").await.unwrap();
+
+        page.set_extra_http_headers([("x-test", "1")])
+            .await
+            .unwrap();
+        page.authenticate(Credentials {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        })
+        .await
+        .unwrap();
+        page.evaluate("1").await.unwrap();
+
+        let methods: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .map(|call| call.method)
+            .skip_while(|method| method != "Network.setExtraHTTPHeaders")
+            .collect();
+        let position = |name: &str| methods.iter().position(|method| method == name);
+        assert_eq!(position("Network.setExtraHTTPHeaders"), Some(0));
+        assert!(position("Fetch.enable").unwrap() < position("Runtime.evaluate").unwrap());
+    }
+
+    #[async_std::test]
+    async fn handlers_can_use_the_mock() {
+        let mock = Arc::new(std::sync::OnceLock::<MockBrowser>::new());
+        let handler_mock = Arc::clone(&mock);
+        let started = MockBrowser::builder()
+            .respond_with("Browser.getVersion", move |call| {
+                let mock = handler_mock.get().unwrap();
+                mock.emit(MockEvent::new(
+                    "Target.targetDestroyed",
+                    json!({ "targetId": "unknown" }),
+                ));
+                assert_eq!(mock.calls().last(), Some(call));
+                MockReply::ok(json!({
+                    "protocolVersion": "1.3",
+                    "product": "MockBrowser/1.0",
+                    "revision": "mock",
+                    "userAgent": "MockBrowser",
+                    "jsVersion": "1.0",
+                }))
+            })
+            .start()
+            .await
+            .unwrap();
+        mock.set(started).unwrap();
+        let browser = connect(mock.get().unwrap(), HandlerConfig::default()).await;
+
+        browser.version().await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn connect_over_a_page_websocket() {
+        let mock = MockBrowser::builder()
+            .websocket_only()
+            .start()
+            .await
+            .unwrap();
+        let page = connect(&mock, HandlerConfig::default())
+            .await
+            .new_page("https://example.com")
+            .await
+            .unwrap();
+
+        let page_ws_url = mock.page_ws_url(page.target_id());
+        let (browser, mut handler, target_id) =
+            Browser::connect_to_target(page_ws_url.clone(), HandlerConfig::default())
+                .await
+                .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+        assert_eq!(&target_id, page.target_id());
+        assert_eq!(browser.websocket_address(), &page_ws_url);
+
+        let mut page = None;
+        for _ in 0..100 {
+            if let Ok(p) = browser.get_page(target_id.clone()).await {
+                page = Some(p);
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+        let page = page.expect("the target of the websocket is attached");
+        assert_eq!(
+            page.url().await.unwrap().as_deref(),
+            Some("https://example.com")
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_with_a_configured_client() {
+        let mock = MockBrowser::start().await.unwrap();
+        let client = crate::devtools::DevToolsHttpClient::builder(mock.url())
+            .bearer_auth("token")
+            .build()
+            .unwrap();
+        let (browser, mut handler) = Browser::connect_with_client(client, HandlerConfig::default())
+            .await
+            .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+
+        assert_eq!(browser.websocket_address(), &mock.ws_url());
+        browser.version().await.unwrap();
+    }
+}
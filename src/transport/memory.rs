@@ -0,0 +1,182 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::{Sink, Stream, StreamExt};
+
+use chromiumoxide_types::{Message, MethodCall};
+
+use crate::error::{CdpError, Result};
+
+/// Creates a connected pair of in-memory transports.
+///
+/// The [`MemoryTransport`] is handed to
+/// [`Browser::connect_with_transport`](crate::Browser::connect_with_transport),
+/// the [`MemoryPeer`] plays the browser: it receives all commands and answers
+/// them with responses and events.
+pub fn memory_pair<T>() -> (MemoryTransport<T>, MemoryPeer<T>) {
+    let (calls_tx, calls_rx) = unbounded();
+    let (messages_tx, messages_rx) = unbounded();
+    (
+        MemoryTransport {
+            calls: calls_tx,
+            messages: messages_rx,
+        },
+        MemoryPeer {
+            calls: calls_rx,
+            messages: messages_tx,
+        },
+    )
+}
+
+/// The client side of an in-memory transport, see [`memory_pair`]
+#[derive(Debug)]
+pub struct MemoryTransport<T> {
+    calls: UnboundedSender<MethodCall>,
+    messages: UnboundedReceiver<Result<Message<T>>>,
+}
+
+impl<T> Sink<MethodCall> for MemoryTransport<T> {
+    type Error = CdpError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.calls)
+            .poll_ready(cx)
+            .map_err(|err| CdpError::ChannelSendError(err.into()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, call: MethodCall) -> Result<()> {
+        Pin::new(&mut self.calls)
+            .start_send(call)
+            .map_err(|err| CdpError::ChannelSendError(err.into()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.calls)
+            .poll_flush(cx)
+            .map_err(|err| CdpError::ChannelSendError(err.into()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.calls)
+            .poll_close(cx)
+            .map_err(|err| CdpError::ChannelSendError(err.into()))
+    }
+}
+
+impl<T> Stream for MemoryTransport<T> {
+    type Item = Result<Message<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.poll_next_unpin(cx)
+    }
+}
+
+/// The browser side of an in-memory transport, see [`memory_pair`]
+///
+/// Yields every command sent by the client and forwards all messages sent
+/// into it to the client.
+#[derive(Debug)]
+pub struct MemoryPeer<T> {
+    calls: UnboundedReceiver<MethodCall>,
+    messages: UnboundedSender<Result<Message<T>>>,
+}
+
+impl<T> MemoryPeer<T> {
+    /// Sends a message to the client.
+    ///
+    /// Returns `false` if the client is gone.
+    pub fn send(&self, msg: Message<T>) -> bool {
+        self.messages.unbounded_send(Ok(msg)).is_ok()
+    }
+
+    /// Sends an error to the client, as if the transport had failed.
+    ///
+    /// Returns `false` if the client is gone.
+    pub fn send_error(&self, err: CdpError) -> bool {
+        self.messages.unbounded_send(Err(err)).is_ok()
+    }
+}
+
+impl<T> Stream for MemoryPeer<T> {
+    type Item = MethodCall;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.calls.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use chromiumoxide_cdp::cdp::browser_protocol::browser::GetVersionParams;
+    use chromiumoxide_cdp::cdp::CdpEventMessage;
+    use chromiumoxide_types::{Message, Response};
+
+    use super::*;
+    use crate::handler::supervision::LifecycleEvent;
+    use crate::handler::HandlerConfig;
+    use crate::Browser;
+
+    #[async_std::test]
+    async fn connect_with_memory_transport() {
+        let (transport, mut peer) = memory_pair::<CdpEventMessage>();
+        let (browser, mut handler) =
+            Browser::connect_with_transport(transport, HandlerConfig::default())
+                .await
+                .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+
+        async_std::task::spawn(async move {
+            while let Some(call) = peer.next().await {
+                let result = if call.method == GetVersionParams::IDENTIFIER {
+                    serde_json::json!({
+                        "protocolVersion": "1.3",
+                        "product": "Memory/1.0",
+                        "revision": "1",
+                        "userAgent": "memory",
+                        "jsVersion": "1"
+                    })
+                } else {
+                    serde_json::json!({})
+                };
+                peer.send(Message::Response(Response {
+                    id: call.id,
+                    result: Some(result),
+                    error: None,
+                }));
+            }
+        });
+
+        let version = browser.version().await.unwrap();
+        assert_eq!(version.product, "Memory/1.0");
+    }
+
+    #[async_std::test]
+    async fn disconnect_fails_pending_commands() {
+        let (transport, mut peer) = memory_pair::<CdpEventMessage>();
+        let (browser, mut handler) =
+            Browser::connect_with_transport(transport, HandlerConfig::default())
+                .await
+                .unwrap();
+        let handler =
+            async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+        let mut events = browser.lifecycle_events().await.unwrap();
+
+        async_std::task::spawn(async move {
+            // drop the connection as soon as the command arrives
+            while let Some(call) = peer.next().await {
+                if call.method == GetVersionParams::IDENTIFIER {
+                    break;
+                }
+            }
+        });
+
+        let err = browser.version().await.unwrap_err();
+        assert!(matches!(err, CdpError::BrowserDisconnected), "{err:?}");
+        assert_eq!(events.next().await, Some(LifecycleEvent::Disconnected));
+        // without a reconnect policy the handler stops
+        handler.await;
+    }
+}
@@ -0,0 +1,69 @@
+//! The channels a [`Connection`](crate::conn::Connection) exchanges messages
+//! with the browser over.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::{Sink, Stream};
+
+use chromiumoxide_types::{EventMessage, Message, MethodCall};
+
+use crate::error::{CdpError, Result};
+
+pub use self::memory::{memory_pair, MemoryPeer, MemoryTransport};
+#[cfg(unix)]
+pub use self::pipe::PipeTransport;
+#[cfg(unix)]
+pub(crate) use self::pipe::{spawn_lock, BrowserPipes};
+pub use self::record::{
+    read_recording, Direction, RecordEvent, RecordedMessage, Recorder, ReplayTransport,
+};
+pub use self::ws::WsTransport;
+
+mod memory;
+#[cfg(unix)]
+mod pipe;
+mod record;
+mod ws;
+
+/// A bidirectional channel to a browser: commands are sent into the sink and
+/// responses and events are read from the stream.
+///
+/// This is implemented for every type that satisfies the bounds.
+pub trait Transport<T: EventMessage>:
+    Sink<MethodCall, Error = CdpError> + Stream<Item = Result<Message<T>>> + Send + Unpin
+{
+}
+
+impl<T, S> Transport<T> for S
+where
+    T: EventMessage,
+    S: Sink<MethodCall, Error = CdpError> + Stream<Item = Result<Message<T>>> + Send + Unpin,
+{
+}
+
+/// Counts the bytes a transport exchanged with the browser
+#[derive(Debug, Default)]
+pub struct TrafficCounter {
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl TrafficCounter {
+    /// Bytes sent to the browser
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// Bytes received from the browser
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    pub fn add_sent(&self, bytes: usize) {
+        self.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_received(&self, bytes: usize) {
+        self.received.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
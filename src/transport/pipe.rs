@@ -0,0 +1,350 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::marker::PhantomData;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{ready, Context, Poll};
+use std::thread;
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use futures::{Sink, Stream, StreamExt};
+
+use chromiumoxide_types::{EventMessage, Message, MethodCall};
+
+use crate::error::{CdpError, Result};
+use crate::transport::TrafficCounter;
+
+/// The file descriptor the browser reads commands from when launched with
+/// `--remote-debugging-pipe`
+const BROWSER_READ_FD: RawFd = 3;
+/// The file descriptor the browser writes responses and events to
+const BROWSER_WRITE_FD: RawFd = 4;
+
+/// Exchanges NUL-delimited JSON messages with a browser that was launched
+/// with `--remote-debugging-pipe`.
+///
+/// The blocking pipe I/O is done on two dedicated threads, so this works
+/// independently of the async runtime.
+#[derive(Debug)]
+pub struct PipeTransport<T> {
+    /// Messages to write to the browser
+    writer: mpsc::Sender<Vec<u8>>,
+    /// Messages read from the browser
+    reader: UnboundedReceiver<io::Result<Vec<u8>>>,
+    traffic: Arc<TrafficCounter>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PipeTransport<T> {
+    /// Creates a new transport that writes commands to `writer` and reads the
+    /// browser's messages from `reader`.
+    pub fn new(
+        mut writer: impl Write + Send + 'static,
+        reader: impl Read + Send + 'static,
+    ) -> Self {
+        let (write_tx, write_rx) = mpsc::channel::<Vec<u8>>();
+        thread::spawn(move || {
+            for mut msg in write_rx {
+                msg.push(0);
+                if let Err(err) = writer.write_all(&msg).and_then(|_| writer.flush()) {
+                    tracing::error!("Failed to write to the browser pipe {}", err);
+                    break;
+                }
+            }
+        });
+
+        let (read_tx, read_rx) = unbounded();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop {
+                let mut buf = Vec::new();
+                match reader.read_until(0, &mut buf) {
+                    // pipe closed
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if buf.last() == Some(&0) {
+                            buf.pop();
+                        }
+                        if read_tx.unbounded_send(Ok(buf)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        let _ = read_tx.unbounded_send(Err(err));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            writer: write_tx,
+            reader: read_rx,
+            traffic: Default::default(),
+            _marker: Default::default(),
+        }
+    }
+
+    /// The bytes exchanged over the pipes
+    pub fn traffic(&self) -> Arc<TrafficCounter> {
+        Arc::clone(&self.traffic)
+    }
+}
+
+impl<T> Sink<MethodCall> for PipeTransport<T> {
+    type Error = CdpError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, call: MethodCall) -> Result<()> {
+        let msg = serde_json::to_vec(&call)?;
+        self.traffic.add_sent(msg.len());
+        self.writer
+            .send(msg)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe).into())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: EventMessage> Stream for PipeTransport<T> {
+    type Item = Result<Message<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.reader.poll_next_unpin(cx)) {
+            Some(Ok(msg)) => {
+                self.traffic.add_received(msg.len());
+                match serde_json::from_slice::<Message<T>>(&msg) {
+                    Ok(msg) => Poll::Ready(Some(Ok(msg))),
+                    Err(err) => {
+                        tracing::error!("Failed to deserialize pipe response {}", err);
+                        Poll::Ready(Some(Err(err.into())))
+                    }
+                }
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+/// The two pipes to talk to a browser over, as seen by the browser process
+#[derive(Debug)]
+pub(crate) struct BrowserPipes {
+    /// The end the browser reads commands from
+    browser_read: OwnedFd,
+    /// The end the browser writes messages to
+    browser_write: OwnedFd,
+    /// Our end to write commands to
+    write: File,
+    /// Our end to read messages from
+    read: File,
+}
+
+impl BrowserPipes {
+    pub(crate) fn new() -> io::Result<Self> {
+        let (browser_read, write) = pipe()?;
+        let (read, browser_write) = pipe()?;
+        Ok(Self {
+            browser_read,
+            browser_write,
+            write: write.into(),
+            read: read.into(),
+        })
+    }
+
+    /// Makes the browser's ends of the pipes available as fd 3 and 4 in the
+    /// process spawned by `cmd`.
+    pub(crate) fn attach(&self, cmd: &mut std::process::Command) {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::process::CommandExt;
+
+        let browser_read = self.browser_read.as_raw_fd();
+        let browser_write = self.browser_write.as_raw_fd();
+        // SAFETY: only async-signal-safe libc calls are made in the child
+        unsafe {
+            cmd.pre_exec(move || {
+                // move the fds out of the way first, in case one of them
+                // already is 3 or 4
+                let read = libc::fcntl(browser_read, libc::F_DUPFD_CLOEXEC, 5);
+                let write = libc::fcntl(browser_write, libc::F_DUPFD_CLOEXEC, 5);
+                if read < 0
+                    || write < 0
+                    || libc::dup2(read, BROWSER_READ_FD) < 0
+                    || libc::dup2(write, BROWSER_WRITE_FD) < 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Closes the browser's ends and returns the transport over our ends.
+    pub(crate) fn into_transport<T>(self) -> PipeTransport<T> {
+        PipeTransport::new(self.write, self.read)
+    }
+}
+
+/// Serializes spawning processes with creating pipes on platforms without
+/// `pipe2`, where the fds are briefly inheritable.
+///
+/// A process spawned in between, e.g. by a `BrowserPool`, would keep the
+/// pipes open, so the other end never sees EOF.
+static SPAWN_LOCK: Mutex<()> = Mutex::new(());
+
+/// Held while spawning a process, see [`SPAWN_LOCK`]
+pub(crate) fn spawn_lock() -> MutexGuard<'static, ()> {
+    SPAWN_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Creates a new pipe, returns the read and the write end
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    cfg_if::cfg_if! {
+        if #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "illumos",
+        ))] {
+            // SAFETY: `fds` is valid for two fds
+            if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        } else {
+            let _spawning = spawn_lock();
+            // SAFETY: `fds` is valid for two fds
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            for fd in fds {
+                // SAFETY: `fd` is a valid, open fd
+                if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+                    let err = io::Error::last_os_error();
+                    // SAFETY: the fds were just created and are owned by nobody else
+                    unsafe {
+                        libc::close(fds[0]);
+                        libc::close(fds[1]);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+    // SAFETY: the fds were just created and are owned by nobody else
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    Ok((read, write))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::SinkExt;
+
+    use chromiumoxide_cdp::cdp::browser_protocol::browser::GetVersionParams;
+    use chromiumoxide_cdp::cdp::CdpEventMessage;
+    use chromiumoxide_types::CallId;
+
+    use super::*;
+
+    /// A transport over pipes, with the browser's ends to drive it
+    fn transport_pair() -> (PipeTransport<CdpEventMessage>, File, File) {
+        let pipes = BrowserPipes::new().unwrap();
+        let browser_read = File::from(pipes.browser_read.try_clone().unwrap());
+        let browser_write = File::from(pipes.browser_write.try_clone().unwrap());
+        (pipes.into_transport(), browser_read, browser_write)
+    }
+
+    #[async_std::test]
+    async fn reads_messages_split_across_reads() {
+        let (mut transport, _browser_read, mut browser_write) = transport_pair();
+
+        thread::spawn(move || {
+            let first = br#"{"id":1,"result":{}}"#;
+            let second = br#"{"id":2,"result":{"a":"b"}}"#;
+            browser_write.write_all(&first[..7]).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            // the rest of the first message and the whole second one at once
+            let mut rest = first[7..].to_vec();
+            rest.push(0);
+            rest.extend_from_slice(second);
+            rest.push(0);
+            browser_write.write_all(&rest).unwrap();
+        });
+
+        for (id, result) in [
+            (1, serde_json::json!({})),
+            (2, serde_json::json!({"a": "b"})),
+        ] {
+            match transport.next().await {
+                Some(Ok(Message::Response(resp))) => {
+                    assert_eq!(resp.id, CallId::new(id));
+                    assert_eq!(resp.result, Some(result));
+                }
+                msg => panic!("{msg:?}"),
+            }
+        }
+        // the writing end was dropped
+        assert!(transport.next().await.is_none());
+        assert_eq!(transport.traffic().received(), 47);
+    }
+
+    #[async_std::test]
+    async fn reports_invalid_messages() {
+        let (mut transport, _browser_read, mut browser_write) = transport_pair();
+        browser_write
+            .write_all(b"not json\0{\"id\":1,\"result\":{}}\0")
+            .unwrap();
+
+        assert!(matches!(
+            transport.next().await,
+            Some(Err(CdpError::Serde(_)))
+        ));
+        assert!(matches!(
+            transport.next().await,
+            Some(Ok(Message::Response(_)))
+        ));
+    }
+
+    #[async_std::test]
+    async fn writes_nul_delimited_messages() {
+        let (mut transport, browser_read, _browser_write) = transport_pair();
+        for id in 0..2 {
+            transport
+                .send(MethodCall {
+                    id: CallId::new(id),
+                    method: GetVersionParams::IDENTIFIER.into(),
+                    session_id: None,
+                    params: serde_json::json!({}),
+                })
+                .await
+                .unwrap();
+        }
+        drop(transport);
+
+        let mut browser_read = BufReader::new(browser_read);
+        for id in 0..2 {
+            let mut buf = Vec::new();
+            browser_read.read_until(0, &mut buf).unwrap();
+            assert_eq!(buf.pop(), Some(0));
+            let call: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+            assert_eq!(call["id"], id);
+            assert_eq!(call["method"], GetVersionParams::IDENTIFIER);
+        }
+    }
+}
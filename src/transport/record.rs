@@ -0,0 +1,416 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::{Sink, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use chromiumoxide_cdp::cdp::CdpEventMessage;
+use chromiumoxide_types::{CallId, Message, MethodCall};
+
+use crate::error::{CdpError, Result};
+
+/// Whether a recorded message was sent to or received from the browser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// A `MethodCall` sent to the browser
+    Sent,
+    /// A response or an event received from the browser
+    Received,
+}
+
+/// A single line of a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    /// Milliseconds since the unix epoch
+    pub timestamp: f64,
+    pub direction: Direction,
+    /// The message as json. Received messages are serialized again after
+    /// they were parsed, so fields the protocol definitions don't know are
+    /// missing. `null` if the received message could not be parsed.
+    pub message: Value,
+    /// Why the received message could not be parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// An event type that can be written to a recording
+pub trait RecordEvent {
+    /// The json of this event in the format the browser sends it
+    fn to_json(&self) -> serde_json::Result<Value>;
+}
+
+impl RecordEvent for CdpEventMessage {
+    fn to_json(&self) -> serde_json::Result<Value> {
+        let mut json = serde_json::json!({
+            "method": self.method,
+            "params": self.params.clone().into_json()?,
+        });
+        if let Some(ref session_id) = self.session_id {
+            json["sessionId"] = session_id.clone().into();
+        }
+        Ok(json)
+    }
+}
+
+/// Writes every message a [`Connection`](crate::conn::Connection) exchanges
+/// with the browser as JSON lines.
+///
+/// The messages are written on a separate thread so recording never blocks
+/// the `Handler`. Clones write into the same recording.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    tx: mpsc::Sender<RecordedMessage>,
+}
+
+impl Recorder {
+    /// Records into a new file at `path`, truncating an existing one
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+
+    /// Records into `writer`
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel::<RecordedMessage>();
+        thread::spawn(move || {
+            let mut writer = BufWriter::new(writer);
+            while let Ok(msg) = rx.recv() {
+                let mut next = Some(msg);
+                // write everything that is queued before flushing
+                while let Some(msg) = next {
+                    if let Err(err) = serde_json::to_writer(&mut writer, &msg)
+                        .map_err(io::Error::from)
+                        .and_then(|_| writer.write_all(b"\n"))
+                    {
+                        tracing::error!("Failed to write the recording: {:?}", err);
+                        return;
+                    }
+                    next = rx.try_recv().ok();
+                }
+                if let Err(err) = writer.flush() {
+                    tracing::error!("Failed to write the recording: {:?}", err);
+                    return;
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    fn record(&self, direction: Direction, message: Value, error: Option<String>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.)
+            .unwrap_or_default();
+        let _ = self.tx.send(RecordedMessage {
+            timestamp,
+            direction,
+            message,
+            error,
+        });
+    }
+
+    pub(crate) fn record_sent(&self, call: &MethodCall) {
+        match serde_json::to_value(call) {
+            Ok(json) => self.record(Direction::Sent, json, None),
+            Err(err) => tracing::warn!("Failed to record {:?}: {:?}", call.method, err),
+        }
+    }
+
+    pub(crate) fn record_received<T: RecordEvent>(&self, msg: &Message<T>) {
+        let json = match msg {
+            Message::Response(resp) => {
+                let mut json = serde_json::json!({ "id": resp.id });
+                if let Some(ref result) = resp.result {
+                    json["result"] = result.clone();
+                }
+                if let Some(ref error) = resp.error {
+                    json["error"] = serde_json::json!(error);
+                }
+                json
+            }
+            Message::Event(ev) => match ev.to_json() {
+                Ok(json) => json,
+                Err(err) => {
+                    tracing::warn!("Failed to record event: {:?}", err);
+                    return;
+                }
+            },
+        };
+        self.record(Direction::Received, json, None);
+    }
+
+    /// Records a received message that could not be parsed
+    pub(crate) fn record_invalid(&self, err: &serde_json::Error) {
+        self.record(Direction::Received, Value::Null, Some(err.to_string()));
+    }
+}
+
+/// Reads all messages of a recording
+pub fn read_recording(path: impl AsRef<Path>) -> io::Result<Vec<RecordedMessage>> {
+    let mut messages = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        messages.push(serde_json::from_str(&line)?);
+    }
+    Ok(messages)
+}
+
+/// A transport that plays back a recording made with a [`Recorder`].
+///
+/// Every received message is delivered only after as many commands were sent
+/// as had been sent before it was recorded, so the `Handler` sees the same
+/// interleaving of commands, responses and events as the original session.
+/// The ids of the responses are mapped to the ids of the replayed commands in
+/// the order they are sent. Messages that could not be parsed when they were
+/// recorded fail to parse again. The stream ends once the recording is
+/// exhausted.
+///
+/// This reproduces the behaviour of the `Handler`, its `FrameManager` and
+/// `NetworkManager` for a session offline:
+///
+/// ```no_run
+/// # use chromiumoxide::{Browser, handler::HandlerConfig};
+/// # use chromiumoxide::transport::ReplayTransport;
+/// # use chromiumoxide_cdp::cdp::CdpEventMessage;
+/// # async fn demo() -> chromiumoxide::error::Result<()> {
+/// let transport = ReplayTransport::<CdpEventMessage>::open("session.jsonl")?;
+/// let (browser, handler) =
+///     Browser::connect_with_transport(transport, HandlerConfig::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReplayTransport<T> {
+    /// The received messages and the number of commands sent before each
+    incoming: VecDeque<(usize, Value)>,
+    /// The ids of the recorded commands in the order they were sent
+    recorded_ids: Vec<CallId>,
+    /// Maps the recorded ids to the ids of the replayed commands
+    ids: HashMap<CallId, CallId>,
+    /// Number of commands sent so far
+    sent: usize,
+    waker: Option<Waker>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ReplayTransport<T> {
+    /// Replays the recording at `path`
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(read_recording(path)?))
+    }
+
+    /// Replays the `messages` in order
+    pub fn new(messages: impl IntoIterator<Item = RecordedMessage>) -> Self {
+        let mut incoming = VecDeque::new();
+        let mut recorded_ids = Vec::new();
+        for msg in messages {
+            match msg.direction {
+                Direction::Sent => {
+                    if let Some(id) = msg.message.get("id").and_then(Value::as_u64) {
+                        recorded_ids.push(CallId::new(id as usize));
+                    }
+                }
+                Direction::Received => incoming.push_back((recorded_ids.len(), msg.message)),
+            }
+        }
+        Self {
+            incoming,
+            recorded_ids,
+            ids: Default::default(),
+            sent: 0,
+            waker: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Whether all recorded messages were delivered
+    pub fn is_finished(&self) -> bool {
+        self.incoming.is_empty()
+    }
+}
+
+impl<T> Sink<MethodCall> for ReplayTransport<T> {
+    type Error = CdpError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, call: MethodCall) -> Result<()> {
+        let this = self.get_mut();
+        if let Some(recorded) = this.recorded_ids.get(this.sent) {
+            this.ids.insert(*recorded, call.id);
+        } else {
+            tracing::debug!("Replay: {} was not recorded", call.method);
+        }
+        this.sent += 1;
+        if let Some(waker) = this.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Stream for ReplayTransport<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    type Item = Result<Message<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match this.incoming.front() {
+            None => Poll::Ready(None),
+            Some((sent, _)) if *sent > this.sent => {
+                this.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            Some(_) => {
+                let (_, mut json) = this.incoming.pop_front().unwrap();
+                if let Some(id) = json.get("id").and_then(Value::as_u64) {
+                    if let Some(id) = this.ids.get(&CallId::new(id as usize)) {
+                        json["id"] = serde_json::json!(id);
+                    }
+                }
+                Poll::Ready(Some(serde_json::from_value(json).map_err(Into::into)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    use chromiumoxide_cdp::cdp::browser_protocol::browser::GetVersionParams;
+    use chromiumoxide_types::Response;
+
+    use super::*;
+    use crate::conn::Connection;
+    use crate::handler::HandlerConfig;
+    use crate::transport::memory_pair;
+    use crate::Browser;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_std::test]
+    async fn replays_recorded_session() {
+        let buf = SharedBuf::default();
+        let (transport, mut peer) = memory_pair::<CdpEventMessage>();
+        let config = HandlerConfig {
+            recorder: Some(Recorder::new(buf.clone())),
+            ..Default::default()
+        };
+        let (browser, mut handler) = Browser::connect_with_transport(transport, config)
+            .await
+            .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+        async_std::task::spawn(async move {
+            while let Some(call) = peer.next().await {
+                let result = if call.method == GetVersionParams::IDENTIFIER {
+                    serde_json::json!({
+                        "protocolVersion": "1.3",
+                        "product": "Recorded/1.0",
+                        "revision": "1",
+                        "userAgent": "recorded",
+                        "jsVersion": "1"
+                    })
+                } else {
+                    serde_json::json!({})
+                };
+                peer.send(Message::Response(Response {
+                    id: call.id,
+                    result: Some(result),
+                    error: None,
+                }));
+            }
+        });
+        assert_eq!(browser.version().await.unwrap().product, "Recorded/1.0");
+
+        // the recorder writes on its own thread
+        let messages = loop {
+            let messages: Vec<RecordedMessage> = String::from_utf8(buf.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            if messages
+                .iter()
+                .filter(|m| m.direction == Direction::Received)
+                .count()
+                == 2
+            {
+                break messages;
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(messages[0].direction, Direction::Sent);
+
+        let transport = ReplayTransport::<CdpEventMessage>::new(messages);
+        let (browser, mut handler) =
+            Browser::connect_with_transport(transport, HandlerConfig::default())
+                .await
+                .unwrap();
+        async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+        assert_eq!(browser.version().await.unwrap().product, "Recorded/1.0");
+    }
+
+    #[async_std::test]
+    async fn records_invalid_messages() {
+        let buf = SharedBuf::default();
+        let (transport, peer) = memory_pair::<CdpEventMessage>();
+        let mut conn = Connection::new(transport).with_recorder(Recorder::new(buf.clone()));
+
+        let err = serde_json::from_str::<Value>("{").unwrap_err();
+        peer.send_error(CdpError::Serde(err));
+        assert!(matches!(conn.next().await, Some(Err(CdpError::Serde(_)))));
+
+        let message = loop {
+            let recorded = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+            if let Some(line) = recorded.lines().next() {
+                break serde_json::from_str::<RecordedMessage>(line).unwrap();
+            }
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(message.direction, Direction::Received);
+        assert_eq!(message.message, Value::Null);
+        assert!(message.error.is_some());
+
+        let mut replay = ReplayTransport::<CdpEventMessage>::new([message]);
+        assert!(matches!(replay.next().await, Some(Err(CdpError::Serde(_)))));
+    }
+}
@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use async_tungstenite::tungstenite::client::IntoClientRequest;
+use async_tungstenite::tungstenite::http::HeaderMap;
+use async_tungstenite::{tungstenite::protocol::WebSocketConfig, WebSocketStream};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+
+use chromiumoxide_types::{EventMessage, Message, MethodCall};
+
+use crate::error::{CdpError, Result};
+use crate::transport::TrafficCounter;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "async-std-runtime")] {
+       use async_tungstenite::async_std::ConnectStream;
+    } else if #[cfg(feature = "tokio-runtime")] {
+        use async_tungstenite::tokio::ConnectStream;
+    }
+}
+
+/// Exchanges the messages over the debug websocket of the browser
+#[derive(Debug)]
+pub struct WsTransport<T> {
+    /// The websocket of the chromium instance
+    ws: WebSocketStream<ConnectStream>,
+    traffic: Arc<TrafficCounter>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> WsTransport<T> {
+    /// Connects to the websocket at `debug_ws_url`
+    pub async fn connect(debug_ws_url: impl AsRef<str>) -> Result<Self> {
+        Self::connect_with_headers(debug_ws_url, HeaderMap::new()).await
+    }
+
+    /// Connects to the websocket at `debug_ws_url` and sends `headers` with
+    /// the handshake, e.g. to authenticate with a proxy in front of the
+    /// browser
+    pub async fn connect_with_headers(
+        debug_ws_url: impl AsRef<str>,
+        headers: HeaderMap,
+    ) -> Result<Self> {
+        let mut request = debug_ws_url.as_ref().into_client_request()?;
+        request.headers_mut().extend(headers);
+        let config = WebSocketConfig {
+            max_message_size: None,
+            max_frame_size: None,
+            ..Default::default()
+        };
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "async-std-runtime")] {
+               let (ws, _) = async_tungstenite::async_std::connect_async_with_config(request, Some(config)).await?;
+            } else if #[cfg(feature = "tokio-runtime")] {
+                 let (ws, _) = async_tungstenite::tokio::connect_async_with_config(request, Some(config)).await?;
+            }
+        }
+
+        Ok(Self {
+            ws,
+            traffic: Default::default(),
+            _marker: Default::default(),
+        })
+    }
+
+    /// The bytes exchanged over this websocket
+    pub fn traffic(&self) -> Arc<TrafficCounter> {
+        Arc::clone(&self.traffic)
+    }
+}
+
+impl<T> Sink<MethodCall> for WsTransport<T> {
+    type Error = CdpError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.ws.poll_ready_unpin(cx).map_err(Into::into)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, call: MethodCall) -> Result<()> {
+        let msg = serde_json::to_string(&call)?;
+        self.traffic.add_sent(msg.len());
+        self.ws.start_send_unpin(msg.into())?;
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.ws.poll_flush_unpin(cx).map_err(Into::into)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.ws.poll_close_unpin(cx).map_err(Into::into)
+    }
+}
+
+impl<T: EventMessage> Stream for WsTransport<T> {
+    type Item = Result<Message<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.ws.poll_next_unpin(cx)) {
+            Some(Ok(msg)) => {
+                let data = msg.into_data();
+                self.traffic.add_received(data.len());
+                match serde_json::from_slice::<Message<T>>(&data) {
+                    Ok(msg) => Poll::Ready(Some(Ok(msg))),
+                    Err(err) => {
+                        tracing::error!("Failed to deserialize WS response {}", err);
+                        Poll::Ready(Some(Err(err.into())))
+                    }
+                }
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(CdpError::Ws(err)))),
+            // ws connection closed
+            None => Poll::Ready(None),
+        }
+    }
+}
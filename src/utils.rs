@@ -29,6 +29,38 @@ pub(crate) async fn canonicalize<P: AsRef<Path> + Unpin>(path: P) -> std::io::Re
     Ok(dunce::simplified(&path).to_path_buf())
 }
 
+/// Spawns a background task on the configured runtime
+pub(crate) fn spawn<F>(fut: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "async-std-runtime")] {
+            async_std::task::spawn(fut);
+        } else if #[cfg(feature = "tokio-runtime")] {
+            tokio::spawn(fut);
+        }
+    }
+}
+
+/// Spawns a background task on the configured runtime if one is available,
+/// returns `false` if `fut` was dropped instead
+pub(crate) fn try_spawn<F>(fut: F) -> bool
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "async-std-runtime")] {
+            async_std::task::spawn(fut);
+            true
+        } else if #[cfg(feature = "tokio-runtime")] {
+            tokio::runtime::Handle::try_current()
+                .map(|handle| drop(handle.spawn(fut)))
+                .is_ok()
+        }
+    }
+}
+
 pub(crate) mod base64 {
     use base64::engine::general_purpose::STANDARD;
     use base64::{DecodeError, Engine};
@@ -37,6 +69,11 @@ pub(crate) mod base64 {
     pub fn decode<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, DecodeError> {
         STANDARD.decode(input)
     }
+
+    /// Encode base64 using the standard alphabet and padding
+    pub fn encode<T: AsRef<[u8]>>(input: T) -> String {
+        STANDARD.encode(input)
+    }
 }
 
 /// Creates a javascript function string as `(<function>)("<param 1>", "<param